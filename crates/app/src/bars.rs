@@ -0,0 +1,321 @@
+use std::collections::HashSet;
+
+use anyhow::{Result, bail};
+use futures::StreamExt;
+use futures_signals::signal::SignalExt;
+use gpui::{App, DisplayId, Global, WindowBackgroundAppearance, WindowHandle};
+use services::hyprland::Monitor;
+use tracing::{error, info, warn};
+use ui::prelude::*;
+
+use crate::clients::Clients;
+use crate::config::{BarConfig, BarPosition};
+use crate::status_bar::StatusBar;
+
+struct BarWindow {
+    /// `None` when the compositor can't tell us about its monitors.
+    monitor: Option<Monitor>,
+    handle: WindowHandle<StatusBar>,
+}
+
+/// Owns one bar window per output and keeps them in sync with the config and the
+/// connected monitors.
+pub struct Bars {
+    config: BarConfig,
+    /// Monitors reported by Hyprland, `None` until they are known or if Hyprland isn't
+    /// reachable, in which case a single bar is shown on the default output.
+    monitors: Option<Vec<Monitor>>,
+    /// Outputs whose bar was hidden on request, `None` standing for the single bar shown
+    /// when the monitors are unknown.
+    hidden: HashSet<Option<String>>,
+    windows: Vec<BarWindow>,
+}
+
+impl Global for Bars {}
+
+impl Bars {
+    pub fn init(config: BarConfig, cx: &mut App) {
+        cx.set_global(Self {
+            config,
+            monitors: None,
+            hidden: HashSet::new(),
+            windows: Vec::new(),
+        });
+
+        let hyprland = Clients::hyprland(cx);
+        cx.spawn(async move |cx: &mut AsyncApp| {
+            let Some(subscriber) = hyprland.await else {
+                warn!("monitors unknown, showing a single bar");
+                cx.update(Self::sync).ok();
+                return;
+            };
+
+            let mut monitors = subscriber
+                .subscribe()
+                .map(|data| data.monitors)
+                .dedupe_cloned()
+                .to_stream();
+            while let Some(monitors) = monitors.next().await {
+                info!(
+                    "monitors changed: {:?}",
+                    monitors.iter().map(|m| &m.name).collect::<Vec<_>>()
+                );
+                let updated = cx.update(|cx| {
+                    cx.global_mut::<Self>().monitors = Some(monitors);
+                    Self::sync(cx);
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
+    /// Applies a new config, only reopening the windows when their layer shell settings
+    /// changed, otherwise just the modified widgets are rebuilt.
+    pub fn reload(config: BarConfig, cx: &mut App) {
+        let bars = cx.global_mut::<Self>();
+        let same_window = bars.config.same_window(&config);
+        bars.config = config.clone();
+        let mut windows = std::mem::take(&mut bars.windows);
+
+        windows.retain(|window| {
+            if same_window {
+                window
+                    .handle
+                    .update(cx, |bar, _, cx| bar.apply(&config, cx))
+                    .is_ok()
+            } else {
+                close_window(window.handle, cx);
+                false
+            }
+        });
+
+        cx.global_mut::<Self>().windows = windows;
+        Self::sync(cx);
+    }
+
+    pub fn config(cx: &App) -> &BarConfig {
+        &cx.global::<Self>().config
+    }
+
+    /// The outputs that get a bar according to the config, and whether it is shown.
+    pub fn outputs(cx: &App) -> Vec<(Option<String>, bool)> {
+        let bars = cx.global::<Self>();
+        bars.configured_monitors()
+            .into_iter()
+            .map(|monitor| {
+                let output = monitor.map(|monitor| monitor.name);
+                let visible = !bars.hidden.contains(&output);
+                (output, visible)
+            })
+            .collect()
+    }
+
+    /// Shows or hides the bar on `output`, or on all outputs.
+    pub fn set_visible(output: Option<&str>, visible: bool, cx: &mut App) -> Result<()> {
+        let outputs: Vec<_> = Self::outputs(cx)
+            .into_iter()
+            .map(|(output, _)| output)
+            .collect();
+        let targets = match output {
+            Some(name) => {
+                let output = Some(name.to_string());
+                if !outputs.contains(&output) {
+                    bail!("no bar on output `{name}`");
+                }
+                vec![output]
+            }
+            None => outputs,
+        };
+
+        let hidden = &mut cx.global_mut::<Self>().hidden;
+        for output in targets {
+            if visible {
+                hidden.remove(&output);
+            } else {
+                hidden.insert(output);
+            }
+        }
+        Self::sync(cx);
+
+        Ok(())
+    }
+
+    fn configured_monitors(&self) -> Vec<Option<Monitor>> {
+        match &self.monitors {
+            Some(monitors) => monitors
+                .iter()
+                .filter(|m| self.config.outputs.is_empty() || self.config.outputs.contains(&m.name))
+                .cloned()
+                .map(Some)
+                .collect(),
+            None => vec![None],
+        }
+    }
+
+    /// Opens bars on the outputs that should have one, and closes the others.
+    fn sync(cx: &mut App) {
+        let bars = cx.global_mut::<Self>();
+        let config = bars.config.clone();
+        let mut wanted = bars.configured_monitors();
+        wanted.retain(|monitor| {
+            let output = monitor.as_ref().map(|monitor| monitor.name.clone());
+            !bars.hidden.contains(&output)
+        });
+        let mut windows = std::mem::take(&mut bars.windows);
+
+        windows.retain(|window| {
+            let keep = wanted.contains(&window.monitor);
+            if !keep {
+                close_window(window.handle, cx);
+            }
+            keep
+        });
+
+        for monitor in wanted {
+            if windows.iter().any(|window| window.monitor == monitor) {
+                continue;
+            }
+            if let Some(handle) = open_window(&config, monitor.as_ref(), cx) {
+                windows.push(BarWindow { monitor, handle });
+            }
+        }
+
+        cx.global_mut::<Self>().windows = windows;
+    }
+}
+
+/// Where a drop-down opened from the point `x` of a bar goes, in a window covering the
+/// output of that bar.
+pub struct Dropdown {
+    position: BarPosition,
+    /// Distance to the bar edge of the window.
+    offset: Pixels,
+    /// Distance to the left edge of the window.
+    x: f32,
+    viewport: f32,
+    /// Whether the drop-down hangs to the left of `x`, past the middle of the output.
+    pub flip: bool,
+}
+
+impl Dropdown {
+    /// A window covering the output on `display`, to close the drop-down on clicks
+    /// outside of it.
+    pub fn window_options(display: Option<DisplayId>, namespace: &str, cx: &App) -> WindowOptions {
+        let size = display
+            .and_then(|id| cx.find_display(id))
+            .or_else(|| cx.primary_display())
+            .map(|display| display.bounds().size)
+            .unwrap_or(size(px(1440.), px(900.)));
+        let bounds = Bounds {
+            origin: point(px(0.), px(0.)),
+            size,
+        };
+        let layer_shell_settings = LayerShellSettings {
+            layer: Layer::Overlay,
+            anchor: Anchor::TOP | Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT,
+            keyboard_interactivity: KeyboardInteractivity::Exclusive,
+            namespace: namespace.to_string(),
+            ..Default::default()
+        };
+
+        WindowOptions {
+            window_bounds: Some(WindowBounds::Windowed(bounds)),
+            window_background: WindowBackgroundAppearance::Transparent,
+            kind: WindowKind::LayerShell(layer_shell_settings),
+            display_id: display,
+            ..Default::default()
+        }
+    }
+
+    pub fn new(x: Pixels, window: &Window, cx: &App) -> Self {
+        let bar = Bars::config(cx);
+        let viewport = window.viewport_size().width / px(1.);
+
+        // bars narrower than the output are centered on it
+        let x = x / px(1.)
+            + bar
+                .width
+                .map_or(0., |width| ((viewport - width) / 2.).max(0.));
+        // the window starts past the exclusive zone of the bar, which may not cover it all
+        let offset = px((bar.height - bar.exclusive_zone().unwrap_or(0.)).max(0.));
+
+        Self {
+            position: bar.position,
+            offset,
+            x,
+            viewport,
+            flip: x > viewport / 2.,
+        }
+    }
+
+    pub fn place(&self, panel: Div) -> Div {
+        let panel = match self.position {
+            BarPosition::Top => panel.absolute().top(self.offset),
+            BarPosition::Bottom => panel.absolute().bottom(self.offset),
+        };
+        if self.flip {
+            panel.right(px(self.viewport - self.x))
+        } else {
+            panel.left(px(self.x))
+        }
+    }
+}
+
+fn open_window(
+    config: &BarConfig,
+    monitor: Option<&Monitor>,
+    cx: &mut App,
+) -> Option<WindowHandle<StatusBar>> {
+    // gpui doesn't know output names, match the display by its position instead
+    let display = monitor.and_then(|monitor| {
+        let origin = point(px(monitor.x as f32), px(monitor.y as f32));
+        cx.displays()
+            .into_iter()
+            .find(|display| display.bounds().origin == origin)
+    });
+    let width = config
+        .width
+        .map(px)
+        .or(monitor.map(|monitor| px(monitor.logical_width())))
+        .or(cx
+            .primary_display()
+            .map(|display| display.bounds().size.width))
+        .unwrap_or(px(1440.));
+
+    let height = px(config.height);
+    let bounds = Bounds {
+        origin: point(px(0.), px(0.)),
+        size: size(width, height),
+    };
+    let layer_shell_settings = LayerShellSettings {
+        layer: config.layer.into(),
+        anchor: config.anchor(),
+        exclusive_zone: config.exclusive_zone().map(px),
+        keyboard_interactivity: KeyboardInteractivity::None,
+        namespace: config.namespace.clone(),
+        ..Default::default()
+    };
+
+    let opts = WindowOptions {
+        window_bounds: Some(WindowBounds::Windowed(bounds)),
+        kind: WindowKind::LayerShell(layer_shell_settings),
+        display_id: display.map(|display| display.id()),
+        ..Default::default()
+    };
+
+    let output = monitor.map(|monitor| monitor.name.clone());
+    cx.open_window(opts, |_, cx| {
+        cx.new(|cx| StatusBar::new(config, output, cx))
+    })
+    .inspect_err(|err| error!("failed to open bar window: {err:#}"))
+    .ok()
+}
+
+fn close_window(handle: WindowHandle<StatusBar>, cx: &mut App) {
+    handle
+        .update(cx, |_, window, _| window.remove_window())
+        .ok();
+}
@@ -0,0 +1,328 @@
+use futures_signals::signal::SignalExt;
+use futures_util::StreamExt;
+use gpui::{AnyElement, FocusHandle, Global, KeyDownEvent, WindowHandle};
+use services::bluetooth::{self, AgentRequest, Reply, RequestKind, service_name};
+use tracing::error;
+use ui::prelude::*;
+
+use crate::clients::{Clients, SharedClient};
+
+const WIDTH: f32 = 360.;
+const HEIGHT: f32 = 210.;
+/// The longest PIN code BlueZ accepts.
+const MAX_PIN_CODE: usize = 16;
+const PASSKEY_DIGITS: usize = 6;
+
+/// Shows what the Bluetooth agent asks the user while pairing, in a dialog taking the
+/// keyboard until it's answered.
+pub struct BluetoothAgentDialog {
+    handle: Option<WindowHandle<AgentDialog>>,
+}
+
+impl Global for BluetoothAgentDialog {}
+
+impl BluetoothAgentDialog {
+    /// Registers the pairing agent, whether or not a bar shows Bluetooth.
+    pub fn init(cx: &mut App) {
+        cx.set_global(Self { handle: None });
+        Clients::bluetooth(cx);
+    }
+
+    /// Follows the requests of the agent `subscriber` registers once it's connected, for
+    /// every client `Clients` creates.
+    pub fn follow(subscriber: SharedClient<bluetooth::Subscriber>, cx: &mut App) {
+        cx.spawn(async move |cx: &mut AsyncApp| {
+            if let Some(subscriber) = subscriber.await {
+                cx.update(|cx| Self::watch(subscriber, cx)).ok();
+            }
+        })
+        .detach();
+    }
+
+    /// Follows the requests of the agent of `subscriber`.
+    fn watch(subscriber: bluetooth::Subscriber, cx: &mut App) {
+        cx.spawn(async move |cx: &mut AsyncApp| {
+            let mut signal = subscriber.subscribe_request().to_stream();
+            while let Some(request) = signal.next().await {
+                let subscriber = subscriber.clone();
+                if cx.update(|cx| Self::show(subscriber, request, cx)).is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn show(subscriber: bluetooth::Subscriber, request: Option<AgentRequest>, cx: &mut App) {
+        let handle = cx.global::<Self>().handle;
+        match (request, handle) {
+            (Some(request), Some(handle)) => {
+                handle
+                    .update(cx, |dialog, _, cx| dialog.set_request(request, cx))
+                    .ok();
+            }
+            (Some(request), None) => Self::open(subscriber, request, cx),
+            (None, Some(handle)) => {
+                cx.global_mut::<Self>().handle = None;
+                handle
+                    .update(cx, |_, window, _| window.remove_window())
+                    .ok();
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn open(subscriber: bluetooth::Subscriber, request: AgentRequest, cx: &mut App) {
+        let bounds = Bounds {
+            origin: point(px(0.), px(0.)),
+            size: size(px(WIDTH), px(HEIGHT)),
+        };
+        // without an anchor the compositor centers the window
+        let layer_shell_settings = LayerShellSettings {
+            layer: Layer::Overlay,
+            keyboard_interactivity: KeyboardInteractivity::Exclusive,
+            namespace: "mgs-bluetooth-agent".to_string(),
+            ..Default::default()
+        };
+        let opts = WindowOptions {
+            window_bounds: Some(WindowBounds::Windowed(bounds)),
+            kind: WindowKind::LayerShell(layer_shell_settings),
+            ..Default::default()
+        };
+
+        let handle = cx.open_window(opts, |window, cx| {
+            let dialog = cx.new(|cx| AgentDialog::new(subscriber, request, cx));
+            window.focus(&dialog.read(cx).focus_handle);
+            dialog
+        });
+        match handle {
+            Ok(handle) => cx.global_mut::<Self>().handle = Some(handle),
+            Err(err) => error!("failed to open bluetooth agent dialog: {err:#}"),
+        }
+    }
+}
+
+pub struct AgentDialog {
+    subscriber: bluetooth::Subscriber,
+    request: AgentRequest,
+    /// The PIN code or passkey typed so far.
+    input: String,
+    focus_handle: FocusHandle,
+}
+
+impl AgentDialog {
+    fn new(
+        subscriber: bluetooth::Subscriber,
+        request: AgentRequest,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        Self {
+            subscriber,
+            request,
+            input: String::new(),
+            focus_handle: cx.focus_handle(),
+        }
+    }
+
+    fn set_request(&mut self, request: AgentRequest, cx: &mut Context<Self>) {
+        if self.request.id != request.id {
+            self.input.clear();
+        }
+        self.request = request;
+        cx.notify();
+    }
+
+    /// Answers the request, the dialog closes once the agent is done with it.
+    fn reply(&mut self, reply: Reply) {
+        self.subscriber.reply(self.request.id, reply);
+    }
+
+    fn submit(&mut self) {
+        let reply = match self.request.kind {
+            RequestKind::PinCode if self.input.is_empty() => return,
+            RequestKind::PinCode => Reply::PinCode(self.input.clone()),
+            RequestKind::Passkey => match self.input.parse() {
+                Ok(passkey) => Reply::Passkey(passkey),
+                Err(_) => return,
+            },
+            _ => Reply::Accept,
+        };
+        self.reply(reply);
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        let keystroke = &event.keystroke;
+        let max_len = match self.request.kind {
+            RequestKind::PinCode => MAX_PIN_CODE,
+            RequestKind::Passkey => PASSKEY_DIGITS,
+            _ => 0,
+        };
+
+        match keystroke.key.as_str() {
+            "escape" => self.reply(Reply::Reject),
+            "enter" => self.submit(),
+            "backspace" => {
+                self.input.pop();
+                cx.notify();
+            }
+            _ if keystroke.modifiers.control
+                || keystroke.modifiers.alt
+                || keystroke.modifiers.platform => {}
+            _ => {
+                let Some(text) = &keystroke.key_char else {
+                    return;
+                };
+                let allowed = self.request.kind != RequestKind::Passkey
+                    || text.chars().all(|c| c.is_ascii_digit());
+                if allowed && self.input.len() + text.len() <= max_len {
+                    self.input.push_str(text);
+                    cx.notify();
+                }
+            }
+        }
+    }
+
+    /// What the dialog asks, and the label of the button accepting it.
+    fn message(&self) -> (String, &'static str) {
+        let name = &self.request.name;
+        match &self.request.kind {
+            RequestKind::PinCode => (format!("Enter the PIN code of {name}."), "Pair"),
+            RequestKind::Passkey => (format!("Enter the passkey of {name}."), "Pair"),
+            RequestKind::DisplayPinCode(_) => (
+                format!("Type this PIN code on {name}, then press Enter on it."),
+                "Close",
+            ),
+            RequestKind::DisplayPasskey { .. } => (
+                format!("Type this passkey on {name}, then press Enter on it."),
+                "Close",
+            ),
+            RequestKind::Confirmation(_) => {
+                (format!("Check that {name} shows this passkey."), "Pair")
+            }
+            RequestKind::Authorization => (format!("{name} wants to pair."), "Allow"),
+            RequestKind::Service(uuid) => {
+                let service = service_name(uuid).unwrap_or(uuid.as_str());
+                (format!("{name} wants to use {service}."), "Allow")
+            }
+        }
+    }
+
+    /// The code to compare or to type on the device, the typed digits highlighted.
+    fn render_code(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        let theme = cx.theme().clone();
+        let (code, entered) = match &self.request.kind {
+            RequestKind::DisplayPinCode(pin_code) => (pin_code.clone(), 0),
+            RequestKind::DisplayPasskey { passkey, entered } => {
+                (format!("{passkey:06}"), *entered as usize)
+            }
+            RequestKind::Confirmation(passkey) => (format!("{passkey:06}"), 0),
+            _ => return None,
+        };
+
+        Some(
+            h_flex()
+                .justify_center()
+                .gap_1()
+                .text_size(px(28.))
+                .children(code.chars().enumerate().map(|(index, digit)| {
+                    let color = if index < entered {
+                        theme.palette.accent
+                    } else {
+                        theme.palette.text
+                    };
+                    div().text_color(color).child(digit.to_string())
+                }))
+                .into_any_element(),
+        )
+    }
+
+    fn render_input(&self, cx: &mut Context<Self>) -> Option<AnyElement> {
+        let theme = cx.theme().clone();
+        let placeholder = match self.request.kind {
+            RequestKind::PinCode => "PIN code",
+            RequestKind::Passkey => "Passkey",
+            _ => return None,
+        };
+
+        Some(
+            div()
+                .px_3()
+                .py_2()
+                .rounded(theme.radius)
+                .bg(theme.palette.surface)
+                .child(if self.input.is_empty() {
+                    div().text_color(theme.palette.subtext).child(placeholder)
+                } else {
+                    div().child(self.input.clone())
+                })
+                .into_any_element(),
+        )
+    }
+}
+
+impl Render for AgentDialog {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let (message, accept) = self.message();
+        let display = self.request.kind.is_display();
+
+        let button = |label: &'static str, primary: bool| {
+            div()
+                .px_3()
+                .py_1()
+                .rounded(theme.radius)
+                .text_sm()
+                .map(|this| {
+                    if primary {
+                        this.bg(theme.palette.accent)
+                            .text_color(theme.palette.background)
+                    } else {
+                        this.bg(theme.palette.surface)
+                            .hover(|this| this.bg(theme.palette.hover))
+                    }
+                })
+                .child(label)
+        };
+
+        v_flex()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .size_full()
+            .p_4()
+            .gap_3()
+            .bg(theme.palette.background)
+            .text_color(theme.palette.text)
+            .font_family(theme.font_family.clone())
+            .font_weight(theme.font_weight)
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(Icon::new("icons/bluetooth.svg".into()).size(18.))
+                    .child("Bluetooth pairing"),
+            )
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(theme.palette.subtext)
+                    .child(message),
+            )
+            .children(self.render_code(cx))
+            .children(self.render_input(cx))
+            .child(div().flex_1())
+            .child(
+                h_flex()
+                    .justify_end()
+                    .gap_2()
+                    .when(!display, |this| {
+                        this.child(button("Reject", false).on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(|this, _, _, _| this.reply(Reply::Reject)),
+                        ))
+                    })
+                    .child(button(accept, !display).on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _, _, _| this.submit()),
+                    )),
+            )
+    }
+}
@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+
+use futures_signals::signal::SignalExt;
+use futures_util::StreamExt;
+use gpui::{AnyElement, DisplayId, FocusHandle, Global, KeyDownEvent, WindowHandle};
+use services::bluetooth::{self, BluetoothData, Device};
+use tracing::{error, warn};
+use ui::prelude::*;
+
+use crate::bars::Dropdown;
+
+const WIDTH: f32 = 320.;
+const MAX_HEIGHT: f32 = 420.;
+
+/// Opens and closes the Bluetooth popup, the devices of the adapter dropping down from
+/// the bar.
+pub struct BluetoothPopupWindow {
+    handle: Option<WindowHandle<BluetoothPopup>>,
+}
+
+impl Global for BluetoothPopupWindow {}
+
+impl BluetoothPopupWindow {
+    pub fn init(cx: &mut App) {
+        cx.set_global(Self { handle: None });
+    }
+
+    pub fn toggle(
+        subscriber: bluetooth::Subscriber,
+        display: Option<DisplayId>,
+        x: Pixels,
+        cx: &mut App,
+    ) {
+        if cx.global::<Self>().handle.is_some() {
+            Self::close(cx);
+        } else {
+            Self::open(subscriber, display, x, cx);
+        }
+    }
+
+    /// Shows the popup under the point `x` of the bar on `display`.
+    pub fn open(
+        subscriber: bluetooth::Subscriber,
+        display: Option<DisplayId>,
+        x: Pixels,
+        cx: &mut App,
+    ) {
+        Self::close(cx);
+
+        let opts = Dropdown::window_options(display, "mgs-bluetooth", cx);
+        let handle = cx.open_window(opts, |window, cx| {
+            let popup = cx.new(|cx| BluetoothPopup::new(subscriber, x, cx));
+            window.focus(&popup.read(cx).focus_handle);
+            popup
+        });
+        match handle {
+            Ok(handle) => cx.global_mut::<Self>().handle = Some(handle),
+            Err(err) => error!("failed to open bluetooth popup window: {err:#}"),
+        }
+    }
+
+    pub fn close(cx: &mut App) {
+        if let Some(handle) = cx.global::<Self>().handle {
+            handle
+                .update(cx, |popup, window, cx| popup.dismiss(window, cx))
+                .ok();
+        }
+    }
+}
+
+pub struct BluetoothPopup {
+    subscriber: bluetooth::Subscriber,
+    data: BluetoothData,
+    /// Devices with a request on the way, and what it does.
+    pending: HashMap<String, &'static str>,
+    /// Whether the popup started the discovery, which it then stops when dismissed.
+    scanning: bool,
+    /// Where the popup was opened, in bar coordinates.
+    x: Pixels,
+    focus_handle: FocusHandle,
+}
+
+impl BluetoothPopup {
+    fn new(subscriber: bluetooth::Subscriber, x: Pixels, cx: &mut Context<Self>) -> Self {
+        let signal = subscriber.subscribe();
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let mut signal = signal.to_stream();
+            while let Some(data) = signal.next().await {
+                let updated = this.update(cx, |this, cx| {
+                    this.data = data;
+                    cx.notify();
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        Self {
+            subscriber,
+            data: BluetoothData::default(),
+            pending: HashMap::new(),
+            scanning: false,
+            x,
+            focus_handle: cx.focus_handle(),
+        }
+    }
+
+    fn dismiss(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let discovering = self
+            .data
+            .adapter
+            .as_ref()
+            .is_some_and(|adapter| adapter.discovering);
+        if self.scanning && discovering {
+            let subscriber = self.subscriber.clone();
+            cx.spawn(async move |_, _| {
+                if let Err(err) = subscriber.stop_discovery().await {
+                    warn!("failed to stop looking for bluetooth devices: {err:#}");
+                }
+            })
+            .detach();
+        }
+
+        cx.global_mut::<BluetoothPopupWindow>().handle = None;
+        window.remove_window();
+    }
+
+    /// Runs `request`, marking `device` as busy with `label` until it's done.
+    fn request<F, Fut>(
+        &mut self,
+        device: Option<(String, &'static str)>,
+        request: F,
+        cx: &mut Context<Self>,
+    ) where
+        F: FnOnce(bluetooth::Subscriber) -> Fut + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + 'static,
+    {
+        let path = device.map(|(path, label)| {
+            self.pending.insert(path.clone(), label);
+            path
+        });
+        let subscriber = self.subscriber.clone();
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            if let Err(err) = request(subscriber).await {
+                warn!("bluetooth request failed: {err:#}");
+            }
+            if let Some(path) = path {
+                this.update(cx, |this, cx| {
+                    this.pending.remove(&path);
+                    cx.notify();
+                })
+                .ok();
+            }
+        })
+        .detach();
+        cx.notify();
+    }
+
+    fn toggle_power(&mut self, powered: bool, cx: &mut Context<Self>) {
+        self.request(
+            None,
+            move |subscriber| async move { subscriber.set_powered(!powered).await },
+            cx,
+        );
+    }
+
+    fn toggle_discovery(&mut self, discovering: bool, cx: &mut Context<Self>) {
+        self.scanning = !discovering;
+        self.request(
+            None,
+            move |subscriber| async move {
+                if discovering {
+                    subscriber.stop_discovery().await
+                } else {
+                    subscriber.start_discovery().await
+                }
+            },
+            cx,
+        );
+    }
+
+    /// Disconnects a connected device, connects a paired one, and pairs with a new one,
+    /// trusting it so it connects on its own next time.
+    fn activate(&mut self, device: &Device, cx: &mut Context<Self>) {
+        if self.pending.contains_key(&device.path) {
+            return;
+        }
+
+        let path = device.path.clone();
+        let (connected, paired) = (device.connected, device.paired);
+        let label = match (connected, paired) {
+            (true, _) => "Disconnecting…",
+            (false, true) => "Connecting…",
+            (false, false) => "Pairing…",
+        };
+        self.request(
+            Some((path.clone(), label)),
+            move |subscriber| async move {
+                if connected {
+                    return subscriber.disconnect(&path).await;
+                }
+                if !paired {
+                    subscriber.pair(&path).await?;
+                    subscriber.set_trusted(&path, true).await?;
+                }
+                subscriber.connect(&path).await
+            },
+            cx,
+        );
+    }
+
+    fn set_trusted(&mut self, device: &Device, cx: &mut Context<Self>) {
+        let path = device.path.clone();
+        let trusted = !device.trusted;
+        self.request(
+            None,
+            move |subscriber| async move { subscriber.set_trusted(&path, trusted).await },
+            cx,
+        );
+    }
+
+    fn forget(&mut self, device: &Device, cx: &mut Context<Self>) {
+        let path = device.path.clone();
+        self.request(
+            Some((path.clone(), "Forgetting…")),
+            move |subscriber| async move { subscriber.remove(&path).await },
+            cx,
+        );
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        if event.keystroke.key == "escape" {
+            self.dismiss(window, cx);
+        }
+    }
+
+    fn render_header(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let adapter = self.data.adapter.clone();
+        let powered = adapter.as_ref().is_some_and(|adapter| adapter.powered);
+        let discovering = adapter.as_ref().is_some_and(|adapter| adapter.discovering);
+
+        let button = |icon: &'static str, on: bool| {
+            let color = if on {
+                theme.palette.accent
+            } else {
+                theme.palette.text
+            };
+            div()
+                .p_1()
+                .rounded(theme.radius)
+                .hover(|this| this.bg(theme.palette.hover))
+                .child(Icon::new(icon.into()).size(16.).color(color))
+        };
+
+        h_flex()
+            .px_2()
+            .py_1()
+            .gap_2()
+            .child(v_flex().flex_1().min_w_0().child("Bluetooth").when_some(
+                adapter.as_ref(),
+                |this, adapter| {
+                    this.child(
+                        div()
+                            .text_xs()
+                            .text_color(theme.palette.subtext)
+                            .overflow_hidden()
+                            .whitespace_nowrap()
+                            .child(adapter.name.clone()),
+                    )
+                },
+            ))
+            .when(powered, |this| {
+                this.child(
+                    button("icons/bluetooth-searching.svg", discovering).on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _, _, cx| {
+                            cx.stop_propagation();
+                            this.toggle_discovery(discovering, cx);
+                        }),
+                    ),
+                )
+            })
+            .when(adapter.is_some(), |this| {
+                this.child(button("icons/power.svg", powered).on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _, _, cx| {
+                        cx.stop_propagation();
+                        this.toggle_power(powered, cx);
+                    }),
+                ))
+            })
+    }
+
+    fn render_section(
+        &self,
+        title: &'static str,
+        devices: Vec<&Device>,
+        empty: &'static str,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let theme = cx.theme().clone();
+
+        v_flex()
+            .gap_1()
+            .child(
+                div()
+                    .px_2()
+                    .text_xs()
+                    .text_color(theme.palette.subtext)
+                    .child(title),
+            )
+            .when(devices.is_empty(), |this| {
+                this.child(
+                    div()
+                        .px_2()
+                        .py_1()
+                        .text_sm()
+                        .text_color(theme.palette.subtext)
+                        .child(empty),
+                )
+            })
+            .children(
+                devices
+                    .into_iter()
+                    .map(|device| self.render_device(device, cx)),
+            )
+            .into_any_element()
+    }
+
+    fn render_device(&self, device: &Device, cx: &mut Context<Self>) -> AnyElement {
+        let theme = cx.theme().clone();
+        let status = match self.pending.get(&device.path) {
+            Some(label) => label.to_string(),
+            None if device.connected => "Connected".to_string(),
+            None if device.paired => "Not connected".to_string(),
+            None => device.address.clone(),
+        };
+        let (icon, color) = if device.connected {
+            ("icons/bluetooth-connected.svg", theme.palette.accent)
+        } else {
+            ("icons/bluetooth.svg", theme.palette.subtext)
+        };
+
+        let button = |icon: &'static str, color: Rgba| {
+            div()
+                .p_0p5()
+                .rounded(theme.radius)
+                .hover(|this| this.bg(theme.palette.hover))
+                .child(Icon::new(icon.into()).size(14.).color(color))
+        };
+        let trusted_color = if device.trusted {
+            theme.palette.accent
+        } else {
+            theme.palette.subtext
+        };
+        let (activated, trusted, forgotten) = (device.clone(), device.clone(), device.clone());
+
+        h_flex()
+            .px_2()
+            .py_1p5()
+            .gap_2()
+            .rounded(theme.radius)
+            .hover(|this| this.bg(theme.palette.hover))
+            .child(Icon::new(icon.into()).size(18.).color(color))
+            .child(
+                v_flex()
+                    .flex_1()
+                    .min_w_0()
+                    .child(
+                        div()
+                            .overflow_hidden()
+                            .whitespace_nowrap()
+                            .child(device.name.clone()),
+                    )
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(theme.palette.subtext)
+                            .child(status),
+                    ),
+            )
+            .when_some(device.battery, |this, battery| {
+                this.child(
+                    div()
+                        .text_sm()
+                        .text_color(theme.palette.subtext)
+                        .child(format!("{battery}%")),
+                )
+            })
+            .when(device.paired, |this| {
+                this.child(
+                    button("icons/shield-check.svg", trusted_color).on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _, _, cx| {
+                            cx.stop_propagation();
+                            this.set_trusted(&trusted, cx);
+                        }),
+                    ),
+                )
+                .child(button("icons/x.svg", theme.palette.subtext).on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _, _, cx| {
+                        cx.stop_propagation();
+                        this.forget(&forgotten, cx);
+                    }),
+                ))
+            })
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _, _, cx| {
+                    cx.stop_propagation();
+                    this.activate(&activated, cx);
+                }),
+            )
+            .into_any_element()
+    }
+}
+
+impl Render for BluetoothPopup {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let adapter = self.data.adapter.clone();
+
+        let placeholder = |text: &'static str| {
+            div()
+                .py_6()
+                .flex()
+                .justify_center()
+                .text_color(theme.palette.subtext)
+                .child(text)
+                .into_any_element()
+        };
+        let content = match adapter {
+            None => placeholder("No Bluetooth adapter"),
+            Some(adapter) if !adapter.powered => placeholder("Bluetooth is off"),
+            Some(adapter) => {
+                let (paired, available): (Vec<_>, Vec<_>) =
+                    self.data.devices.iter().partition(|device| device.paired);
+                let available_empty = if adapter.discovering {
+                    "Looking for devices…"
+                } else {
+                    "Scan to find new devices"
+                };
+                v_flex()
+                    .id("bluetooth-devices")
+                    .max_h(px(MAX_HEIGHT))
+                    .overflow_y_scroll()
+                    .gap_3()
+                    .child(self.render_section("Devices", paired, "No paired devices", cx))
+                    .child(self.render_section("Available", available, available_empty, cx))
+                    .into_any_element()
+            }
+        };
+
+        let panel = v_flex()
+            .w(px(WIDTH))
+            .p_2()
+            .gap_2()
+            .rounded(theme.radius)
+            .border_1()
+            .border_color(theme.palette.border)
+            .bg(theme.palette.background)
+            .occlude()
+            .child(self.render_header(cx))
+            .child(content);
+
+        div()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .size_full()
+            .text_color(theme.palette.text)
+            .font_family(theme.font_family.clone())
+            .font_weight(theme.font_weight)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _, window, cx| this.dismiss(window, cx)),
+            )
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(|this, _, window, cx| this.dismiss(window, cx)),
+            )
+            .child(Dropdown::new(self.x, window, cx).place(panel))
+    }
+}
@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use futures::FutureExt;
+use futures::future::Shared;
+use gpui::{App, Global, Task};
+use services::{
+    appearance, applications, audio, bluetooth, hyprland, mpris, network, notifications, tray,
+    upower,
+};
+use tracing::error;
+
+use crate::bluetooth_agent::BluetoothAgentDialog;
+use crate::network_agent::NetworkAgentDialog;
+
+pub type SharedClient<T> = Shared<Task<Option<T>>>;
+
+/// Service clients shared by all widgets.
+///
+/// Widgets are rebuilt whenever their config changes, keeping the clients here
+/// means that doesn't reconnect to D-Bus or to the audio server.
+#[derive(Default)]
+pub struct Clients {
+    appearance: Option<SharedClient<appearance::Subscriber>>,
+    applications: Option<SharedClient<applications::Subscriber>>,
+    audio: Option<Arc<audio::Client>>,
+    bluetooth: Option<SharedClient<bluetooth::Subscriber>>,
+    mpris: Option<SharedClient<mpris::Subscriber>>,
+    network: Option<SharedClient<network::Subscriber>>,
+    notifications: Option<SharedClient<notifications::Subscriber>>,
+    upower: Option<SharedClient<upower::Subscriber>>,
+    tray: Option<SharedClient<tray::Subscriber>>,
+    hyprland: Option<SharedClient<hyprland::Subscriber>>,
+}
+
+impl Global for Clients {}
+
+impl Clients {
+    pub fn init(cx: &mut App) {
+        cx.set_global(Self::default());
+    }
+
+    pub fn appearance(cx: &mut App) -> SharedClient<appearance::Subscriber> {
+        if let Some(appearance) = &cx.global::<Self>().appearance {
+            return appearance.clone();
+        }
+
+        let appearance = connect(
+            "the desktop portal",
+            |clients| &mut clients.appearance,
+            appearance::Subscriber::new,
+            cx,
+        );
+        cx.global_mut::<Self>().appearance = Some(appearance.clone());
+        appearance
+    }
+
+    pub fn applications(cx: &mut App) -> SharedClient<applications::Subscriber> {
+        if let Some(applications) = &cx.global::<Self>().applications {
+            return applications.clone();
+        }
+
+        let applications = connect(
+            "the applications index",
+            |clients| &mut clients.applications,
+            applications::Subscriber::new,
+            cx,
+        );
+        cx.global_mut::<Self>().applications = Some(applications.clone());
+        applications
+    }
+
+    pub fn audio(cx: &mut App) -> Arc<audio::Client> {
+        cx.global_mut::<Self>()
+            .audio
+            .get_or_insert_with(audio::create_client)
+            .clone()
+    }
+
+    pub fn bluetooth(cx: &mut App) -> SharedClient<bluetooth::Subscriber> {
+        if let Some(bluetooth) = &cx.global::<Self>().bluetooth {
+            return bluetooth.clone();
+        }
+
+        let bluetooth = connect(
+            "BlueZ",
+            |clients| &mut clients.bluetooth,
+            bluetooth::Subscriber::new,
+            cx,
+        );
+        cx.global_mut::<Self>().bluetooth = Some(bluetooth.clone());
+        BluetoothAgentDialog::follow(bluetooth.clone(), cx);
+        bluetooth
+    }
+
+    pub fn mpris(cx: &mut App) -> SharedClient<mpris::Subscriber> {
+        if let Some(mpris) = &cx.global::<Self>().mpris {
+            return mpris.clone();
+        }
+
+        let mpris = connect(
+            "the media players",
+            |clients| &mut clients.mpris,
+            mpris::Subscriber::new,
+            cx,
+        );
+        cx.global_mut::<Self>().mpris = Some(mpris.clone());
+        mpris
+    }
+
+    pub fn network(cx: &mut App) -> SharedClient<network::Subscriber> {
+        if let Some(network) = &cx.global::<Self>().network {
+            return network.clone();
+        }
+
+        let network = connect(
+            "NetworkManager",
+            |clients| &mut clients.network,
+            network::Subscriber::new,
+            cx,
+        );
+        cx.global_mut::<Self>().network = Some(network.clone());
+        NetworkAgentDialog::follow(network.clone(), cx);
+        network
+    }
+
+    pub fn notifications(cx: &mut App) -> SharedClient<notifications::Subscriber> {
+        if let Some(notifications) = &cx.global::<Self>().notifications {
+            return notifications.clone();
+        }
+
+        let notifications = connect(
+            "the notification daemon",
+            |clients| &mut clients.notifications,
+            notifications::Subscriber::new,
+            cx,
+        );
+        cx.global_mut::<Self>().notifications = Some(notifications.clone());
+        notifications
+    }
+
+    pub fn upower(cx: &mut App) -> SharedClient<upower::Subscriber> {
+        if let Some(upower) = &cx.global::<Self>().upower {
+            return upower.clone();
+        }
+
+        let upower = connect(
+            "UPower",
+            |clients| &mut clients.upower,
+            upower::Subscriber::new,
+            cx,
+        );
+        cx.global_mut::<Self>().upower = Some(upower.clone());
+        upower
+    }
+
+    pub fn tray(cx: &mut App) -> SharedClient<tray::Subscriber> {
+        if let Some(tray) = &cx.global::<Self>().tray {
+            return tray.clone();
+        }
+
+        let tray = connect(
+            "the system tray",
+            |clients| &mut clients.tray,
+            tray::Subscriber::new,
+            cx,
+        );
+        cx.global_mut::<Self>().tray = Some(tray.clone());
+        tray
+    }
+
+    pub fn hyprland(cx: &mut App) -> SharedClient<hyprland::Subscriber> {
+        if let Some(hyprland) = &cx.global::<Self>().hyprland {
+            return hyprland.clone();
+        }
+
+        let hyprland = connect(
+            "Hyprland",
+            |clients| &mut clients.hyprland,
+            hyprland::Subscriber::new,
+            cx,
+        );
+        cx.global_mut::<Self>().hyprland = Some(hyprland.clone());
+        hyprland
+    }
+}
+
+/// Creates the client on the foreground executor, which runs inside the tokio runtime
+/// the subscribers spawn their tasks on.
+///
+/// A failed connection is forgotten from `slot`, so the next widget asking for the client,
+/// e.g. after a config reload, tries again.
+fn connect<T, F, Fut>(
+    name: &'static str,
+    slot: fn(&mut Clients) -> &mut Option<SharedClient<T>>,
+    f: F,
+    cx: &mut App,
+) -> SharedClient<T>
+where
+    T: Clone + 'static,
+    F: FnOnce() -> Fut + 'static,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    cx.spawn(async move |cx| match f().await {
+        Ok(client) => Some(client),
+        Err(err) => {
+            error!("failed to connect to {name}: {err:#}");
+            cx.update(|cx| *slot(cx.global_mut::<Clients>()) = None)
+                .ok();
+            None
+        }
+    })
+    .shared()
+}
@@ -0,0 +1,1141 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use chrono::format::{Item, StrftimeItems};
+use futures::channel::mpsc::{self, UnboundedReceiver};
+use gpui::FontWeight;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use tracing::warn;
+use ui::prelude::{Anchor, Layer, Rgba, px, rgba};
+use ui::{Palette, Theme};
+
+const CONFIG_DIR: &str = "mgs";
+const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bar: BarConfig,
+    pub launcher: LauncherConfig,
+    pub notifications: NotificationsConfig,
+    pub theme: ThemeConfig,
+    /// Custom themes, by name.
+    pub themes: BTreeMap<String, ThemeDefinition>,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/mgs/config.toml`, falling back to `~/.config/mgs/config.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config_home.join(CONFIG_DIR).join(CONFIG_FILE))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+
+        Self::parse(&source).with_context(|| format!("invalid config file {}", path.display()))
+    }
+
+    pub fn parse(source: &str) -> Result<Self> {
+        let config: Self = toml::from_str(source)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Watches the config file, the receiver yields whenever it is written, created or removed.
+    ///
+    /// The parent directory is watched rather than the file itself, as editors often replace
+    /// the file on save, which a watch on the old inode wouldn't notice.
+    pub fn watch(path: &Path) -> Result<(RecommendedWatcher, UnboundedReceiver<()>)> {
+        let dir = path
+            .parent()
+            .context("config path has no parent directory")?;
+        let file_name = path.file_name().map(ToOwned::to_owned);
+
+        let (tx, rx) = mpsc::unbounded();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) if event.kind.is_access() => {}
+                Ok(event) => {
+                    if event
+                        .paths
+                        .iter()
+                        .any(|path| path.file_name() == file_name.as_deref())
+                    {
+                        tx.unbounded_send(()).ok();
+                    }
+                }
+                Err(err) => warn!("config watcher error: {err}"),
+            })?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("failed to watch {}", dir.display()))?;
+
+        Ok((watcher, rx))
+    }
+
+    /// The theme to use, `theme.dark` when the system prefers a dark appearance and
+    /// it is set, `theme.name` otherwise.
+    pub fn theme(&self, dark: bool) -> Result<Theme> {
+        match &self.theme.dark {
+            Some(name) if dark => self.named_theme("theme.dark", name),
+            _ => self.named_theme("theme.name", &self.theme.name),
+        }
+    }
+
+    /// Either a preset or one from `themes`.
+    fn named_theme(&self, key: &str, name: &str) -> Result<Theme> {
+        if let Some(definition) = self.themes.get(name) {
+            return definition
+                .build(name)
+                .with_context(|| format!("themes.{name}"));
+        }
+
+        Theme::preset(name).with_context(|| {
+            format!("{key}: unknown theme `{name}`, expected a preset or one of `themes`")
+        })
+    }
+
+    /// Checks constraints spanning several keys, which can't be expressed while deserializing.
+    fn validate(&self) -> Result<()> {
+        for (name, definition) in &self.themes {
+            definition
+                .build(name)
+                .with_context(|| format!("themes.{name}"))?;
+        }
+        self.theme(false)?;
+        self.theme(true)?;
+
+        for (section, widgets) in self.bar.sections() {
+            for (i, widget) in widgets.iter().enumerate() {
+                widget
+                    .validate()
+                    .with_context(|| format!("bar.{section}[{i}] ({})", widget.name()))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BarConfig {
+    pub position: BarPosition,
+    #[serde(deserialize_with = "positive")]
+    pub height: f32,
+    /// Width of the bar, defaults to the width of the output.
+    #[serde(deserialize_with = "positive_opt")]
+    pub width: Option<f32>,
+    pub layer: BarLayer,
+    pub exclusive_zone: ExclusiveZone,
+    pub namespace: String,
+    /// Names of the outputs to show a bar on, all of them when empty.
+    pub outputs: Vec<String>,
+    #[serde(deserialize_with = "left_widgets")]
+    pub left: Vec<WidgetConfig>,
+    #[serde(deserialize_with = "center_widgets")]
+    pub center: Vec<WidgetConfig>,
+    #[serde(deserialize_with = "right_widgets")]
+    pub right: Vec<WidgetConfig>,
+}
+
+impl Default for BarConfig {
+    fn default() -> Self {
+        Self {
+            position: BarPosition::Top,
+            height: 35.,
+            width: None,
+            layer: BarLayer::Top,
+            exclusive_zone: ExclusiveZone::Auto,
+            namespace: "mgs".to_string(),
+            outputs: Vec::new(),
+            left: vec![WidgetConfig::Workspaces(WorkspacesConfig::default())],
+            center: vec![WidgetConfig::Media(MediaConfig::default())],
+            right: vec![
+                WidgetConfig::Tray(TrayConfig::default()),
+                WidgetConfig::Network(NetworkConfig::default()),
+                WidgetConfig::SysInfo(SysInfoConfig::default()),
+                WidgetConfig::Volume(VolumeConfig::default()),
+                WidgetConfig::Upower(UpowerConfig::default()),
+                WidgetConfig::Notifications(NotificationsButtonConfig::default()),
+                WidgetConfig::Clock(ClockConfig::default()),
+            ],
+        }
+    }
+}
+
+impl BarConfig {
+    /// Whether both configs produce the same layer shell window, ignoring the widgets.
+    pub fn same_window(&self, other: &Self) -> bool {
+        self.position == other.position
+            && self.height == other.height
+            && self.width == other.width
+            && self.layer == other.layer
+            && self.exclusive_zone == other.exclusive_zone
+            && self.namespace == other.namespace
+    }
+
+    pub fn sections(&self) -> [(&'static str, &[WidgetConfig]); 3] {
+        [
+            ("left", &self.left),
+            ("center", &self.center),
+            ("right", &self.right),
+        ]
+    }
+
+    pub fn anchor(&self) -> Anchor {
+        match self.position {
+            BarPosition::Top => Anchor::TOP | Anchor::LEFT | Anchor::RIGHT,
+            BarPosition::Bottom => Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT,
+        }
+    }
+
+    pub fn exclusive_zone(&self) -> Option<f32> {
+        match self.exclusive_zone {
+            ExclusiveZone::Auto => Some(self.height),
+            ExclusiveZone::Disabled => None,
+            ExclusiveZone::Fixed(zone) => Some(zone),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub name: String,
+    /// Theme used instead of `name` while the system prefers a dark appearance.
+    pub dark: Option<String>,
+    /// Use the accent color picked in the desktop settings, if any.
+    pub system_accent: bool,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            name: "latte".to_string(),
+            dark: None,
+            system_accent: false,
+        }
+    }
+}
+
+/// A theme based on a preset, overriding some of its values.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeDefinition {
+    pub extends: String,
+    pub palette: PaletteConfig,
+    pub font_family: Option<String>,
+    #[serde(deserialize_with = "font_weight")]
+    pub font_weight: Option<u16>,
+    #[serde(deserialize_with = "positive_opt")]
+    pub spacing: Option<f32>,
+    #[serde(deserialize_with = "positive_opt")]
+    pub radius: Option<f32>,
+}
+
+impl Default for ThemeDefinition {
+    fn default() -> Self {
+        Self {
+            extends: "latte".to_string(),
+            palette: PaletteConfig::default(),
+            font_family: None,
+            font_weight: None,
+            spacing: None,
+            radius: None,
+        }
+    }
+}
+
+impl ThemeDefinition {
+    fn build(&self, name: &str) -> Result<Theme> {
+        let mut theme = Theme::preset(&self.extends)
+            .with_context(|| format!("extends: unknown preset `{}`", self.extends))?;
+
+        theme.name = name.to_string().into();
+        self.palette.apply(&mut theme.palette);
+        if let Some(font_family) = &self.font_family {
+            theme.font_family = font_family.clone().into();
+        }
+        if let Some(font_weight) = self.font_weight {
+            theme.font_weight = FontWeight(font_weight as f32);
+        }
+        if let Some(spacing) = self.spacing {
+            theme.spacing = px(spacing);
+        }
+        if let Some(radius) = self.radius {
+            theme.radius = px(radius);
+        }
+
+        Ok(theme)
+    }
+}
+
+/// Colors as `#rrggbb` or `#rrggbbaa`, unset ones are taken from the preset.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaletteConfig {
+    #[serde(deserialize_with = "color")]
+    pub background: Option<Rgba>,
+    #[serde(deserialize_with = "color")]
+    pub surface: Option<Rgba>,
+    #[serde(deserialize_with = "color")]
+    pub text: Option<Rgba>,
+    #[serde(deserialize_with = "color")]
+    pub subtext: Option<Rgba>,
+    #[serde(deserialize_with = "color")]
+    pub hover: Option<Rgba>,
+    #[serde(deserialize_with = "color")]
+    pub border: Option<Rgba>,
+    #[serde(deserialize_with = "color")]
+    pub accent: Option<Rgba>,
+    #[serde(deserialize_with = "color")]
+    pub track: Option<Rgba>,
+    #[serde(deserialize_with = "color")]
+    pub warning: Option<Rgba>,
+    #[serde(deserialize_with = "color")]
+    pub error: Option<Rgba>,
+}
+
+impl PaletteConfig {
+    fn apply(&self, palette: &mut Palette) {
+        let overrides = [
+            (self.background, &mut palette.background),
+            (self.surface, &mut palette.surface),
+            (self.text, &mut palette.text),
+            (self.subtext, &mut palette.subtext),
+            (self.hover, &mut palette.hover),
+            (self.border, &mut palette.border),
+            (self.accent, &mut palette.accent),
+            (self.track, &mut palette.track),
+            (self.warning, &mut palette.warning),
+            (self.error, &mut palette.error),
+        ];
+        for (color, slot) in overrides {
+            if let Some(color) = color {
+                *slot = color;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LauncherConfig {
+    #[serde(deserialize_with = "positive")]
+    pub width: f32,
+    #[serde(deserialize_with = "positive")]
+    pub height: f32,
+    #[serde(deserialize_with = "at_least_one")]
+    pub max_results: usize,
+    /// Command prefix running applications that need a terminal, e.g. `["foot", "-e"]`.
+    pub terminal: Vec<String>,
+}
+
+impl Default for LauncherConfig {
+    fn default() -> Self {
+        Self {
+            width: 600.,
+            height: 420.,
+            max_results: 8,
+            terminal: vec!["xterm".to_string(), "-e".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Corner of the output the popups stack in.
+    pub position: Corner,
+    /// Width of the popups and of the notification center.
+    #[serde(deserialize_with = "positive")]
+    pub width: f32,
+    /// Popups shown at once, the newest ones first.
+    #[serde(deserialize_with = "at_least_one")]
+    pub max_popups: usize,
+    /// How long notifications stay when their app leaves it to us.
+    pub timeout_ms: u64,
+    /// Notifications kept in the history, the oldest ones going first.
+    #[serde(deserialize_with = "at_least_one")]
+    pub history_size: usize,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            position: Corner::TopRight,
+            width: 380.,
+            max_popups: 5,
+            timeout_ms: 5000,
+            history_size: 100,
+        }
+    }
+}
+
+impl NotificationsConfig {
+    pub fn anchor(&self) -> Anchor {
+        match self.position {
+            Corner::TopLeft => Anchor::TOP | Anchor::LEFT,
+            Corner::TopRight => Anchor::TOP | Anchor::RIGHT,
+            Corner::BottomLeft => Anchor::BOTTOM | Anchor::LEFT,
+            Corner::BottomRight => Anchor::BOTTOM | Anchor::RIGHT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarPosition {
+    #[default]
+    Top,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarLayer {
+    Background,
+    Bottom,
+    #[default]
+    Top,
+    Overlay,
+}
+
+impl From<BarLayer> for Layer {
+    fn from(layer: BarLayer) -> Self {
+        match layer {
+            BarLayer::Background => Layer::Background,
+            BarLayer::Bottom => Layer::Bottom,
+            BarLayer::Top => Layer::Top,
+            BarLayer::Overlay => Layer::Overlay,
+        }
+    }
+}
+
+/// `true` reserves the bar height, `false` reserves nothing, a number reserves that many pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ExclusiveZone {
+    #[default]
+    Auto,
+    Disabled,
+    Fixed(f32),
+}
+
+impl<'de> Deserialize<'de> for ExclusiveZone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ExclusiveZoneVisitor;
+
+        impl Visitor<'_> for ExclusiveZoneVisitor {
+            type Value = ExclusiveZone;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a boolean or a non-negative number of pixels")
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+                Ok(if v {
+                    ExclusiveZone::Auto
+                } else {
+                    ExclusiveZone::Disabled
+                })
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                self.visit_f64(v as f64)
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                if v < 0. {
+                    return Err(E::invalid_value(de::Unexpected::Float(v), &self));
+                }
+                Ok(ExclusiveZone::Fixed(v as f32))
+            }
+        }
+
+        deserializer.deserialize_any(ExclusiveZoneVisitor)
+    }
+}
+
+/// A widget entry in one of the bar sections.
+///
+/// Either just the widget name (`"clock"`), or a table with a `type` key and the
+/// widget options (`{ type = "clock", format = "%H:%M:%S" }`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WidgetConfig {
+    Workspaces(WorkspacesConfig),
+    Clock(ClockConfig),
+    Network(NetworkConfig),
+    #[serde(rename = "sysinfo")]
+    SysInfo(SysInfoConfig),
+    Volume(VolumeConfig),
+    Microphone(MicrophoneConfig),
+    Upower(UpowerConfig),
+    Launcher(LauncherButtonConfig),
+    Tray(TrayConfig),
+    Notifications(NotificationsButtonConfig),
+    Media(MediaConfig),
+    Bluetooth(BluetoothConfig),
+}
+
+impl WidgetConfig {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Workspaces(_) => "workspaces",
+            Self::Clock(_) => "clock",
+            Self::Network(_) => "network",
+            Self::SysInfo(_) => "sysinfo",
+            Self::Volume(_) => "volume",
+            Self::Microphone(_) => "microphone",
+            Self::Upower(_) => "upower",
+            Self::Launcher(_) => "launcher",
+            Self::Tray(_) => "tray",
+            Self::Notifications(_) => "notifications",
+            Self::Media(_) => "media",
+            Self::Bluetooth(_) => "bluetooth",
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self {
+            Self::Network(network) => {
+                ascending(&[
+                    ("low_threshold", network.low_threshold as f64),
+                    ("high_threshold", network.high_threshold as f64),
+                ])?;
+            }
+            Self::Volume(volume) => {
+                ascending(&[
+                    ("low_threshold", volume.low_threshold as f64),
+                    ("medium_threshold", volume.medium_threshold as f64),
+                ])?;
+            }
+            Self::Upower(upower) => {
+                ascending(&[
+                    ("warning_threshold", upower.warning_threshold),
+                    ("low_threshold", upower.low_threshold),
+                    ("medium_threshold", upower.medium_threshold),
+                ])?;
+            }
+            Self::Workspaces(_)
+            | Self::Clock(_)
+            | Self::SysInfo(_)
+            | Self::Microphone(_)
+            | Self::Launcher(_)
+            | Self::Tray(_)
+            | Self::Notifications(_)
+            | Self::Media(_)
+            | Self::Bluetooth(_) => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkspacesConfig {}
+
+/// A button opening the launcher.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LauncherButtonConfig {}
+
+/// A button opening the notification center, with the count of unread notifications.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsButtonConfig {}
+
+/// The track of the active media player, with playback controls.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MediaConfig {
+    /// Width past which the track is cut off.
+    #[serde(deserialize_with = "positive")]
+    pub max_width: f32,
+}
+
+impl Default for MediaConfig {
+    fn default() -> Self {
+        Self { max_width: 240. }
+    }
+}
+
+/// The Bluetooth adapter state and the count of connected devices, opening the device list.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BluetoothConfig {}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClockConfig {
+    /// A `strftime` format string.
+    #[serde(deserialize_with = "strftime")]
+    pub format: String,
+    #[serde(deserialize_with = "refresh_rate")]
+    pub refresh_rate_ms: u64,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            format: "%H:%M".to_string(),
+            refresh_rate_ms: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    #[serde(deserialize_with = "refresh_rate")]
+    pub refresh_rate_ms: u32,
+    pub show_speed: bool,
+    /// Signal strength (in percent) below which the low wifi icon is shown.
+    #[serde(deserialize_with = "percentage")]
+    pub low_threshold: u8,
+    /// Signal strength (in percent) below which the high wifi icon is shown.
+    #[serde(deserialize_with = "percentage")]
+    pub high_threshold: u8,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            refresh_rate_ms: 5000,
+            show_speed: true,
+            low_threshold: 25,
+            high_threshold: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SysInfoConfig {
+    #[serde(deserialize_with = "refresh_rate")]
+    pub refresh_rate_ms: u64,
+}
+
+impl Default for SysInfoConfig {
+    fn default() -> Self {
+        Self {
+            refresh_rate_ms: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VolumeConfig {
+    /// Volume (in percent) below which the low volume icon is shown.
+    #[serde(deserialize_with = "percentage")]
+    pub low_threshold: u8,
+    /// Volume (in percent) below which the medium volume icon is shown.
+    #[serde(deserialize_with = "percentage")]
+    pub medium_threshold: u8,
+    /// Move the playing applications along when another output is picked in the mixer.
+    pub move_inputs: bool,
+}
+
+impl Default for VolumeConfig {
+    fn default() -> Self {
+        Self {
+            low_threshold: 30,
+            medium_threshold: 70,
+            move_inputs: true,
+        }
+    }
+}
+
+/// The default microphone, muted on click, highlighted while an app records.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MicrophoneConfig {
+    /// Only show the microphone while an app records.
+    pub hide_when_idle: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpowerConfig {
+    /// Battery percentage at or below which the warning icon is shown.
+    #[serde(deserialize_with = "percentage")]
+    pub warning_threshold: f64,
+    /// Battery percentage at or below which the low battery icon is shown.
+    #[serde(deserialize_with = "percentage")]
+    pub low_threshold: f64,
+    /// Battery percentage at or below which the medium battery icon is shown.
+    #[serde(deserialize_with = "percentage")]
+    pub medium_threshold: f64,
+}
+
+impl Default for UpowerConfig {
+    fn default() -> Self {
+        Self {
+            warning_threshold: 10.,
+            low_threshold: 30.,
+            medium_threshold: 80.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrayConfig {
+    #[serde(deserialize_with = "positive")]
+    pub icon_size: f32,
+    /// Also show the items that report nothing worth attention going on.
+    pub show_passive: bool,
+}
+
+impl Default for TrayConfig {
+    fn default() -> Self {
+        Self {
+            icon_size: 16.,
+            show_passive: false,
+        }
+    }
+}
+
+fn ascending(thresholds: &[(&str, f64)]) -> Result<()> {
+    for pair in thresholds.windows(2) {
+        let ((lower_key, lower), (upper_key, upper)) = (pair[0], pair[1]);
+        if lower >= upper {
+            bail!("`{lower_key}` ({lower}) must be lower than `{upper_key}` ({upper})");
+        }
+    }
+
+    Ok(())
+}
+
+fn left_widgets<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<WidgetConfig>, D::Error> {
+    widgets("left", deserializer)
+}
+
+fn center_widgets<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<WidgetConfig>, D::Error> {
+    widgets("center", deserializer)
+}
+
+fn right_widgets<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<WidgetConfig>, D::Error> {
+    widgets("right", deserializer)
+}
+
+/// The widgets of a bar section, errors name the key at fault as `bar.{section}[{i}].{key}`,
+/// as they can only point at the whole entry.
+fn widgets<'de, D: Deserializer<'de>>(
+    section: &'static str,
+    deserializer: D,
+) -> Result<Vec<WidgetConfig>, D::Error> {
+    struct Entries(&'static str);
+
+    impl<'de> Visitor<'de> for Entries {
+        type Value = Vec<WidgetConfig>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a list of widgets")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut widgets = Vec::new();
+            while let Some(widget) = seq.next_element_seed(Entry {
+                section: self.0,
+                index: widgets.len(),
+            })? {
+                widgets.push(widget);
+            }
+
+            Ok(widgets)
+        }
+    }
+
+    struct Entry {
+        section: &'static str,
+        index: usize,
+    }
+
+    impl<'de> DeserializeSeed<'de> for Entry {
+        type Value = WidgetConfig;
+
+        fn deserialize<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_any(self)
+        }
+    }
+
+    // the entry is converted while it's visited, for errors to point at it
+    impl<'de> Visitor<'de> for Entry {
+        type Value = WidgetConfig;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a widget name or a table with a `type` key")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            self.widget(toml::Table::from_iter([("type".to_string(), v.into())]))
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+            let table = toml::Table::deserialize(de::value::MapAccessDeserializer::new(map))?;
+            self.widget(table)
+        }
+    }
+
+    impl Entry {
+        fn widget<E: de::Error>(self, table: toml::Table) -> Result<WidgetConfig, E> {
+            let Self { section, index } = self;
+            widget(&table).map_err(|(key, message)| match key {
+                Some(key) => E::custom(format!("bar.{section}[{index}].{key}: {message}")),
+                None => E::custom(format!("bar.{section}[{index}]: {message}")),
+            })
+        }
+    }
+
+    deserializer.deserialize_seq(Entries(section))
+}
+
+/// A widget from its `type` and options, fails with the key at fault, if it's down to one,
+/// and why.
+fn widget(table: &toml::Table) -> Result<WidgetConfig, (Option<&str>, String)> {
+    let parse = |keys: &[&str]| {
+        let table: toml::Table = table
+            .iter()
+            .filter(|(key, _)| keys.contains(&key.as_str()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        WidgetConfig::deserialize(toml::Value::Table(table))
+    };
+
+    let keys: Vec<&str> = table.keys().map(String::as_str).collect();
+    parse(&keys).map_err(|err| {
+        // the options all have defaults, so the key failing along with the type alone is it
+        let key = if parse(&["type"]).is_err() {
+            table.contains_key("type").then_some("type")
+        } else {
+            keys.iter()
+                .copied()
+                .find(|&key| key != "type" && parse(&["type", key]).is_err())
+        };
+        (key, err.message().to_string())
+    })
+}
+
+fn positive<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    let value = f32::deserialize(deserializer)?;
+    if value.is_nan() || value <= 0. {
+        return Err(de::Error::custom(format!(
+            "expected a positive number, found {value}"
+        )));
+    }
+
+    Ok(value)
+}
+
+fn at_least_one<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    let value = usize::deserialize(deserializer)?;
+    if value == 0 {
+        return Err(de::Error::custom("expected at least 1"));
+    }
+
+    Ok(value)
+}
+
+fn positive_opt<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f32>, D::Error> {
+    positive(deserializer).map(Some)
+}
+
+fn refresh_rate<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Into<u64> + Copy,
+{
+    let value = T::deserialize(deserializer)?;
+    if value.into() < 100 {
+        return Err(de::Error::custom(format!(
+            "refresh rate must be at least 100ms, found {}ms",
+            value.into()
+        )));
+    }
+
+    Ok(value)
+}
+
+fn percentage<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Into<f64> + Copy,
+{
+    let value = T::deserialize(deserializer)?;
+    if !(0.0..=100.0).contains(&value.into()) {
+        return Err(de::Error::custom(format!(
+            "expected a percentage between 0 and 100, found {}",
+            value.into()
+        )));
+    }
+
+    Ok(value)
+}
+
+fn strftime<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let format = String::deserialize(deserializer)?;
+    if StrftimeItems::new(&format).any(|item| matches!(item, Item::Error)) {
+        return Err(de::Error::custom(format!(
+            "invalid strftime format `{format}`"
+        )));
+    }
+
+    Ok(format)
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Rgba>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    let invalid = || {
+        de::Error::custom(format!(
+            "expected `#rrggbb` or `#rrggbbaa`, found `{value}`"
+        ))
+    };
+
+    let hex = value
+        .strip_prefix('#')
+        .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(invalid)?;
+    let color = u32::from_str_radix(hex, 16).map_err(|_| invalid())?;
+    match hex.len() {
+        6 => Ok(Some(rgba(color << 8 | 0xff))),
+        8 => Ok(Some(rgba(color))),
+        _ => Err(invalid()),
+    }
+}
+
+fn font_weight<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u16>, D::Error> {
+    let value = u16::deserialize(deserializer)?;
+    if !(100..=900).contains(&value) {
+        return Err(de::Error::custom(format!(
+            "expected a font weight between 100 and 900, found {value}"
+        )));
+    }
+
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> String {
+        match Config::parse(source) {
+            Ok(config) => panic!("expected an error, got {config:?}"),
+            Err(err) => format!("{err:#}"),
+        }
+    }
+
+    #[track_caller]
+    fn assert_error(source: &str, location: &str, message: &str) {
+        let err = error(source);
+        assert!(err.contains(location), "no `{location}` in {err}");
+        assert!(err.contains(message), "no `{message}` in {err}");
+    }
+
+    #[test]
+    fn the_default_config_is_valid() {
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn widgets_are_names_or_tables() {
+        let config = Config::parse(
+            r#"
+[bar]
+left = ["clock", { type = "clock", format = "%H:%M:%S" }]
+center = []
+right = ["sysinfo"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.bar.left,
+            [
+                WidgetConfig::Clock(ClockConfig::default()),
+                WidgetConfig::Clock(ClockConfig {
+                    format: "%H:%M:%S".to_string(),
+                    ..Default::default()
+                }),
+            ]
+        );
+        assert!(config.bar.center.is_empty());
+        assert_eq!(
+            config.bar.right,
+            [WidgetConfig::SysInfo(SysInfoConfig::default())]
+        );
+    }
+
+    #[test]
+    fn widgets_reject_unknown_entries() {
+        assert_error(
+            "[bar]\nleft = [\"clock\", \"nope\"]\n",
+            "line 2, column 18",
+            "bar.left[1].type: unknown variant `nope`",
+        );
+        assert_error(
+            "[bar]\nleft = [3]\n",
+            "line 2, column 9",
+            "expected a widget name or a table with a `type` key",
+        );
+        assert_error(
+            "[bar]\nright = [{ type = \"tray\", size = 16 }]\n",
+            "line 2, column 10",
+            "bar.right[0].size: unknown field `size`",
+        );
+        assert_error(
+            "[bar]\nright = [{ size = 16 }]\n",
+            "line 2, column 10",
+            "bar.right[0]: missing field `type`",
+        );
+        assert_error(
+            "[[bar.right]]\ntype = \"sysinfo\"\n\n\
+             [[bar.right]]\ntype = \"volume\"\nmove_inputs = true\nlow_threshold = 300\n",
+            "line 4, column 1",
+            "bar.right[1].low_threshold: invalid value: integer `300`, expected u8",
+        );
+    }
+
+    #[test]
+    fn positive_rejects_zero_negatives_and_nan() {
+        assert_error(
+            "[bar]\nheight = 0\n",
+            "line 2, column 10",
+            "expected a positive number, found 0",
+        );
+        assert_error(
+            "[bar]\nwidth = -10.5\n",
+            "line 2, column 9",
+            "expected a positive number, found -10.5",
+        );
+        assert_error(
+            "[bar]\nheight = nan\n",
+            "line 2, column 10",
+            "expected a positive number, found NaN",
+        );
+        assert_error(
+            "[[bar.center]]\ntype = \"media\"\nmax_width = 0\n",
+            "line 1, column 1",
+            "bar.center[0].max_width: expected a positive number, found 0",
+        );
+    }
+
+    #[test]
+    fn refresh_rate_has_a_floor() {
+        assert_error(
+            "[bar]\nright = [{ type = \"network\", refresh_rate_ms = 50 }]\n",
+            "line 2, column 10",
+            "bar.right[0].refresh_rate_ms: refresh rate must be at least 100ms, found 50ms",
+        );
+        assert_error(
+            "[bar]\nleft = [\"clock\", { type = \"sysinfo\", refresh_rate_ms = 99 }]\n",
+            "line 2, column 18",
+            "bar.left[1].refresh_rate_ms: refresh rate must be at least 100ms, found 99ms",
+        );
+
+        let config =
+            Config::parse("[bar]\nright = [{ type = \"sysinfo\", refresh_rate_ms = 100 }]\n")
+                .unwrap();
+        assert_eq!(
+            config.bar.right,
+            [WidgetConfig::SysInfo(SysInfoConfig {
+                refresh_rate_ms: 100
+            })]
+        );
+    }
+
+    #[test]
+    fn percentage_is_between_0_and_100() {
+        assert_error(
+            "[bar]\nright = [{ type = \"volume\", medium_threshold = 101 }]\n",
+            "line 2, column 10",
+            "bar.right[0].medium_threshold: expected a percentage between 0 and 100, found 101",
+        );
+        assert_error(
+            "[bar]\nright = [{ type = \"upower\", warning_threshold = -1.5 }]\n",
+            "line 2, column 10",
+            "bar.right[0].warning_threshold: expected a percentage between 0 and 100, found -1.5",
+        );
+    }
+
+    #[test]
+    fn strftime_rejects_unknown_specifiers() {
+        assert_error(
+            "[[bar.right]]\ntype = \"clock\"\nformat = \"%H:%Q\"\n",
+            "line 1, column 1",
+            "bar.right[0].format: invalid strftime format `%H:%Q`",
+        );
+    }
+
+    #[test]
+    fn validate_reports_the_widget_path() {
+        assert_eq!(
+            error("[bar]\nright = [\"clock\", { type = \"network\", low_threshold = 60 }]\n"),
+            "bar.right[1] (network): \
+             `low_threshold` (60) must be lower than `high_threshold` (50)"
+        );
+        assert_eq!(
+            error(
+                "[bar]\nleft = [{ type = \"volume\", low_threshold = 70, medium_threshold = 70 }]\n"
+            ),
+            "bar.left[0] (volume): \
+             `low_threshold` (70) must be lower than `medium_threshold` (70)"
+        );
+        assert_eq!(
+            error(
+                "[[bar.center]]\ntype = \"media\"\n\n\
+                 [[bar.center]]\ntype = \"upower\"\nwarning_threshold = 40\n"
+            ),
+            "bar.center[1] (upower): \
+             `warning_threshold` (40) must be lower than `low_threshold` (30)"
+        );
+    }
+
+    #[test]
+    fn validate_checks_the_themes() {
+        assert_eq!(
+            error("[theme]\nname = \"nope\"\n"),
+            "theme.name: unknown theme `nope`, expected a preset or one of `themes`"
+        );
+        assert_eq!(
+            error("[themes.custom]\nextends = \"nope\"\n"),
+            "themes.custom: extends: unknown preset `nope`"
+        );
+        assert!(
+            Config::parse("[theme]\ndark = \"custom\"\n\n[themes.custom]\nextends = \"mocha\"\n")
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! The `dev.gallon.mgs` service on the session bus, exposing the same commands as the
+//! control socket and the state other desktop tools may want to follow.
+//!
+//! Method calls go to the request handler the control socket uses, in process, so they
+//! keep working when the socket couldn't be bound.
+
+use std::sync::Arc;
+
+use futures::StreamExt;
+use futures_signals::signal::SignalExt;
+use services::{APP_ID, audio, hyprland, upower};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+use ui::prelude::*;
+use zbus::object_server::InterfaceRef;
+use zbus::{connection, fdo, interface};
+
+use crate::clients::{Clients, SharedClient};
+use crate::ipc::{self, Request, Requests, Response, Switch, Visibility, VolumeChange};
+
+const PATH: &str = "/dev/gallon/mgs";
+
+pub struct Shell {
+    requests: Requests,
+    active_workspace: i32,
+    volume: f64,
+    muted: bool,
+    battery_percentage: f64,
+    battery_state: String,
+}
+
+impl Shell {
+    async fn request(&self, request: Request) -> fdo::Result<()> {
+        match ipc::call(&self.requests, request).await {
+            Response::Ok { .. } => Ok(()),
+            Response::Error { message } => Err(fdo::Error::Failed(message)),
+        }
+    }
+
+    async fn set_bar(&self, action: Visibility, output: &str) -> fdo::Result<()> {
+        let output = (!output.is_empty()).then(|| output.to_string());
+        self.request(Request::Bar { action, output }).await
+    }
+
+    async fn change_volume(&self, change: VolumeChange) -> fdo::Result<()> {
+        self.request(Request::Volume { change }).await
+    }
+}
+
+#[interface(name = "dev.gallon.mgs.Shell")]
+impl Shell {
+    /// Shows the bar on `output`, or on all outputs when it's empty.
+    async fn show_bar(&self, output: &str) -> fdo::Result<()> {
+        self.set_bar(Visibility::Show, output).await
+    }
+
+    async fn hide_bar(&self, output: &str) -> fdo::Result<()> {
+        self.set_bar(Visibility::Hide, output).await
+    }
+
+    async fn toggle_bar(&self, output: &str) -> fdo::Result<()> {
+        self.set_bar(Visibility::Toggle, output).await
+    }
+
+    async fn open_launcher(&self) -> fdo::Result<()> {
+        self.request(Request::Launcher {
+            action: Visibility::Show,
+        })
+        .await
+    }
+
+    async fn close_launcher(&self) -> fdo::Result<()> {
+        self.request(Request::Launcher {
+            action: Visibility::Hide,
+        })
+        .await
+    }
+
+    async fn toggle_launcher(&self) -> fdo::Result<()> {
+        self.request(Request::Launcher {
+            action: Visibility::Toggle,
+        })
+        .await
+    }
+
+    async fn reload(&self) -> fdo::Result<()> {
+        self.request(Request::Reload).await
+    }
+
+    /// Sets the volume of the default sink, in percent.
+    async fn set_volume(&self, volume: f64) -> fdo::Result<()> {
+        self.change_volume(VolumeChange::Set(volume as f32)).await
+    }
+
+    async fn adjust_volume(&self, delta: f64) -> fdo::Result<()> {
+        self.change_volume(VolumeChange::Adjust(delta as f32)).await
+    }
+
+    async fn toggle_mute(&self) -> fdo::Result<()> {
+        self.change_volume(VolumeChange::ToggleMute).await
+    }
+
+    async fn set_do_not_disturb(&self, do_not_disturb: bool) -> fdo::Result<()> {
+        let action = if do_not_disturb {
+            Switch::On
+        } else {
+            Switch::Off
+        };
+        self.request(Request::DoNotDisturb { action }).await
+    }
+
+    async fn toggle_do_not_disturb(&self) -> fdo::Result<()> {
+        self.request(Request::DoNotDisturb {
+            action: Switch::Toggle,
+        })
+        .await
+    }
+
+    #[zbus(property)]
+    async fn active_workspace(&self) -> i32 {
+        self.active_workspace
+    }
+
+    #[zbus(property)]
+    async fn volume(&self) -> f64 {
+        self.volume
+    }
+
+    #[zbus(property)]
+    async fn muted(&self) -> bool {
+        self.muted
+    }
+
+    #[zbus(property)]
+    async fn battery_percentage(&self) -> f64 {
+        self.battery_percentage
+    }
+
+    #[zbus(property)]
+    async fn battery_state(&self) -> String {
+        self.battery_state.clone()
+    }
+}
+
+/// Owns `dev.gallon.mgs` on the session bus and exports the shell at `/dev/gallon/mgs`,
+/// passing its method calls on to `requests`.
+pub fn serve(requests: Requests, cx: &mut App) {
+    let hyprland = Clients::hyprland(cx);
+    let audio = Clients::audio(cx);
+    let upower = Clients::upower(cx);
+
+    cx.spawn(async move |_| {
+        let shell = Shell {
+            requests,
+            active_workspace: 0,
+            volume: 0.,
+            muted: false,
+            battery_percentage: 0.,
+            battery_state: String::new(),
+        };
+        let conn = match async {
+            // a second instance should fail instead of taking the name over
+            connection::Builder::session()?
+                .name(APP_ID)?
+                .allow_name_replacements(false)
+                .replace_existing_names(false)
+                .serve_at(PATH, shell)?
+                .build()
+                .await
+        }
+        .await
+        {
+            Ok(conn) => conn,
+            Err(err) => {
+                warn!("not exporting the D-Bus interface: {err}");
+                return;
+            }
+        };
+        let shell = match conn.object_server().interface::<_, Shell>(PATH).await {
+            Ok(shell) => shell,
+            Err(err) => {
+                warn!("not exporting the D-Bus interface: {err}");
+                return;
+            }
+        };
+        info!("owning {APP_ID} on the session bus");
+
+        futures::join!(
+            watch_workspaces(&shell, hyprland),
+            watch_volume(&shell, audio),
+            watch_battery(&shell, upower),
+        );
+        // keep the name even if there is nothing left to follow
+        futures::future::pending::<()>().await;
+    })
+    .detach();
+}
+
+async fn watch_workspaces(
+    shell: &InterfaceRef<Shell>,
+    hyprland: SharedClient<hyprland::Subscriber>,
+) {
+    let Some(hyprland) = hyprland.await else {
+        return;
+    };
+
+    let mut signal = hyprland.subscribe().to_stream();
+    while let Some(data) = signal.next().await {
+        let Some(workspace) = data.workspaces.get(data.active) else {
+            continue;
+        };
+
+        let mut iface = shell.get_mut().await;
+        if iface.active_workspace != workspace.id {
+            iface.active_workspace = workspace.id;
+            iface
+                .active_workspace_changed(shell.signal_emitter())
+                .await
+                .ok();
+        }
+    }
+}
+
+async fn watch_volume(shell: &InterfaceRef<Shell>, audio: Arc<audio::Client>) {
+    let mut events = audio.subscribe();
+    loop {
+        let Some(sink) = audio.default_sink() else {
+            match events.recv().await {
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            }
+        };
+
+        {
+            let mut iface = shell.get_mut().await;
+            if iface.volume != sink.volume as f64 {
+                iface.volume = sink.volume as f64;
+                iface.volume_changed(shell.signal_emitter()).await.ok();
+            }
+            if iface.muted != sink.muted {
+                iface.muted = sink.muted;
+                iface.muted_changed(shell.signal_emitter()).await.ok();
+            }
+        }
+
+        // any sink event may have changed the default sink or its volume
+        match events.recv().await {
+            Ok(_) | Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+async fn watch_battery(shell: &InterfaceRef<Shell>, upower: SharedClient<upower::Subscriber>) {
+    let Some(upower) = upower.await else {
+        return;
+    };
+
+    let mut signal = upower.subscribe().to_stream();
+    while let Some(data) = signal.next().await {
+        let state = format!("{:?}", data.state);
+
+        let mut iface = shell.get_mut().await;
+        if iface.battery_percentage != data.percentage {
+            iface.battery_percentage = data.percentage;
+            iface
+                .battery_percentage_changed(shell.signal_emitter())
+                .await
+                .ok();
+        }
+        if iface.battery_state != state {
+            iface.battery_state = state;
+            iface
+                .battery_state_changed(shell.signal_emitter())
+                .await
+                .ok();
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use gpui::{Image, ImageFormat, ImageSource};
+use services::applications::data_dirs;
+
+/// An icon ready to be drawn, either a file or pixels sent over D-Bus.
+#[derive(Clone)]
+pub enum IconImage {
+    File(Arc<Path>),
+    Pixels(Arc<Image>),
+}
+
+impl IconImage {
+    /// Takes ARGB32 pixels in network byte order, as D-Bus services send them.
+    pub fn from_argb(width: u32, height: u32, data: &[u8]) -> Self {
+        IconImage::Pixels(Arc::new(Image::from_bytes(
+            ImageFormat::Bmp,
+            argb_to_bmp(width, height, data),
+        )))
+    }
+
+    pub fn source(&self) -> ImageSource {
+        match self {
+            IconImage::File(path) => path.clone().into(),
+            IconImage::Pixels(image) => image.clone().into(),
+        }
+    }
+}
+
+/// Finds the file for an `Icon` value, either an absolute path or a name looked up in
+/// the hicolor theme and the pixmaps directory.
+pub fn find_icon(icon: &str) -> Option<PathBuf> {
+    const SIZES: [&str; 4] = ["scalable", "48x48", "64x64", "128x128"];
+    lookup(icon, None, &SIZES, &["apps"])
+}
+
+/// Finds a tray icon, looking in the `theme_path` the app ships its icons in first.
+pub fn find_tray_icon(icon: &str, theme_path: Option<&Path>) -> Option<PathBuf> {
+    const SIZES: [&str; 7] = [
+        "scalable", "22x22", "24x24", "32x32", "48x48", "16x16", "64x64",
+    ];
+    lookup(
+        icon,
+        theme_path,
+        &SIZES,
+        &["status", "apps", "devices", "panel"],
+    )
+}
+
+fn lookup(
+    icon: &str,
+    theme_path: Option<&Path>,
+    sizes: &[&str],
+    contexts: &[&str],
+) -> Option<PathBuf> {
+    let path = Path::new(icon);
+    if path.is_absolute() {
+        return path.exists().then(|| path.to_path_buf());
+    }
+
+    let files = |dir: &Path| {
+        [
+            dir.join(format!("{icon}.svg")),
+            dir.join(format!("{icon}.png")),
+        ]
+    };
+    let theme = |dir: &Path| {
+        sizes
+            .iter()
+            .flat_map(|size| {
+                contexts
+                    .iter()
+                    .map(move |context| dir.join(size).join(context))
+            })
+            .flat_map(|dir| files(&dir))
+            .collect::<Vec<_>>()
+    };
+
+    // the theme path is either a flat directory or the root of a hicolor-like theme
+    let shipped = theme_path.into_iter().flat_map(|dir| {
+        let mut paths = files(dir).to_vec();
+        paths.extend(theme(&dir.join("hicolor")));
+        paths
+    });
+    let installed = data_dirs().into_iter().flat_map(|dir| {
+        let mut paths = theme(&dir.join("icons/hicolor"));
+        paths.extend(files(&dir.join("pixmaps")));
+        paths
+    });
+
+    shipped.chain(installed).find(|path| path.exists())
+}
+
+/// Encodes ARGB32 pixels as a top-down BMP with an alpha channel, a format gpui decodes.
+fn argb_to_bmp(width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+    const FILE_HEADER_SIZE: u32 = 14;
+    const INFO_HEADER_SIZE: u32 = 108;
+    const BI_BITFIELDS: u32 = 3;
+    const LCS_SRGB: u32 = 0x7352_4742;
+
+    let data_size = data.len() as u32;
+    let offset = FILE_HEADER_SIZE + INFO_HEADER_SIZE;
+
+    let mut bmp = Vec::with_capacity((offset + data_size) as usize);
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&(offset + data_size).to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&offset.to_le_bytes());
+
+    // BITMAPV4HEADER, the first one with an alpha mask
+    bmp.extend_from_slice(&INFO_HEADER_SIZE.to_le_bytes());
+    bmp.extend_from_slice(&(width as i32).to_le_bytes());
+    bmp.extend_from_slice(&(-(height as i32)).to_le_bytes());
+    bmp.extend_from_slice(&1u16.to_le_bytes());
+    bmp.extend_from_slice(&32u16.to_le_bytes());
+    bmp.extend_from_slice(&BI_BITFIELDS.to_le_bytes());
+    bmp.extend_from_slice(&data_size.to_le_bytes());
+    // resolution and palette
+    bmp.extend_from_slice(&[0; 16]);
+    for mask in [0x00ff_0000u32, 0x0000_ff00, 0x0000_00ff, 0xff00_0000] {
+        bmp.extend_from_slice(&mask.to_le_bytes());
+    }
+    bmp.extend_from_slice(&LCS_SRGB.to_le_bytes());
+    // color space endpoints and gamma, unused for sRGB
+    bmp.extend_from_slice(&[0; 48]);
+
+    // each big-endian ARGB pixel becomes a little-endian one
+    for pixel in data.chunks_exact(4) {
+        bmp.extend_from_slice(&[pixel[3], pixel[2], pixel[1], pixel[0]]);
+    }
+
+    bmp
+}
@@ -0,0 +1,580 @@
+//! Control socket, so that mgs can be driven from keybindings and scripts.
+//!
+//! Clients write one JSON request per line and get one JSON response line back, e.g.
+//! `{"command":"volume","change":{"adjust":5}}` answered by `{"status":"ok"}`.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result, anyhow, bail};
+use futures::StreamExt;
+use futures::channel::{mpsc, oneshot};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tracing::{debug, error, info, warn};
+use ui::prelude::*;
+
+use crate::bars::Bars;
+use crate::clients::Clients;
+use crate::launcher::LauncherWindow;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// Shows or hides the bar on `output`, or on all outputs.
+    Bar {
+        action: Visibility,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+    },
+    Launcher {
+        action: Visibility,
+    },
+    /// Reloads the config file.
+    Reload,
+    /// Changes the volume of the default sink.
+    Volume {
+        change: VolumeChange,
+    },
+    /// Keeps notifications from popping up, except critical ones.
+    DoNotDisturb {
+        action: Switch,
+    },
+    /// Returns the state of the bars and widgets.
+    Query,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    Show,
+    Hide,
+    Toggle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Switch {
+    On,
+    Off,
+    Toggle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeChange {
+    /// Sets the volume, in percent.
+    Set(f32),
+    /// Raises or lowers the volume by some percent.
+    Adjust(f32),
+    Mute,
+    Unmute,
+    ToggleMute,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Response {
+    Ok {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        state: Option<State>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct State {
+    pub bars: Vec<BarState>,
+    pub launcher: bool,
+    pub volume: Option<VolumeState>,
+    pub battery: Option<BatteryState>,
+    pub notifications: Option<NotificationsState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BarState {
+    /// `None` when the outputs are unknown and a single bar is shown.
+    pub output: Option<String>,
+    pub visible: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeState {
+    pub sink: String,
+    pub volume: f32,
+    pub muted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatteryState {
+    pub percentage: f64,
+    pub state: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationsState {
+    /// Notifications in the history.
+    pub count: usize,
+    pub unread: usize,
+    pub do_not_disturb: bool,
+}
+
+/// Sends requests to the handler on the foreground executor, each with the channel its
+/// response goes to.
+pub type Requests = mpsc::UnboundedSender<(Request, oneshot::Sender<Response>)>;
+
+/// `$XDG_RUNTIME_DIR/mgs-$WAYLAND_DISPLAY.sock`, one socket per compositor session.
+pub fn socket_path() -> Result<PathBuf> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .context("XDG_RUNTIME_DIR is not set")?;
+    let display = std::env::var("WAYLAND_DISPLAY").unwrap_or_else(|_| "wayland-0".to_string());
+
+    Ok(PathBuf::from(runtime_dir).join(format!("mgs-{display}.sock")))
+}
+
+/// Handles requests on the foreground executor until every sender is dropped.
+/// `config_path` is the file `reload` reads.
+pub fn handler(config_path: Option<PathBuf>, cx: &mut App) -> Requests {
+    let (tx, mut requests) = mpsc::unbounded::<(Request, oneshot::Sender<Response>)>();
+
+    cx.spawn(async move |cx: &mut AsyncApp| {
+        while let Some((request, reply)) = requests.next().await {
+            debug!("IPC request: {request:?}");
+            let response = match handle(request, config_path.as_deref(), cx).await {
+                Ok(state) => Response::Ok { state },
+                Err(err) => Response::Error {
+                    message: format!("{err:#}"),
+                },
+            };
+            reply.send(response).ok();
+        }
+    })
+    .detach();
+
+    tx
+}
+
+/// Sends `request` to the handler and waits for its response.
+pub async fn call(requests: &Requests, request: Request) -> Response {
+    let (tx, rx) = oneshot::channel();
+    if requests.unbounded_send((request, tx)).is_err() {
+        return Response::Error {
+            message: "mgs is shutting down".to_string(),
+        };
+    }
+
+    rx.await.unwrap_or_else(|_| Response::Error {
+        message: "mgs is shutting down".to_string(),
+    })
+}
+
+/// Listens on the control socket, passing its requests on to `requests`.
+pub fn serve(requests: Requests) {
+    let listener = match socket_path().and_then(|path| bind(&path)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!("not listening for commands: {err:#}");
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(stream, requests.clone()));
+                }
+                Err(err) => {
+                    error!("failed to accept IPC connection: {err}");
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Binds the socket, replacing the one left behind by an instance that didn't exit
+/// cleanly.
+fn bind(path: &Path) -> Result<UnixListener> {
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            bail!("another instance is listening on {}", path.display());
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("failed to remove stale socket {}", path.display()))?;
+    }
+
+    let listener =
+        UnixListener::bind(path).with_context(|| format!("failed to bind {}", path.display()))?;
+    info!("listening for commands on {}", path.display());
+
+    Ok(listener)
+}
+
+async fn handle_connection(stream: UnixStream, requests: Requests) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => call(&requests, request).await,
+            Err(err) => Response::Error {
+                message: format!("invalid request: {err}"),
+            },
+        };
+
+        let mut response = match serde_json::to_string(&response) {
+            Ok(response) => response,
+            Err(err) => {
+                error!("failed to serialize IPC response: {err}");
+                return;
+            }
+        };
+        response.push('\n');
+        if writer.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn handle(
+    request: Request,
+    config_path: Option<&Path>,
+    cx: &mut AsyncApp,
+) -> Result<Option<State>> {
+    match request {
+        Request::Bar { action, output } => cx.update(|cx| {
+            let visible = match action {
+                Visibility::Show => true,
+                Visibility::Hide => false,
+                Visibility::Toggle => {
+                    // hide them all if any is shown, so a toggle never leaves them mixed
+                    let outputs = Bars::outputs(cx);
+                    match &output {
+                        Some(name) => !outputs
+                            .iter()
+                            .any(|(output, visible)| output.as_ref() == Some(name) && *visible),
+                        None => !outputs.iter().any(|(_, visible)| *visible),
+                    }
+                }
+            };
+            Bars::set_visible(output.as_deref(), visible, cx)
+        })??,
+        Request::Launcher { action } => cx.update(|cx| match action {
+            Visibility::Show => LauncherWindow::open(cx),
+            Visibility::Hide => LauncherWindow::close(cx),
+            Visibility::Toggle => LauncherWindow::toggle(cx),
+        })?,
+        Request::Reload => {
+            let path = config_path.context("there is no config file to reload")?;
+            cx.update(|cx| crate::reload_config(path, cx))??;
+        }
+        Request::Volume { change } => {
+            let audio = cx.update(Clients::audio)?;
+            let sink = audio.default_sink().context("there is no audio sink")?;
+            match change {
+                VolumeChange::Set(volume) => {
+                    audio
+                        .set_sink_volume(&sink.name, volume.clamp(0., 100.))
+                        .await?
+                }
+                VolumeChange::Adjust(delta) => {
+                    audio
+                        .set_sink_volume(&sink.name, (sink.volume + delta).clamp(0., 100.))
+                        .await?
+                }
+                VolumeChange::Mute => audio.set_sink_muted(&sink.name, true).await?,
+                VolumeChange::Unmute => audio.set_sink_muted(&sink.name, false).await?,
+                VolumeChange::ToggleMute => audio.set_sink_muted(&sink.name, !sink.muted).await?,
+            }
+        }
+        Request::DoNotDisturb { action } => {
+            let notifications = cx.update(Clients::notifications)?;
+            let notifications = notifications
+                .await
+                .context("mgs isn't the notification daemon")?;
+            let do_not_disturb = match action {
+                Switch::On => true,
+                Switch::Off => false,
+                Switch::Toggle => !notifications.get().do_not_disturb,
+            };
+            notifications.set_do_not_disturb(do_not_disturb);
+        }
+        Request::Query => return query(cx).await.map(Some),
+    }
+
+    Ok(None)
+}
+
+async fn query(cx: &mut AsyncApp) -> Result<State> {
+    let (bars, launcher, volume, upower, notifications) = cx.update(|cx| {
+        let bars = Bars::outputs(cx)
+            .into_iter()
+            .map(|(output, visible)| BarState { output, visible })
+            .collect();
+        let volume = Clients::audio(cx).default_sink().map(|sink| VolumeState {
+            sink: sink.name,
+            volume: sink.volume,
+            muted: sink.muted,
+        });
+
+        (
+            bars,
+            LauncherWindow::is_open(cx),
+            volume,
+            Clients::upower(cx),
+            Clients::notifications(cx),
+        )
+    })?;
+    let battery = upower.await.map(|upower| {
+        let data = upower.get();
+        BatteryState {
+            percentage: data.percentage,
+            state: format!("{:?}", data.state),
+        }
+    });
+
+    let notifications = notifications.await.map(|notifications| {
+        let data = notifications.get();
+        NotificationsState {
+            count: data.history.len(),
+            unread: data.unread,
+            do_not_disturb: data.do_not_disturb,
+        }
+    });
+
+    Ok(State {
+        bars,
+        launcher,
+        volume,
+        battery,
+        notifications,
+    })
+}
+
+/// Runs `mgs msg <args>`: sends the request to the running instance and prints the
+/// state it answers with, if any.
+pub fn send(args: &[String]) -> Result<()> {
+    let request = parse_args(args)?;
+    let path = socket_path()?;
+    let mut stream = std::os::unix::net::UnixStream::connect(&path)
+        .with_context(|| format!("failed to connect to {}, is mgs running?", path.display()))?;
+
+    let mut line = serde_json::to_string(&request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    match serde_json::from_str(&response).context("invalid response")? {
+        Response::Ok { state: Some(state) } => {
+            println!("{}", serde_json::to_string_pretty(&state)?);
+            Ok(())
+        }
+        Response::Ok { state: None } => Ok(()),
+        Response::Error { message } => Err(anyhow!(message)),
+    }
+}
+
+const USAGE: &str = "usage: mgs msg <command>
+
+commands:
+  bar show|hide|toggle [output]
+  launcher [open|close|toggle]
+  reload
+  volume <N|+N|-N|mute|unmute|toggle-mute>
+  dnd [on|off|toggle]
+  query";
+
+fn parse_args(args: &[String]) -> Result<Request> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let request = match args.as_slice() {
+        ["bar", action, rest @ ..] if rest.len() <= 1 => Request::Bar {
+            action: match *action {
+                "show" => Visibility::Show,
+                "hide" => Visibility::Hide,
+                "toggle" => Visibility::Toggle,
+                _ => bail!("unknown bar action `{action}`\n\n{USAGE}"),
+            },
+            output: rest.first().map(ToString::to_string),
+        },
+        ["launcher"] => Request::Launcher {
+            action: Visibility::Toggle,
+        },
+        ["launcher", action] => Request::Launcher {
+            action: match *action {
+                "open" => Visibility::Show,
+                "close" => Visibility::Hide,
+                "toggle" => Visibility::Toggle,
+                _ => bail!("unknown launcher action `{action}`\n\n{USAGE}"),
+            },
+        },
+        ["reload"] => Request::Reload,
+        ["volume", change] => Request::Volume {
+            change: parse_volume(change)?,
+        },
+        ["dnd"] => Request::DoNotDisturb {
+            action: Switch::Toggle,
+        },
+        ["dnd", action] => Request::DoNotDisturb {
+            action: match *action {
+                "on" => Switch::On,
+                "off" => Switch::Off,
+                "toggle" => Switch::Toggle,
+                _ => bail!("unknown dnd action `{action}`\n\n{USAGE}"),
+            },
+        },
+        ["query"] => Request::Query,
+        _ => bail!("{USAGE}"),
+    };
+
+    Ok(request)
+}
+
+fn parse_volume(change: &str) -> Result<VolumeChange> {
+    let change = match change {
+        "mute" => VolumeChange::Mute,
+        "unmute" => VolumeChange::Unmute,
+        "toggle-mute" => VolumeChange::ToggleMute,
+        _ => {
+            let value = change
+                .parse::<f32>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| anyhow!("invalid volume `{change}`\n\n{USAGE}"))?;
+            if change.starts_with(['+', '-']) {
+                VolumeChange::Adjust(value)
+            } else {
+                VolumeChange::Set(value)
+            }
+        }
+    };
+
+    Ok(change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Request> {
+        let args: Vec<String> = line.split_whitespace().map(ToString::to_string).collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn parses_the_commands() {
+        let cases = [
+            (
+                "bar toggle",
+                Request::Bar {
+                    action: Visibility::Toggle,
+                    output: None,
+                },
+            ),
+            (
+                "bar hide DP-1",
+                Request::Bar {
+                    action: Visibility::Hide,
+                    output: Some("DP-1".to_string()),
+                },
+            ),
+            (
+                "launcher",
+                Request::Launcher {
+                    action: Visibility::Toggle,
+                },
+            ),
+            (
+                "launcher open",
+                Request::Launcher {
+                    action: Visibility::Show,
+                },
+            ),
+            (
+                "launcher close",
+                Request::Launcher {
+                    action: Visibility::Hide,
+                },
+            ),
+            ("reload", Request::Reload),
+            (
+                "dnd",
+                Request::DoNotDisturb {
+                    action: Switch::Toggle,
+                },
+            ),
+            ("dnd on", Request::DoNotDisturb { action: Switch::On }),
+            ("query", Request::Query),
+        ];
+
+        for (line, request) in cases {
+            assert_eq!(parse(line).unwrap(), request, "{line}");
+        }
+    }
+
+    #[test]
+    fn parses_volume_changes() {
+        let cases = [
+            ("50", VolumeChange::Set(50.)),
+            ("0", VolumeChange::Set(0.)),
+            ("+5", VolumeChange::Adjust(5.)),
+            ("-5", VolumeChange::Adjust(-5.)),
+            ("+2.5", VolumeChange::Adjust(2.5)),
+            // clamped once applied to the current volume
+            ("150", VolumeChange::Set(150.)),
+            ("-200", VolumeChange::Adjust(-200.)),
+            ("mute", VolumeChange::Mute),
+            ("unmute", VolumeChange::Unmute),
+            ("toggle-mute", VolumeChange::ToggleMute),
+        ];
+
+        for (change, expected) in cases {
+            assert_eq!(parse_volume(change).unwrap(), expected, "{change}");
+            assert_eq!(
+                parse(&format!("volume {change}")).unwrap(),
+                Request::Volume { change: expected },
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_volumes() {
+        for change in ["", "loud", "5%", "+", "++5", "nan", "inf", "-inf", "1e39"] {
+            let err = parse_volume(change).unwrap_err().to_string();
+            assert!(
+                err.starts_with(&format!("invalid volume `{change}`")),
+                "{change}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_commands() {
+        let cases = [
+            ("", USAGE),
+            ("volume", USAGE),
+            ("volume 5 10", USAGE),
+            ("bar", USAGE),
+            ("bar show DP-1 HDMI-A-1", USAGE),
+            ("restart", USAGE),
+            ("bar open", "unknown bar action `open`"),
+            ("launcher show", "unknown launcher action `show`"),
+            ("dnd maybe", "unknown dnd action `maybe`"),
+        ];
+
+        for (line, message) in cases {
+            let err = parse(line).unwrap_err().to_string();
+            assert!(err.starts_with(message), "{line}: {err}");
+            assert!(err.ends_with(USAGE), "{line}: {err}");
+        }
+    }
+}
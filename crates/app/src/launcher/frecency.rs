@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const STATE_DIR: &str = "mgs";
+const STATE_FILE: &str = "launcher.toml";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct Usage {
+    count: u32,
+    /// Unix timestamp of the last launch, in seconds.
+    last_used: u64,
+}
+
+/// How often and how recently each application was launched, by desktop file ID.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Frecency {
+    #[serde(default)]
+    usage: HashMap<String, Usage>,
+}
+
+impl Frecency {
+    /// `$XDG_STATE_HOME/mgs/launcher.toml`, falling back to `~/.local/state/mgs/launcher.toml`.
+    fn path() -> Option<PathBuf> {
+        let state_home = std::env::var_os("XDG_STATE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state"))
+            })?;
+
+        Some(state_home.join(STATE_DIR).join(STATE_FILE))
+    }
+
+    /// Loads the saved usage, starting from scratch if there is none or it's unreadable.
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|source| toml::from_str(&source).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().context("no state directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+
+        std::fs::write(&path, toml::to_string(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    pub fn record(&mut self, id: &str) {
+        let usage = self.usage.entry(id.to_string()).or_default();
+        usage.count += 1;
+        usage.last_used = now();
+    }
+
+    /// The launch count weighted by how long ago the last launch was.
+    pub fn score(&self, id: &str) -> u32 {
+        let Some(usage) = self.usage.get(id) else {
+            return 0;
+        };
+
+        let days = now().saturating_sub(usage.last_used) / (24 * 60 * 60);
+        let weight = match days {
+            0..4 => 100,
+            4..14 => 70,
+            14..31 => 50,
+            31..90 => 30,
+            _ => 10,
+        };
+
+        usage.count.saturating_mul(weight)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
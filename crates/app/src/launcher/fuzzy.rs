@@ -0,0 +1,52 @@
+/// Scores how well `query` matches `text`, `None` if its characters don't all appear
+/// in order.
+///
+/// Matches at the start of the text or of a word, and runs of consecutive characters
+/// score higher, so "ff" ranks "Firefox" below "Fast Files".
+pub fn score(query: &str, text: &str) -> Option<u32> {
+    let query: Vec<char> = query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let Some(&first) = query.first() else {
+        return Some(0);
+    };
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+
+    // matching greedily from the first occurrence would miss "ter" at the start of the
+    // second word of "Alacritty Terminal", so try every occurrence of the first character
+    let best = (0..text.len())
+        .filter(|&start| text[start] == first)
+        .filter_map(|start| score_from(&query, &text, start))
+        .max()?;
+
+    // prefer shorter texts among equal matches
+    Some((best * 100).saturating_sub(text.len() as u32))
+}
+
+fn score_from(query: &[char], text: &[char], start: usize) -> Option<u32> {
+    let mut score = 0;
+    let mut position = start;
+    let mut previous = None;
+
+    for &q in query {
+        let offset = text[position..].iter().position(|&c| c == q)?;
+        let index = position + offset;
+
+        score += 1;
+        if index == 0 {
+            score += 8;
+        } else if !text[index - 1].is_alphanumeric() {
+            score += 6;
+        }
+        if previous.is_some_and(|previous| previous + 1 == index) {
+            score += 4;
+        }
+
+        previous = Some(index);
+        position = index + 1;
+    }
+
+    Some(score)
+}
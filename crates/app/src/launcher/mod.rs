@@ -0,0 +1,366 @@
+mod frecency;
+mod fuzzy;
+
+use std::cmp::Reverse;
+use std::path::Path;
+use std::sync::Arc;
+
+use gpui::{FocusHandle, Global, KeyDownEvent, WindowHandle, img};
+use services::applications::Application;
+use tracing::error;
+use ui::prelude::*;
+
+use self::frecency::Frecency;
+use crate::clients::Clients;
+use crate::config::LauncherConfig;
+use crate::icons::find_icon;
+
+/// Caps how much launching an application often helps it rank, so that it doesn't
+/// beat a much better match.
+const MAX_FRECENCY_BONUS: u32 = 500;
+
+/// Opens and closes the launcher window.
+pub struct LauncherWindow {
+    config: LauncherConfig,
+    handle: Option<WindowHandle<Launcher>>,
+}
+
+impl Global for LauncherWindow {}
+
+impl LauncherWindow {
+    pub fn init(config: LauncherConfig, cx: &mut App) {
+        cx.set_global(Self {
+            config,
+            handle: None,
+        });
+    }
+
+    /// The new config applies the next time the launcher opens.
+    pub fn reload(config: LauncherConfig, cx: &mut App) {
+        cx.global_mut::<Self>().config = config;
+    }
+
+    pub fn is_open(cx: &App) -> bool {
+        cx.global::<Self>().handle.is_some()
+    }
+
+    pub fn toggle(cx: &mut App) {
+        if cx.global::<Self>().handle.is_some() {
+            Self::close(cx);
+        } else {
+            Self::open(cx);
+        }
+    }
+
+    pub fn open(cx: &mut App) {
+        if cx.global::<Self>().handle.is_some() {
+            return;
+        }
+
+        let config = cx.global::<Self>().config.clone();
+        let bounds = Bounds {
+            origin: point(px(0.), px(0.)),
+            size: size(px(config.width), px(config.height)),
+        };
+        // without an anchor the compositor centers the window
+        let layer_shell_settings = LayerShellSettings {
+            layer: Layer::Overlay,
+            keyboard_interactivity: KeyboardInteractivity::Exclusive,
+            namespace: "mgs-launcher".to_string(),
+            ..Default::default()
+        };
+        let opts = WindowOptions {
+            window_bounds: Some(WindowBounds::Windowed(bounds)),
+            kind: WindowKind::LayerShell(layer_shell_settings),
+            ..Default::default()
+        };
+
+        let handle = cx.open_window(opts, |window, cx| {
+            let launcher = cx.new(|cx| Launcher::new(config, cx));
+            window.focus(&launcher.read(cx).focus_handle);
+            launcher
+        });
+        match handle {
+            Ok(handle) => cx.global_mut::<Self>().handle = Some(handle),
+            Err(err) => error!("failed to open launcher window: {err:#}"),
+        }
+    }
+
+    pub fn close(cx: &mut App) {
+        if let Some(handle) = cx.global_mut::<Self>().handle.take() {
+            handle
+                .update(cx, |_, window, _| window.remove_window())
+                .ok();
+        }
+    }
+}
+
+struct Entry {
+    application: Application,
+    icon: Option<Arc<Path>>,
+}
+
+pub struct Launcher {
+    config: LauncherConfig,
+    focus_handle: FocusHandle,
+    query: String,
+    entries: Vec<Entry>,
+    frecency: Frecency,
+    /// Indices into `entries`, best match first.
+    matches: Vec<usize>,
+    /// Index into `matches`.
+    selected: usize,
+}
+
+impl Launcher {
+    fn new(config: LauncherConfig, cx: &mut Context<Self>) -> Self {
+        let applications = Clients::applications(cx);
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let Some(applications) = applications.await else {
+                return;
+            };
+
+            let applications = applications.get();
+            let (entries, frecency) = cx
+                .background_executor()
+                .spawn(async {
+                    let entries = applications
+                        .into_iter()
+                        .map(|application| Entry {
+                            icon: application
+                                .icon
+                                .as_deref()
+                                .and_then(find_icon)
+                                .map(Into::into),
+                            application,
+                        })
+                        .collect::<Vec<_>>();
+                    (entries, Frecency::load())
+                })
+                .await;
+
+            this.update(cx, |this, cx| {
+                this.entries = entries;
+                this.frecency = frecency;
+                this.update_matches();
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+
+        Self {
+            config,
+            focus_handle: cx.focus_handle(),
+            query: String::new(),
+            entries: Vec::new(),
+            frecency: Frecency::default(),
+            matches: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    fn update_matches(&mut self) {
+        let mut matches: Vec<(u32, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                let frecency = self.frecency.score(&entry.application.id);
+                let score = if self.query.is_empty() {
+                    frecency
+                } else {
+                    match_score(&self.query, &entry.application)? + frecency.min(MAX_FRECENCY_BONUS)
+                };
+                Some((score, i))
+            })
+            .collect();
+        // stable, so equal scores stay sorted by name
+        matches.sort_by_key(|&(score, _)| Reverse(score));
+
+        self.matches = matches
+            .into_iter()
+            .take(self.config.max_results)
+            .map(|(_, i)| i)
+            .collect();
+        self.selected = 0;
+    }
+
+    fn select(&mut self, delta: isize, cx: &mut Context<Self>) {
+        if self.matches.is_empty() {
+            return;
+        }
+
+        let len = self.matches.len() as isize;
+        self.selected = (self.selected as isize + delta).rem_euclid(len) as usize;
+        cx.notify();
+    }
+
+    fn launch(&mut self, index: usize, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(entry) = self.matches.get(index).map(|&i| &self.entries[i]) else {
+            return;
+        };
+
+        if let Err(err) = entry.application.launch(None, &self.config.terminal) {
+            error!("failed to launch {}: {err:#}", entry.application.id);
+            return;
+        }
+
+        self.frecency.record(&entry.application.id);
+        let frecency = self.frecency.clone();
+        cx.background_executor()
+            .spawn(async move {
+                if let Err(err) = frecency.save() {
+                    error!("failed to save launcher history: {err:#}");
+                }
+            })
+            .detach();
+
+        self.dismiss(window, cx);
+    }
+
+    fn dismiss(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        cx.global_mut::<LauncherWindow>().handle = None;
+        window.remove_window();
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let keystroke = &event.keystroke;
+        let ctrl = keystroke.modifiers.control;
+
+        match keystroke.key.as_str() {
+            "escape" => self.dismiss(window, cx),
+            "enter" => self.launch(self.selected, window, cx),
+            "up" => self.select(-1, cx),
+            "down" | "tab" => self.select(1, cx),
+            "p" if ctrl => self.select(-1, cx),
+            "n" if ctrl => self.select(1, cx),
+            "u" if ctrl => self.set_query(String::new(), cx),
+            "backspace" => {
+                let mut query = self.query.clone();
+                query.pop();
+                self.set_query(query, cx);
+            }
+            _ if ctrl || keystroke.modifiers.alt || keystroke.modifiers.platform => {}
+            _ => {
+                if let Some(text) = &keystroke.key_char {
+                    let query = format!("{}{text}", self.query);
+                    self.set_query(query, cx);
+                }
+            }
+        }
+    }
+
+    fn set_query(&mut self, query: String, cx: &mut Context<Self>) {
+        if self.query != query {
+            self.query = query;
+            self.update_matches();
+            cx.notify();
+        }
+    }
+
+    fn render_entry(
+        &self,
+        index: usize,
+        entry: &Entry,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let theme = cx.theme();
+
+        h_flex()
+            .id(index)
+            .gap_3()
+            .px_3()
+            .py_1()
+            .rounded(theme.radius)
+            .when(index == self.selected, |this| this.bg(theme.palette.hover))
+            .child(match &entry.icon {
+                Some(icon) => img(icon.clone()).size_6().flex_none().into_any_element(),
+                None => div().size_6().flex_none().into_any_element(),
+            })
+            .child(
+                v_flex()
+                    .min_w_0()
+                    .overflow_x_hidden()
+                    .whitespace_nowrap()
+                    .child(entry.application.name.clone())
+                    .when_some(
+                        entry.application.generic_name.clone(),
+                        |this, generic_name| {
+                            this.child(
+                                div()
+                                    .text_xs()
+                                    .text_color(theme.palette.subtext)
+                                    .child(generic_name),
+                            )
+                        },
+                    ),
+            )
+            .on_mouse_move(cx.listener(move |this, _, _, cx| {
+                if this.selected != index {
+                    this.selected = index;
+                    cx.notify();
+                }
+            }))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _, window, cx| this.launch(index, window, cx)),
+            )
+    }
+}
+
+impl Render for Launcher {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let entries: Vec<_> = self
+            .matches
+            .iter()
+            .enumerate()
+            .map(|(index, &i)| self.render_entry(index, &self.entries[i], cx))
+            .collect();
+
+        v_flex()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .size_full()
+            .p_3()
+            .gap_2()
+            .bg(theme.palette.background)
+            .text_color(theme.palette.text)
+            .font_family(theme.font_family.clone())
+            .font_weight(theme.font_weight)
+            .child(
+                h_flex()
+                    .gap_2()
+                    .px_3()
+                    .py_2()
+                    .rounded(theme.radius)
+                    .bg(theme.palette.surface)
+                    .child(Icon::new("icons/search.svg".into()).size(18.))
+                    .child(if self.query.is_empty() {
+                        div()
+                            .text_color(theme.palette.subtext)
+                            .child("Search applications")
+                    } else {
+                        div().child(self.query.clone())
+                    }),
+            )
+            .child(v_flex().gap_1().children(entries))
+    }
+}
+
+/// Scores an entry by its best matching field, the name weighing the most.
+fn match_score(query: &str, entry: &Application) -> Option<u32> {
+    let name = fuzzy::score(query, &entry.name).map(|score| score * 2);
+    let generic_name = entry
+        .generic_name
+        .as_deref()
+        .and_then(|generic_name| fuzzy::score(query, generic_name));
+    let keywords = entry
+        .keywords
+        .iter()
+        .filter_map(|keyword| fuzzy::score(query, keyword))
+        .max();
+
+    [name, generic_name, keywords].into_iter().flatten().max()
+}
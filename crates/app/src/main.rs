@@ -1,40 +1,157 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
 use assets::Assets;
-use status_bar::StatusBar;
+use bars::Bars;
+use bluetooth_agent::BluetoothAgentDialog;
+use bluetooth_popup::BluetoothPopupWindow;
+use clients::Clients;
+use config::Config;
+use futures::StreamExt;
+use launcher::LauncherWindow;
+use media_popup::MediaPopupWindow;
+use mixer_popup::MixerPopupWindow;
+use network_agent::NetworkAgentDialog;
+use notifications::{NotificationCenterWindow, NotificationPopups};
+use themes::Themes;
+use tracing::{error, info, warn};
+use tray_menu::TrayMenuWindow;
 use ui::prelude::*;
+use wifi_popup::WifiPopupWindow;
 
+mod bars;
+mod bluetooth_agent;
+mod bluetooth_popup;
+mod clients;
+mod config;
+mod dbus;
+mod icons;
+mod ipc;
+mod launcher;
+mod media_popup;
+mod mixer_popup;
+mod network_agent;
+mod notifications;
 mod status_bar;
+mod themes;
+mod tray_menu;
 mod widgets;
+mod wifi_popup;
 
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "msg") {
+        if let Err(err) = ipc::send(&args[1..]) {
+            eprintln!("{err:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let (config, path) = load_config();
+
     let assets = Assets {};
     let app = Application::new().with_assets(assets);
 
-    app.run(|cx: &mut App| {
-        let height = px(35.0);
-        let bounds = Bounds {
-            origin: point(px(0.), px(0.)),
-            // TODO: Make this dynamic
-            size: size(px(1440.), height),
-        };
-        let layer_shell_settings = LayerShellSettings {
-            layer: Layer::Top,
-            anchor: Anchor::TOP | Anchor::LEFT | Anchor::RIGHT,
-            exclusive_zone: Some(height),
-            keyboard_interactivity: KeyboardInteractivity::None,
-            namespace: "simple bar".to_string(),
-            ..Default::default()
-        };
-
-        let opts = WindowOptions {
-            window_bounds: Some(WindowBounds::Windowed(bounds)),
-            kind: WindowKind::LayerShell(layer_shell_settings),
-            ..Default::default()
-        };
-
-        cx.open_window(opts, |_, cx| cx.new(StatusBar::new))
-            .unwrap();
+    app.run(move |cx: &mut App| {
+        Clients::init(cx);
+        Themes::init(config.clone(), cx);
+        LauncherWindow::init(config.launcher.clone(), cx);
+        TrayMenuWindow::init(cx);
+        MediaPopupWindow::init(cx);
+        MixerPopupWindow::init(cx);
+        BluetoothPopupWindow::init(cx);
+        WifiPopupWindow::init(cx);
+        NetworkAgentDialog::init(cx);
+        BluetoothAgentDialog::init(cx);
+        NotificationPopups::init(config.notifications.clone(), cx);
+        NotificationCenterWindow::init(cx);
+        Bars::init(config.bar, cx);
+
+        let requests = ipc::handler(path.clone(), cx);
+        ipc::serve(requests.clone());
+        dbus::serve(requests, cx);
+        if let Some(path) = path {
+            watch_config(path, cx);
+        }
     });
 }
+
+/// Loads the config from `--config <path>`, or from the default location if it exists.
+///
+/// Falls back to the default config when the file can't be loaded, so a typo
+/// doesn't leave the desktop without a bar. The returned path is the one to watch
+/// for changes.
+fn load_config() -> (Config, Option<PathBuf>) {
+    let mut args = std::env::args().skip(1);
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-c" | "--config" => path = args.next().map(PathBuf::from),
+            _ => error!("unknown argument: {arg}"),
+        }
+    }
+
+    let Some(path) = path.or_else(Config::default_path) else {
+        info!("no config directory found, using defaults");
+        return (Config::default(), None);
+    };
+
+    if !path.exists() {
+        info!("{} doesn't exist, using defaults", path.display());
+        return (Config::default(), Some(path));
+    }
+
+    match Config::load(&path) {
+        Ok(config) => {
+            info!("loaded config from {}", path.display());
+            (config, Some(path))
+        }
+        Err(err) => {
+            error!("{err:#}, using defaults");
+            (Config::default(), Some(path))
+        }
+    }
+}
+
+/// Reloads the config whenever the file changes, keeping the current one when it's invalid.
+fn watch_config(path: PathBuf, cx: &mut App) {
+    let (watcher, mut changes) = match Config::watch(&path) {
+        Ok(watch) => watch,
+        Err(err) => {
+            warn!("not watching config for changes: {err:#}");
+            return;
+        }
+    };
+
+    cx.spawn(async move |cx: &mut AsyncApp| {
+        let _watcher = watcher;
+
+        while changes.next().await.is_some() {
+            // editors tend to save in several steps, let them settle
+            Timer::after(Duration::from_millis(100)).await;
+            while let Ok(Some(())) = changes.try_next() {}
+
+            if let Ok(Err(err)) = cx.update(|cx| reload_config(&path, cx)) {
+                error!("{err:#}, keeping the previous config");
+            }
+        }
+    })
+    .detach();
+}
+
+/// Applies the config at `path`, leaving the current one in place when it's invalid.
+fn reload_config(path: &Path, cx: &mut App) -> Result<()> {
+    let config = Config::load(path)?;
+    info!("reloading config from {}", path.display());
+    Themes::reload(config.clone(), cx);
+    LauncherWindow::reload(config.launcher.clone(), cx);
+    NotificationPopups::reload(config.notifications.clone(), cx);
+    Bars::reload(config.bar, cx);
+
+    Ok(())
+}
@@ -0,0 +1,529 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use futures_signals::signal::SignalExt;
+use futures_util::StreamExt;
+use gpui::{DisplayId, FocusHandle, Global, KeyDownEvent, Task, WindowHandle, img};
+use services::mpris::{self, LoopStatus, MprisData, PlaybackStatus, Player};
+use tracing::{error, warn};
+use ui::prelude::*;
+
+use crate::bars::Dropdown;
+use crate::widgets::media::Control;
+
+const WIDTH: f32 = 340.;
+const ART_SIZE: f32 = 72.;
+/// How often the position moves on while playing.
+const TICK: Duration = Duration::from_millis(250);
+/// How long the seek bar has to settle before the player is asked to seek, so that
+/// dragging it doesn't flood the player.
+const SEEK_DELAY: Duration = Duration::from_millis(150);
+
+/// `1:05`, or `1:01:05` past an hour.
+fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+/// Opens and closes the media popup, the controls of the active media player dropping
+/// down from the bar.
+pub struct MediaPopupWindow {
+    handle: Option<WindowHandle<MediaPopup>>,
+}
+
+impl Global for MediaPopupWindow {}
+
+impl MediaPopupWindow {
+    pub fn init(cx: &mut App) {
+        cx.set_global(Self { handle: None });
+    }
+
+    pub fn toggle(
+        subscriber: mpris::Subscriber,
+        display: Option<DisplayId>,
+        x: Pixels,
+        cx: &mut App,
+    ) {
+        if cx.global::<Self>().handle.is_some() {
+            Self::close(cx);
+        } else {
+            Self::open(subscriber, display, x, cx);
+        }
+    }
+
+    /// Shows the popup under the point `x` of the bar on `display`.
+    pub fn open(
+        subscriber: mpris::Subscriber,
+        display: Option<DisplayId>,
+        x: Pixels,
+        cx: &mut App,
+    ) {
+        Self::close(cx);
+
+        let opts = Dropdown::window_options(display, "mgs-media", cx);
+        let handle = cx.open_window(opts, |window, cx| {
+            let popup = cx.new(|cx| MediaPopup::new(subscriber, x, cx));
+            window.focus(&popup.read(cx).focus_handle);
+            popup
+        });
+        match handle {
+            Ok(handle) => cx.global_mut::<Self>().handle = Some(handle),
+            Err(err) => error!("failed to open media popup window: {err:#}"),
+        }
+    }
+
+    pub fn close(cx: &mut App) {
+        if let Some(handle) = cx.global::<Self>().handle {
+            handle
+                .update(cx, |popup, window, cx| popup.dismiss(window, cx))
+                .ok();
+        }
+    }
+}
+
+pub struct MediaPopup {
+    subscriber: mpris::Subscriber,
+    data: MprisData,
+    /// The art URL of the active player, and the file it was fetched into.
+    art: Option<(String, Option<PathBuf>)>,
+    /// In percent of the track length.
+    seek_bar: Entity<Slider>,
+    /// In percent.
+    volume: Entity<Slider>,
+    /// The seek waiting for the seek bar to settle.
+    seeking: Option<Task<()>>,
+    /// Where the popup was opened, in bar coordinates.
+    x: Pixels,
+    focus_handle: FocusHandle,
+}
+
+impl MediaPopup {
+    fn new(subscriber: mpris::Subscriber, x: Pixels, cx: &mut Context<Self>) -> Self {
+        let seek_bar = cx.new(|_| Slider::new().min(0.).max(100.).step(0.1));
+        let volume = cx.new(|_| Slider::new().min(0.).max(100.).step(1.));
+        cx.subscribe(&seek_bar, |this, _, event: &SliderEvent, cx| match event {
+            SliderEvent::Change(value) => this.seek(*value, cx),
+        })
+        .detach();
+        cx.subscribe(&volume, |this, _, event: &SliderEvent, cx| match event {
+            SliderEvent::Change(value) => this.set_volume(*value, cx),
+        })
+        .detach();
+
+        let signal = subscriber.subscribe();
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let mut signal = signal.to_stream();
+            while let Some(data) = signal.next().await {
+                let updated = this.update(cx, |this, cx| this.set_data(data, cx));
+                if updated.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        // players only tell where they are at when the position jumps
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            loop {
+                Timer::after(TICK).await;
+                let updated = this.update(cx, |this, cx| {
+                    let playing = this
+                        .data
+                        .active()
+                        .is_some_and(|player| player.status == PlaybackStatus::Playing);
+                    if playing {
+                        this.sync_seek_bar(cx);
+                        cx.notify();
+                    }
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        Self {
+            subscriber,
+            data: MprisData::default(),
+            art: None,
+            seek_bar,
+            volume,
+            seeking: None,
+            x,
+            focus_handle: cx.focus_handle(),
+        }
+    }
+
+    fn dismiss(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        cx.global_mut::<MediaPopupWindow>().handle = None;
+        window.remove_window();
+    }
+
+    fn set_data(&mut self, data: MprisData, cx: &mut Context<Self>) {
+        self.data = data;
+        self.sync_seek_bar(cx);
+
+        let player = self.data.active();
+        if let Some(volume) = player.and_then(|player| player.volume) {
+            self.volume.update(cx, |slider, cx| {
+                slider.set_value((volume * 100.) as f32, cx)
+            });
+        }
+
+        let url = player.and_then(|player| player.metadata.art_url.clone());
+        if self.art.as_ref().map(|(current, _)| current) != url.as_ref() {
+            self.art = None;
+            if let Some(url) = url {
+                self.fetch_art(url, cx);
+            }
+        }
+        cx.notify();
+    }
+
+    fn fetch_art(&mut self, url: String, cx: &mut Context<Self>) {
+        self.art = Some((url.clone(), None));
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let path = mpris::art(&url).await;
+            this.update(cx, |this, cx| {
+                // the track may have changed in the meantime
+                if let Some((current, art)) = &mut this.art {
+                    if *current == url {
+                        *art = path;
+                        cx.notify();
+                    }
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    /// Moves the seek bar to where the track is at, unless it's being dragged around.
+    fn sync_seek_bar(&mut self, cx: &mut Context<Self>) {
+        if self.seeking.is_some() {
+            return;
+        }
+        let Some(player) = self.data.active() else {
+            return;
+        };
+        let Some(length) = player.metadata.length else {
+            return;
+        };
+
+        let percent = player.current_position().as_secs_f32() / length.as_secs_f32() * 100.;
+        self.seek_bar
+            .update(cx, |slider, cx| slider.set_value(percent, cx));
+    }
+
+    fn seek(&mut self, percent: f32, cx: &mut Context<Self>) {
+        let Some(player) = self.data.active() else {
+            return;
+        };
+        let Some(length) = player.metadata.length.filter(|_| player.can_seek) else {
+            return;
+        };
+
+        let name = player.name.clone();
+        let position = length.mul_f32(percent / 100.);
+        let subscriber = self.subscriber.clone();
+        // replacing the pending seek drops it
+        self.seeking = Some(
+            cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+                Timer::after(SEEK_DELAY).await;
+                if let Err(err) = subscriber.set_position(&name, position).await {
+                    warn!("media player {name} didn't seek: {err:#}");
+                }
+                this.update(cx, |this, _| this.seeking = None).ok();
+            }),
+        );
+    }
+
+    fn set_volume(&mut self, percent: f32, cx: &mut Context<Self>) {
+        let Some(name) = self.data.active.clone() else {
+            return;
+        };
+        let subscriber = self.subscriber.clone();
+        cx.spawn(async move |_, _| {
+            if let Err(err) = subscriber.set_volume(&name, percent as f64 / 100.).await {
+                warn!("failed to set the volume of media player {name}: {err:#}");
+            }
+        })
+        .detach();
+    }
+
+    fn control(&mut self, control: Control, cx: &mut Context<Self>) {
+        let Some(name) = self.data.active.clone() else {
+            return;
+        };
+        let subscriber = self.subscriber.clone();
+        cx.spawn(async move |_, _| control.send(&subscriber, &name).await)
+            .detach();
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        if event.keystroke.key == "escape" {
+            self.dismiss(window, cx);
+        }
+    }
+
+    /// One button per player, to pick the one the popup controls.
+    fn render_players(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+
+        h_flex()
+            .flex_wrap()
+            .gap_1()
+            .children(self.data.players.iter().map(|player| {
+                let active = self.data.active.as_ref() == Some(&player.name);
+                let name = player.name.clone();
+                div()
+                    .px_2()
+                    .py_0p5()
+                    .rounded(theme.radius)
+                    .text_sm()
+                    .map(|this| {
+                        if active {
+                            this.bg(theme.palette.accent)
+                                .text_color(theme.palette.background)
+                        } else {
+                            this.bg(theme.palette.surface)
+                                .hover(|this| this.bg(theme.palette.hover))
+                        }
+                    })
+                    .child(player.identity.clone())
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _, _, cx| {
+                            cx.stop_propagation();
+                            this.subscriber.set_active(&name);
+                        }),
+                    )
+            }))
+    }
+
+    fn render_player(&self, player: &Player, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let metadata = &player.metadata;
+        let art = self.art.as_ref().and_then(|(_, path)| path.clone());
+
+        let button = |icon: &'static str,
+                      enabled: bool,
+                      on: bool,
+                      control: Control,
+                      cx: &mut Context<Self>| {
+            let color = match (enabled, on) {
+                (false, _) => theme.palette.subtext,
+                (true, true) => theme.palette.accent,
+                (true, false) => theme.palette.text,
+            };
+            div()
+                .p_1()
+                .rounded(theme.radius)
+                .when(enabled, |this| {
+                    this.hover(|this| this.bg(theme.palette.hover))
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(move |this, _, _, cx| {
+                                cx.stop_propagation();
+                                this.control(control, cx);
+                            }),
+                        )
+                })
+                .child(Icon::new(icon.into()).size(18.).color(color))
+        };
+        let (play_pause, can_play_pause) = if player.status == PlaybackStatus::Playing {
+            ("icons/pause.svg", player.can_pause)
+        } else {
+            ("icons/play.svg", player.can_play)
+        };
+        let shuffle = player.shuffle.map(|shuffle| {
+            button(
+                "icons/shuffle.svg",
+                player.can_control,
+                shuffle,
+                Control::Shuffle(!shuffle),
+                cx,
+            )
+        });
+        let loop_status = player.loop_status.map(|status| {
+            let icon = if status == LoopStatus::Track {
+                "icons/repeat-1.svg"
+            } else {
+                "icons/repeat.svg"
+            };
+            button(
+                icon,
+                player.can_control,
+                status != LoopStatus::None,
+                Control::Loop(status.next()),
+                cx,
+            )
+        });
+        let previous = button(
+            "icons/skip-back.svg",
+            player.can_go_previous,
+            false,
+            Control::Previous,
+            cx,
+        );
+        let play_pause = button(play_pause, can_play_pause, false, Control::PlayPause, cx);
+        let next = button(
+            "icons/skip-forward.svg",
+            player.can_go_next,
+            false,
+            Control::Next,
+            cx,
+        );
+
+        v_flex()
+            .gap_3()
+            .child(
+                h_flex()
+                    .gap_3()
+                    .child(
+                        div()
+                            .size(px(ART_SIZE))
+                            .flex_none()
+                            .flex()
+                            .items_center()
+                            .justify_center()
+                            .rounded(theme.radius)
+                            .bg(theme.palette.surface)
+                            .overflow_hidden()
+                            .child(match art {
+                                Some(path) => img(path).size(px(ART_SIZE)).into_any_element(),
+                                None => Icon::new("icons/music.svg".into())
+                                    .size(28.)
+                                    .color(theme.palette.subtext)
+                                    .into_any_element(),
+                            }),
+                    )
+                    .child(
+                        v_flex()
+                            .flex_1()
+                            .min_w_0()
+                            .gap_0p5()
+                            .child(
+                                div().overflow_hidden().whitespace_nowrap().child(
+                                    metadata
+                                        .title
+                                        .clone()
+                                        .unwrap_or_else(|| player.identity.clone()),
+                                ),
+                            )
+                            .when(!metadata.artists.is_empty(), |this| {
+                                this.child(
+                                    div()
+                                        .text_sm()
+                                        .text_color(theme.palette.subtext)
+                                        .overflow_hidden()
+                                        .whitespace_nowrap()
+                                        .child(metadata.artists.join(", ")),
+                                )
+                            })
+                            .when_some(metadata.album.clone(), |this, album| {
+                                this.child(
+                                    div()
+                                        .text_xs()
+                                        .text_color(theme.palette.subtext)
+                                        .overflow_hidden()
+                                        .whitespace_nowrap()
+                                        .child(album),
+                                )
+                            }),
+                    ),
+            )
+            .when_some(metadata.length, |this, length| {
+                this.child(
+                    v_flex()
+                        .gap_1()
+                        .when(player.can_seek, |this| {
+                            this.child(div().w_full().child(self.seek_bar.clone()))
+                        })
+                        .child(
+                            h_flex()
+                                .justify_between()
+                                .text_xs()
+                                .text_color(theme.palette.subtext)
+                                .child(format_time(player.current_position()))
+                                .child(format_time(length)),
+                        ),
+                )
+            })
+            .child(
+                h_flex()
+                    .justify_center()
+                    .gap_3()
+                    .children(shuffle)
+                    .child(previous)
+                    .child(play_pause)
+                    .child(next)
+                    .children(loop_status),
+            )
+            .when(player.volume.is_some(), |this| {
+                this.child(
+                    h_flex()
+                        .gap_2()
+                        .child(
+                            Icon::new("icons/volume-medium.svg".into())
+                                .size(16.)
+                                .color(theme.palette.subtext),
+                        )
+                        .child(div().flex_1().child(self.volume.clone())),
+                )
+            })
+    }
+}
+
+impl Render for MediaPopup {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+
+        let content = match self.data.active().cloned() {
+            Some(player) => self.render_player(&player, cx).into_any_element(),
+            None => div()
+                .py_6()
+                .flex()
+                .justify_center()
+                .text_color(theme.palette.subtext)
+                .child("Nothing playing")
+                .into_any_element(),
+        };
+        let panel = v_flex()
+            .w(px(WIDTH))
+            .p_3()
+            .gap_3()
+            .rounded(theme.radius)
+            .border_1()
+            .border_color(theme.palette.border)
+            .bg(theme.palette.background)
+            .occlude()
+            .when(self.data.players.len() > 1, |this| {
+                this.child(self.render_players(cx))
+            })
+            .child(content);
+
+        div()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .size_full()
+            .text_color(theme.palette.text)
+            .font_family(theme.font_family.clone())
+            .font_weight(theme.font_weight)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _, window, cx| this.dismiss(window, cx)),
+            )
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(|this, _, window, cx| this.dismiss(window, cx)),
+            )
+            .child(Dropdown::new(self.x, window, cx).place(panel))
+    }
+}
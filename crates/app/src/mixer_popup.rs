@@ -0,0 +1,690 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use gpui::{AnyElement, DisplayId, FocusHandle, Global, KeyDownEvent, WindowHandle, img};
+use services::audio::card::Card;
+use services::audio::sink::Sink;
+use services::audio::sink_input::SinkInput;
+use services::audio::{self, Client};
+use services::lock;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{error, warn};
+use ui::prelude::*;
+
+use crate::bars::Dropdown;
+use crate::icons::find_icon;
+
+const WIDTH: f32 = 340.;
+const MAX_HEIGHT: f32 = 420.;
+const ICON_SIZE: f32 = 20.;
+
+/// Opens and closes the mixer, the volume of each playing application dropping down
+/// from the bar.
+pub struct MixerPopupWindow {
+    handle: Option<WindowHandle<MixerPopup>>,
+}
+
+impl Global for MixerPopupWindow {}
+
+impl MixerPopupWindow {
+    pub fn init(cx: &mut App) {
+        cx.set_global(Self { handle: None });
+    }
+
+    pub fn toggle(
+        client: Arc<Client>,
+        move_inputs: bool,
+        display: Option<DisplayId>,
+        x: Pixels,
+        cx: &mut App,
+    ) {
+        if cx.global::<Self>().handle.is_some() {
+            Self::close(cx);
+        } else {
+            Self::open(client, move_inputs, display, x, cx);
+        }
+    }
+
+    /// Shows the popup under the point `x` of the bar on `display`, moving the playing
+    /// applications along when another output is picked if `move_inputs`.
+    pub fn open(
+        client: Arc<Client>,
+        move_inputs: bool,
+        display: Option<DisplayId>,
+        x: Pixels,
+        cx: &mut App,
+    ) {
+        Self::close(cx);
+
+        let opts = Dropdown::window_options(display, "mgs-mixer", cx);
+        let handle = cx.open_window(opts, |window, cx| {
+            let popup = cx.new(|cx| MixerPopup::new(client, move_inputs, x, cx));
+            window.focus(&popup.read(cx).focus_handle);
+            popup
+        });
+        match handle {
+            Ok(handle) => cx.global_mut::<Self>().handle = Some(handle),
+            Err(err) => error!("failed to open mixer popup window: {err:#}"),
+        }
+    }
+
+    pub fn close(cx: &mut App) {
+        if let Some(handle) = cx.global::<Self>().handle {
+            handle
+                .update(cx, |popup, window, cx| popup.dismiss(window, cx))
+                .ok();
+        }
+    }
+}
+
+/// A playing application, with its volume slider.
+struct Row {
+    input: SinkInput,
+    slider: Entity<Slider>,
+}
+
+pub struct MixerPopup {
+    client: Arc<Client>,
+    /// The default sink, which the master slider drives.
+    sink: Option<Sink>,
+    sink_slider: Entity<Slider>,
+    /// All the outputs, to pick the default one from.
+    sinks: Vec<Sink>,
+    cards: Vec<Card>,
+    /// Whether the outputs and the card profiles are listed.
+    picking: bool,
+    move_inputs: bool,
+    rows: Vec<Row>,
+    /// The icon files found for the icon names of the applications.
+    icons: HashMap<String, Option<PathBuf>>,
+    /// Where the popup was opened, in bar coordinates.
+    x: Pixels,
+    focus_handle: FocusHandle,
+}
+
+impl MixerPopup {
+    fn new(client: Arc<Client>, move_inputs: bool, x: Pixels, cx: &mut Context<Self>) -> Self {
+        let sink_slider = cx.new(|_| Slider::new().min(0.).max(100.).step(1.));
+        cx.subscribe(
+            &sink_slider,
+            |this, _, event: &SliderEvent, cx| match event {
+                SliderEvent::Change(value) => {
+                    if let Some(sink) = &this.sink {
+                        let (name, value) = (sink.name.clone(), *value);
+                        this.request(
+                            move |client| async move { client.set_sink_volume(&name, value).await },
+                            cx,
+                        );
+                    }
+                }
+            },
+        )
+        .detach();
+
+        let mut events = client.subscribe();
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => Some(event),
+                    // missed events are caught up on from the client
+                    Err(RecvError::Lagged(_)) => None,
+                    Err(RecvError::Closed) => break,
+                };
+                let updated = this.update(cx, |this, cx| match event {
+                    Some(event) => this.on_event(event, cx),
+                    None => this.sync(cx),
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        let mut popup = Self {
+            client,
+            sink: None,
+            sink_slider,
+            sinks: Vec::new(),
+            cards: Vec::new(),
+            picking: false,
+            move_inputs,
+            rows: Vec::new(),
+            icons: HashMap::new(),
+            x,
+            focus_handle: cx.focus_handle(),
+        };
+        popup.sync(cx);
+        popup
+    }
+
+    fn dismiss(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        cx.global_mut::<MixerPopupWindow>().handle = None;
+        window.remove_window();
+    }
+
+    fn on_event(&mut self, event: audio::Event, cx: &mut Context<Self>) {
+        match event {
+            audio::Event::AddInput(input) | audio::Event::UpdateInput(input) => {
+                self.set_input(input, cx);
+            }
+            audio::Event::RemoveInput(index) => {
+                self.rows.retain(|row| row.input.index != index);
+            }
+            audio::Event::AddSink(_)
+            | audio::Event::UpdateSink(_)
+            | audio::Event::RemoveSink(_) => {
+                self.sync_sinks(cx);
+            }
+            audio::Event::AddCard(_)
+            | audio::Event::UpdateCard(_)
+            | audio::Event::RemoveCard(_) => self.sync_cards(),
+            audio::Event::Connected | audio::Event::Disconnected => self.sync(cx),
+            _ => return,
+        }
+        cx.notify();
+    }
+
+    /// Catches up on the sink and the applications from the client.
+    fn sync(&mut self, cx: &mut Context<Self>) {
+        self.sync_sinks(cx);
+        self.sync_cards();
+
+        let inputs = self.client.sink_inputs();
+        let inputs = lock!(inputs).clone();
+        self.rows
+            .retain(|row| inputs.iter().any(|input| input.index == row.input.index));
+        for input in inputs {
+            self.set_input(input, cx);
+        }
+        cx.notify();
+    }
+
+    fn sync_sinks(&mut self, cx: &mut Context<Self>) {
+        let sinks = self.client.sinks();
+        self.sinks = lock!(sinks).clone();
+        let sink = self.client.default_sink();
+        if let Some(sink) = &sink {
+            self.sink_slider
+                .update(cx, |slider, cx| slider.set_value(sink.volume, cx));
+        }
+        self.sink = sink;
+    }
+
+    fn sync_cards(&mut self) {
+        let cards = self.client.cards();
+        self.cards = lock!(cards).clone();
+    }
+
+    fn set_input(&mut self, input: SinkInput, cx: &mut Context<Self>) {
+        if let Some(icon_name) = &input.icon_name {
+            self.icons
+                .entry(icon_name.clone())
+                .or_insert_with(|| find_icon(icon_name));
+        }
+
+        if let Some(row) = self
+            .rows
+            .iter_mut()
+            .find(|row| row.input.index == input.index)
+        {
+            row.slider
+                .update(cx, |slider, cx| slider.set_value(input.volume, cx));
+            row.input = input;
+            return;
+        }
+
+        let index = input.index;
+        let slider = cx.new(|_| {
+            Slider::new()
+                .min(0.)
+                .max(100.)
+                .step(1.)
+                .default_value(input.volume)
+        });
+        cx.subscribe(
+            &slider,
+            move |this, _, event: &SliderEvent, cx| match event {
+                SliderEvent::Change(value) => {
+                    let value = *value;
+                    this.request(
+                        move |client| async move { client.set_input_volume(index, value).await },
+                        cx,
+                    );
+                }
+            },
+        )
+        .detach();
+        self.rows.push(Row { input, slider });
+    }
+
+    /// Asks the server for a change, which comes back as events.
+    fn request<F>(&self, request: impl FnOnce(Arc<Client>) -> F, cx: &mut Context<Self>)
+    where
+        F: Future<Output = anyhow::Result<()>> + 'static,
+    {
+        let request = request(self.client.clone());
+        cx.spawn(async move |_, _| {
+            if let Err(err) = request.await {
+                warn!("{err:#}");
+            }
+        })
+        .detach();
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        if event.keystroke.key == "escape" {
+            self.dismiss(window, cx);
+        }
+    }
+
+    fn mute_button(
+        &self,
+        muted: bool,
+        on_click: impl Fn(&mut Self, &mut Context<Self>) + 'static,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let theme = cx.theme().clone();
+        let (icon, color) = if muted {
+            ("icons/volume-mute.svg", theme.palette.subtext)
+        } else {
+            ("icons/volume-high.svg", theme.palette.text)
+        };
+
+        div()
+            .p_1()
+            .rounded(theme.radius)
+            .hover(|this| this.bg(theme.palette.hover))
+            .child(Icon::new(icon.into()).size(16.).color(color))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _, _, cx| {
+                    cx.stop_propagation();
+                    on_click(this, cx);
+                    cx.notify();
+                }),
+            )
+            .into_any_element()
+    }
+
+    fn render_sink(&self, sink: &Sink, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let name = sink.name.clone();
+        let muted = sink.muted;
+
+        v_flex()
+            .gap_1()
+            .child(
+                h_flex()
+                    .gap_1()
+                    .rounded(theme.radius)
+                    .text_sm()
+                    .text_color(theme.palette.subtext)
+                    .hover(|this| this.text_color(theme.palette.text))
+                    .child(
+                        div()
+                            .flex_1()
+                            .min_w_0()
+                            .overflow_hidden()
+                            .whitespace_nowrap()
+                            .child(sink.description.clone()),
+                    )
+                    .child(
+                        Icon::new("icons/chevrons-up-down.svg".into())
+                            .size(14.)
+                            .color(theme.palette.subtext),
+                    )
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _, _, cx| {
+                            cx.stop_propagation();
+                            this.picking = !this.picking;
+                            cx.notify();
+                        }),
+                    ),
+            )
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(self.mute_button(
+                        muted,
+                        move |this, cx| {
+                            let name = name.clone();
+                            this.request(
+                                    move |client| async move {
+                                        client.set_sink_muted(&name, !muted).await
+                                    },
+                                    cx,
+                                );
+                            if let Some(sink) = &mut this.sink {
+                                sink.muted = !muted;
+                            }
+                        },
+                        cx,
+                    ))
+                    .child(div().flex_1().child(self.sink_slider.clone()))
+                    .child(
+                        div()
+                            .w_8()
+                            .text_right()
+                            .text_sm()
+                            .child(format!("{:.0}", sink.volume)),
+                    ),
+            )
+    }
+
+    fn chip(
+        &self,
+        label: String,
+        active: bool,
+        on_click: impl Fn(&mut Self, &mut Context<Self>) + 'static,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let theme = cx.theme().clone();
+
+        div()
+            .px_2()
+            .py_0p5()
+            .rounded(theme.radius)
+            .text_xs()
+            .map(|this| {
+                if active {
+                    this.bg(theme.palette.accent)
+                        .text_color(theme.palette.background)
+                } else {
+                    this.bg(theme.palette.surface)
+                        .hover(|this| this.bg(theme.palette.hover))
+                }
+            })
+            .child(label)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _, _, cx| {
+                    cx.stop_propagation();
+                    on_click(this, cx);
+                    cx.notify();
+                }),
+            )
+            .into_any_element()
+    }
+
+    /// The outputs to pick the default one from, the ports of the default one, and the
+    /// profiles of the cards.
+    fn render_picker(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let default = self.sink.as_ref().map(|sink| sink.index);
+
+        let sinks: Vec<_> = self
+            .sinks
+            .iter()
+            .map(|sink| {
+                let active = Some(sink.index) == default;
+                let picked = sink.clone();
+                h_flex()
+                    .px_2()
+                    .py_1()
+                    .gap_2()
+                    .rounded(theme.radius)
+                    .text_sm()
+                    .hover(|this| this.bg(theme.palette.hover))
+                    .child(
+                        div()
+                            .flex_1()
+                            .min_w_0()
+                            .overflow_hidden()
+                            .whitespace_nowrap()
+                            .child(sink.description.clone()),
+                    )
+                    .when(active, |this| {
+                        this.child(
+                            Icon::new("icons/check.svg".into())
+                                .size(14.)
+                                .color(theme.palette.accent),
+                        )
+                    })
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _, _, cx| {
+                            cx.stop_propagation();
+                            if !active {
+                                let (picked, move_inputs) = (picked.clone(), this.move_inputs);
+                                this.request(
+                                    move |client| async move {
+                                        client.switch_sink(&picked, move_inputs).await
+                                    },
+                                    cx,
+                                );
+                            }
+                        }),
+                    )
+            })
+            .collect();
+
+        let ports: Vec<_> = match &self.sink {
+            Some(sink) if sink.ports.len() > 1 => sink
+                .ports
+                .iter()
+                .filter(|port| port.available)
+                .map(|port| {
+                    let active = sink.active_port.as_ref() == Some(&port.name);
+                    let (name, port_name) = (sink.name.clone(), port.name.clone());
+                    self.chip(
+                        port.description.clone(),
+                        active,
+                        move |this, cx| {
+                            let (name, port_name) = (name.clone(), port_name.clone());
+                            this.request(
+                                move |client| async move {
+                                    client.set_sink_port(&name, &port_name).await
+                                },
+                                cx,
+                            );
+                        },
+                        cx,
+                    )
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let cards: Vec<_> = self
+            .cards
+            .iter()
+            .filter(|card| card.profiles.len() > 1)
+            .map(|card| {
+                let profiles: Vec<_> = card
+                    .profiles
+                    .iter()
+                    .filter(|profile| profile.available)
+                    .map(|profile| {
+                        let active = card.active_profile.as_ref() == Some(&profile.name);
+                        let (index, name) = (card.index, profile.name.clone());
+                        self.chip(
+                            profile.description.clone(),
+                            active,
+                            move |this, cx| {
+                                let name = name.clone();
+                                this.request(
+                                    move |client| async move {
+                                        client.set_card_profile(index, &name).await
+                                    },
+                                    cx,
+                                );
+                            },
+                            cx,
+                        )
+                    })
+                    .collect();
+                v_flex()
+                    .gap_1()
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(theme.palette.subtext)
+                            .child(card.description.clone()),
+                    )
+                    .child(h_flex().flex_wrap().gap_1().children(profiles))
+            })
+            .collect();
+
+        v_flex()
+            .gap_2()
+            .child(v_flex().children(sinks))
+            .when(!ports.is_empty(), |this| {
+                this.child(h_flex().flex_wrap().gap_1().children(ports))
+            })
+            .children(cards)
+    }
+
+    fn render_row(&self, row: &Row, cx: &mut Context<Self>) -> AnyElement {
+        let theme = cx.theme().clone();
+        let input = &row.input;
+        let index = input.index;
+        let muted = input.muted;
+        let icon = input
+            .icon_name
+            .as_ref()
+            .and_then(|icon_name| self.icons.get(icon_name).cloned().flatten());
+        let name = input
+            .application_name
+            .clone()
+            .unwrap_or_else(|| input.name.clone());
+
+        h_flex()
+            .gap_2()
+            .child(
+                div()
+                    .size(px(ICON_SIZE))
+                    .flex_none()
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .child(match icon {
+                        Some(path) => img(path).size(px(ICON_SIZE)).into_any_element(),
+                        None => Icon::new("icons/music.svg".into())
+                            .size(16.)
+                            .color(theme.palette.subtext)
+                            .into_any_element(),
+                    }),
+            )
+            .child(
+                v_flex()
+                    .flex_1()
+                    .min_w_0()
+                    .gap_0p5()
+                    .child(
+                        div()
+                            .text_sm()
+                            .overflow_hidden()
+                            .whitespace_nowrap()
+                            .child(name),
+                    )
+                    .child(if input.can_set_volume {
+                        div().child(row.slider.clone())
+                    } else {
+                        div()
+                            .text_xs()
+                            .text_color(theme.palette.subtext)
+                            .child("Fixed volume")
+                    }),
+            )
+            .child(self.mute_button(
+                muted,
+                move |this, cx| {
+                    this.request(
+                        move |client| async move { client.set_input_muted(index, !muted).await },
+                        cx,
+                    );
+                    if let Some(row) = this.rows.iter_mut().find(|row| row.input.index == index) {
+                        row.input.muted = !muted;
+                    }
+                },
+                cx,
+            ))
+            .child(
+                div()
+                    .w_8()
+                    .text_right()
+                    .text_sm()
+                    .child(format!("{:.0}", input.volume)),
+            )
+            .into_any_element()
+    }
+}
+
+impl Render for MixerPopup {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+
+        let sink = self
+            .sink
+            .clone()
+            .map(|sink| self.render_sink(&sink, cx).into_any_element());
+        let picker = self
+            .picking
+            .then(|| self.render_picker(cx).into_any_element());
+        let rows: Vec<_> = self
+            .rows
+            .iter()
+            .map(|row| self.render_row(row, cx))
+            .collect();
+        let applications = if rows.is_empty() {
+            div()
+                .py_4()
+                .flex()
+                .justify_center()
+                .text_color(theme.palette.subtext)
+                .child(if self.client.is_connected() {
+                    "No application is playing"
+                } else {
+                    "Not connected to the audio server"
+                })
+                .into_any_element()
+        } else {
+            v_flex()
+                .id("mixer-applications")
+                .max_h(px(MAX_HEIGHT))
+                .overflow_y_scroll()
+                .gap_2()
+                .children(rows)
+                .into_any_element()
+        };
+
+        let panel = v_flex()
+            .w(px(WIDTH))
+            .p_3()
+            .gap_3()
+            .rounded(theme.radius)
+            .border_1()
+            .border_color(theme.palette.border)
+            .bg(theme.palette.background)
+            .occlude()
+            .children(sink)
+            .children(picker)
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(theme.palette.subtext)
+                    .child("Applications"),
+            )
+            .child(applications);
+
+        div()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .size_full()
+            .text_color(theme.palette.text)
+            .font_family(theme.font_family.clone())
+            .font_weight(theme.font_weight)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _, window, cx| this.dismiss(window, cx)),
+            )
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(|this, _, window, cx| this.dismiss(window, cx)),
+            )
+            .child(Dropdown::new(self.x, window, cx).place(panel))
+    }
+}
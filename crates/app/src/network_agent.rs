@@ -0,0 +1,273 @@
+use futures_signals::signal::SignalExt;
+use futures_util::StreamExt;
+use gpui::{FocusHandle, Global, KeyDownEvent, WindowHandle};
+use services::network::{self, SecretKind, SecretReply, SecretRequest};
+use tracing::error;
+use ui::prelude::*;
+
+use crate::clients::{Clients, SharedClient};
+
+const WIDTH: f32 = 360.;
+const HEIGHT: f32 = 210.;
+/// The longest WPA passphrase, which is plenty for the other passwords too.
+const MAX_PASSWORD: usize = 63;
+
+/// Asks for the passwords NetworkManager lacks to bring a connection up, in a dialog
+/// taking the keyboard until it's answered.
+pub struct NetworkAgentDialog {
+    handle: Option<WindowHandle<SecretDialog>>,
+}
+
+impl Global for NetworkAgentDialog {}
+
+impl NetworkAgentDialog {
+    /// Registers the secret agent, whether or not a bar shows the network.
+    pub fn init(cx: &mut App) {
+        cx.set_global(Self { handle: None });
+        Clients::network(cx);
+    }
+
+    /// Follows the requests of the agent `subscriber` registers once it's connected, for
+    /// every client `Clients` creates.
+    pub fn follow(subscriber: SharedClient<network::Subscriber>, cx: &mut App) {
+        cx.spawn(async move |cx: &mut AsyncApp| {
+            if let Some(subscriber) = subscriber.await {
+                cx.update(|cx| Self::watch(subscriber, cx)).ok();
+            }
+        })
+        .detach();
+    }
+
+    /// Follows the requests of the secret agent of `subscriber`.
+    fn watch(subscriber: network::Subscriber, cx: &mut App) {
+        cx.spawn(async move |cx: &mut AsyncApp| {
+            let mut signal = subscriber.subscribe_secret_request().to_stream();
+            while let Some(request) = signal.next().await {
+                let subscriber = subscriber.clone();
+                if cx.update(|cx| Self::show(subscriber, request, cx)).is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn show(subscriber: network::Subscriber, request: Option<SecretRequest>, cx: &mut App) {
+        let handle = cx.global::<Self>().handle;
+        match (request, handle) {
+            (Some(request), Some(handle)) => {
+                handle
+                    .update(cx, |dialog, _, cx| dialog.set_request(request, cx))
+                    .ok();
+            }
+            (Some(request), None) => Self::open(subscriber, request, cx),
+            (None, Some(handle)) => {
+                cx.global_mut::<Self>().handle = None;
+                handle
+                    .update(cx, |_, window, _| window.remove_window())
+                    .ok();
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn open(subscriber: network::Subscriber, request: SecretRequest, cx: &mut App) {
+        let bounds = Bounds {
+            origin: point(px(0.), px(0.)),
+            size: size(px(WIDTH), px(HEIGHT)),
+        };
+        // without an anchor the compositor centers the window
+        let layer_shell_settings = LayerShellSettings {
+            layer: Layer::Overlay,
+            keyboard_interactivity: KeyboardInteractivity::Exclusive,
+            namespace: "mgs-network-agent".to_string(),
+            ..Default::default()
+        };
+        let opts = WindowOptions {
+            window_bounds: Some(WindowBounds::Windowed(bounds)),
+            kind: WindowKind::LayerShell(layer_shell_settings),
+            ..Default::default()
+        };
+
+        let handle = cx.open_window(opts, |window, cx| {
+            let dialog = cx.new(|cx| SecretDialog::new(subscriber, request, cx));
+            window.focus(&dialog.read(cx).focus_handle);
+            dialog
+        });
+        match handle {
+            Ok(handle) => cx.global_mut::<Self>().handle = Some(handle),
+            Err(err) => error!("failed to open network agent dialog: {err:#}"),
+        }
+    }
+}
+
+pub struct SecretDialog {
+    subscriber: network::Subscriber,
+    request: SecretRequest,
+    /// The password typed so far.
+    input: String,
+    focus_handle: FocusHandle,
+}
+
+impl SecretDialog {
+    fn new(
+        subscriber: network::Subscriber,
+        request: SecretRequest,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        Self {
+            subscriber,
+            request,
+            input: String::new(),
+            focus_handle: cx.focus_handle(),
+        }
+    }
+
+    fn set_request(&mut self, request: SecretRequest, cx: &mut Context<Self>) {
+        if self.request.id != request.id {
+            self.input.clear();
+        }
+        self.request = request;
+        cx.notify();
+    }
+
+    /// Answers the request, the dialog closes once the agent is done with it.
+    fn reply(&mut self, reply: SecretReply) {
+        self.subscriber.reply_secret(self.request.id, reply);
+    }
+
+    fn submit(&mut self) {
+        if !self.input.is_empty() {
+            self.reply(SecretReply::Secret(self.input.clone()));
+        }
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        let keystroke = &event.keystroke;
+        match keystroke.key.as_str() {
+            "escape" => self.reply(SecretReply::Cancel),
+            "enter" => self.submit(),
+            "backspace" => {
+                self.input.pop();
+                cx.notify();
+            }
+            _ if keystroke.modifiers.control
+                || keystroke.modifiers.alt
+                || keystroke.modifiers.platform => {}
+            _ => {
+                let Some(text) = &keystroke.key_char else {
+                    return;
+                };
+                if self.input.len() + text.len() <= MAX_PASSWORD {
+                    self.input.push_str(text);
+                    cx.notify();
+                }
+            }
+        }
+    }
+
+    /// What the dialog asks, and what is typed.
+    fn message(&self) -> (String, &'static str) {
+        let name = &self.request.name;
+        match &self.request.kind {
+            SecretKind::Wifi => (format!("Enter the password of {name}."), "Password"),
+            SecretKind::Wep => (format!("Enter the WEP key of {name}."), "Key"),
+            SecretKind::Enterprise { identity } if !identity.is_empty() => (
+                format!("Enter the password of {identity} on {name}."),
+                "Password",
+            ),
+            SecretKind::Enterprise { .. } | SecretKind::Password => {
+                (format!("Enter the password of {name}."), "Password")
+            }
+            SecretKind::Vpn => (format!("Enter the password of the VPN {name}."), "Password"),
+        }
+    }
+}
+
+impl Render for SecretDialog {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let (message, placeholder) = self.message();
+        let icon = match self.request.kind {
+            SecretKind::Wifi | SecretKind::Wep | SecretKind::Enterprise { .. } => {
+                "icons/wifi-full.svg"
+            }
+            SecretKind::Vpn | SecretKind::Password => "icons/lock.svg",
+        };
+
+        let button = |label: &'static str, primary: bool| {
+            div()
+                .px_3()
+                .py_1()
+                .rounded(theme.radius)
+                .text_sm()
+                .map(|this| {
+                    if primary {
+                        this.bg(theme.palette.accent)
+                            .text_color(theme.palette.background)
+                    } else {
+                        this.bg(theme.palette.surface)
+                            .hover(|this| this.bg(theme.palette.hover))
+                    }
+                })
+                .child(label)
+        };
+
+        v_flex()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .size_full()
+            .p_4()
+            .gap_3()
+            .bg(theme.palette.background)
+            .text_color(theme.palette.text)
+            .font_family(theme.font_family.clone())
+            .font_weight(theme.font_weight)
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(Icon::new(icon.into()).size(18.))
+                    .child("Authentication required"),
+            )
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(theme.palette.subtext)
+                    .child(message),
+            )
+            .when(self.request.retry, |this| {
+                this.child(
+                    div()
+                        .text_sm()
+                        .text_color(theme.palette.error)
+                        .child("That didn't work, try again."),
+                )
+            })
+            .child(
+                div()
+                    .px_3()
+                    .py_2()
+                    .rounded(theme.radius)
+                    .bg(theme.palette.surface)
+                    .child(if self.input.is_empty() {
+                        div().text_color(theme.palette.subtext).child(placeholder)
+                    } else {
+                        div().child("•".repeat(self.input.chars().count()))
+                    }),
+            )
+            .child(div().flex_1())
+            .child(
+                h_flex()
+                    .justify_end()
+                    .gap_2()
+                    .child(button("Cancel", false).on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _, _, _| this.reply(SecretReply::Cancel)),
+                    ))
+                    .child(button("Connect", true).on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _, _, _| this.submit()),
+                    )),
+            )
+    }
+}
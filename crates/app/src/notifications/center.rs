@@ -0,0 +1,482 @@
+use chrono::{DateTime, Local};
+use futures_signals::signal::SignalExt;
+use futures_util::StreamExt;
+use gpui::{AnyElement, DisplayId, FocusHandle, Global, KeyDownEvent, WindowHandle, img};
+use services::notifications::{self, Notification, Urgency};
+use tracing::{error, warn};
+use ui::prelude::*;
+
+use super::popups::{DEFAULT_ACTION, buttons};
+use super::{Entry, NotificationPopups};
+use crate::bars::Dropdown;
+
+const MAX_HEIGHT: f32 = 560.;
+const IMAGE_SIZE: f32 = 32.;
+
+/// Opens and closes the notification center, the history of notifications dropping
+/// down from the bar.
+pub struct NotificationCenterWindow {
+    handle: Option<WindowHandle<NotificationCenter>>,
+}
+
+impl Global for NotificationCenterWindow {}
+
+impl NotificationCenterWindow {
+    pub fn init(cx: &mut App) {
+        cx.set_global(Self { handle: None });
+    }
+
+    pub fn toggle(
+        subscriber: notifications::Subscriber,
+        display: Option<DisplayId>,
+        x: Pixels,
+        cx: &mut App,
+    ) {
+        if cx.global::<Self>().handle.is_some() {
+            Self::close(cx);
+        } else {
+            Self::open(subscriber, display, x, cx);
+        }
+    }
+
+    /// Shows the notification center under the point `x` of the bar on `display`.
+    pub fn open(
+        subscriber: notifications::Subscriber,
+        display: Option<DisplayId>,
+        x: Pixels,
+        cx: &mut App,
+    ) {
+        Self::close(cx);
+
+        let opts = Dropdown::window_options(display, "mgs-notification-center", cx);
+        let handle = cx.open_window(opts, |window, cx| {
+            let center = cx.new(|cx| NotificationCenter::new(subscriber, x, cx));
+            window.focus(&center.read(cx).focus_handle);
+            center
+        });
+        match handle {
+            Ok(handle) => cx.global_mut::<Self>().handle = Some(handle),
+            Err(err) => error!("failed to open notification center window: {err:#}"),
+        }
+    }
+
+    pub fn close(cx: &mut App) {
+        if let Some(handle) = cx.global::<Self>().handle {
+            handle
+                .update(cx, |center, window, cx| center.dismiss(window, cx))
+                .ok();
+        }
+    }
+}
+
+pub struct NotificationCenter {
+    subscriber: notifications::Subscriber,
+    /// The history, newest first.
+    entries: Vec<Entry>,
+    do_not_disturb: bool,
+    /// Where the center was opened, in bar coordinates.
+    x: Pixels,
+    focus_handle: FocusHandle,
+}
+
+impl NotificationCenter {
+    fn new(subscriber: notifications::Subscriber, x: Pixels, cx: &mut Context<Self>) -> Self {
+        let signal = subscriber.subscribe();
+        let read = subscriber.clone();
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let mut signal = signal.to_stream();
+            while let Some(data) = signal.next().await {
+                // whatever comes in while the center is open is seen right away
+                read.mark_read();
+
+                let do_not_disturb = data.do_not_disturb;
+                // image lookups hit the disk
+                let entries = cx
+                    .background_executor()
+                    .spawn(async move {
+                        data.history
+                            .into_iter()
+                            .rev()
+                            .map(Entry::new)
+                            .collect::<Vec<_>>()
+                    })
+                    .await;
+
+                let updated = this.update(cx, |this, cx| {
+                    this.entries = entries;
+                    this.do_not_disturb = do_not_disturb;
+                    cx.notify();
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        Self {
+            subscriber,
+            entries: Vec::new(),
+            do_not_disturb: false,
+            x,
+            focus_handle: cx.focus_handle(),
+        }
+    }
+
+    fn dismiss(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        cx.global_mut::<NotificationCenterWindow>().handle = None;
+        window.remove_window();
+    }
+
+    /// Removes the notifications with `ids` from the history.
+    fn remove(&mut self, ids: Vec<u32>, cx: &mut Context<Self>) {
+        let subscriber = self.subscriber.clone();
+        cx.spawn(async move |_, _| {
+            for id in ids {
+                if let Err(err) = subscriber.remove(id).await {
+                    warn!("failed to remove notification {id}: {err:#}");
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn clear(&mut self, cx: &mut Context<Self>) {
+        let subscriber = self.subscriber.clone();
+        cx.spawn(async move |_, _| {
+            if let Err(err) = subscriber.clear().await {
+                warn!("failed to clear notifications: {err:#}");
+            }
+        })
+        .detach();
+    }
+
+    fn invoke(&mut self, id: u32, key: String, cx: &mut Context<Self>) {
+        let subscriber = self.subscriber.clone();
+        cx.spawn(async move |_, _| {
+            if let Err(err) = subscriber.invoke_action(id, &key).await {
+                warn!("failed to invoke action {key} of notification {id}: {err:#}");
+            }
+        })
+        .detach();
+    }
+
+    /// Clicking a notification invokes its default action, which usually brings its app
+    /// up, so the center gets out of the way.
+    fn click(&mut self, notification: &Notification, window: &mut Window, cx: &mut Context<Self>) {
+        let has_default = notification
+            .actions
+            .iter()
+            .any(|action| action.key == DEFAULT_ACTION);
+        if has_default {
+            self.invoke(notification.id, DEFAULT_ACTION.to_string(), cx);
+            self.dismiss(window, cx);
+        }
+    }
+
+    fn toggle_do_not_disturb(&mut self, cx: &mut Context<Self>) {
+        self.do_not_disturb = !self.do_not_disturb;
+        self.subscriber.set_do_not_disturb(self.do_not_disturb);
+        cx.notify();
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        if event.keystroke.key == "escape" {
+            self.dismiss(window, cx);
+        }
+    }
+
+    fn render_header(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let (dnd_bg, dnd_text) = if self.do_not_disturb {
+            (theme.palette.accent, theme.palette.background)
+        } else {
+            (theme.palette.surface, theme.palette.text)
+        };
+
+        h_flex()
+            .px_2()
+            .py_1()
+            .gap_2()
+            .child(div().flex_1().child("Notifications"))
+            .child(
+                h_flex()
+                    .gap_1()
+                    .px_2()
+                    .py_0p5()
+                    .rounded(theme.radius)
+                    .bg(dnd_bg)
+                    .text_color(dnd_text)
+                    .text_sm()
+                    .child(
+                        Icon::new("icons/bell-off.svg".into())
+                            .size(14.)
+                            .color(dnd_text),
+                    )
+                    .child("Do not disturb")
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _, _, cx| {
+                            cx.stop_propagation();
+                            this.toggle_do_not_disturb(cx);
+                        }),
+                    ),
+            )
+            .when(!self.entries.is_empty(), |this| {
+                this.child(
+                    div()
+                        .px_2()
+                        .py_0p5()
+                        .rounded(theme.radius)
+                        .text_sm()
+                        .hover(|this| this.bg(theme.palette.hover))
+                        .child("Clear all")
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(|this, _, _, cx| {
+                                cx.stop_propagation();
+                                this.clear(cx);
+                            }),
+                        ),
+                )
+            })
+    }
+
+    fn render_group(
+        &self,
+        app_name: &str,
+        entries: &[Entry],
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let theme = cx.theme().clone();
+        let ids: Vec<_> = entries.iter().map(|entry| entry.notification.id).collect();
+        let app_name = if app_name.is_empty() {
+            "Unknown app"
+        } else {
+            app_name
+        };
+
+        v_flex()
+            .gap_1()
+            .child(
+                h_flex()
+                    .px_2()
+                    .gap_2()
+                    .text_xs()
+                    .text_color(theme.palette.subtext)
+                    .child(
+                        div()
+                            .flex_1()
+                            .overflow_hidden()
+                            .whitespace_nowrap()
+                            .child(app_name.to_string()),
+                    )
+                    .child(
+                        div()
+                            .rounded(theme.radius)
+                            .hover(|this| this.bg(theme.palette.hover))
+                            .child(
+                                Icon::new("icons/x.svg".into())
+                                    .size(12.)
+                                    .color(theme.palette.subtext),
+                            )
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, _, _, cx| {
+                                    cx.stop_propagation();
+                                    this.remove(ids.clone(), cx);
+                                }),
+                            ),
+                    ),
+            )
+            .children(entries.iter().map(|entry| self.render_entry(entry, cx)))
+            .into_any_element()
+    }
+
+    fn render_entry(&self, entry: &Entry, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let notification = &entry.notification;
+        let id = notification.id;
+        let border = if notification.urgency == Urgency::Critical {
+            theme.palette.error
+        } else {
+            theme.palette.border
+        };
+
+        let actions: Vec<_> = buttons(notification)
+            .map(|action| {
+                let key = action.key.clone();
+                div()
+                    .flex_1()
+                    .px_2()
+                    .py_0p5()
+                    .rounded(theme.radius)
+                    .bg(theme.palette.background)
+                    .hover(|this| this.bg(theme.palette.hover))
+                    .flex()
+                    .justify_center()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .text_sm()
+                    .child(action.label.clone())
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _, _, cx| {
+                            cx.stop_propagation();
+                            this.invoke(id, key.clone(), cx);
+                        }),
+                    )
+            })
+            .collect();
+
+        let clicked = notification.clone();
+        h_flex()
+            .gap_3()
+            .p_2()
+            .items_start()
+            .rounded(theme.radius)
+            .border_1()
+            .border_color(border)
+            .bg(theme.palette.surface)
+            .when_some(entry.image.as_ref(), |this, image| {
+                this.child(img(image.source()).size(px(IMAGE_SIZE)).flex_none())
+            })
+            .child(
+                v_flex()
+                    .flex_1()
+                    .min_w_0()
+                    .gap_1()
+                    .child(
+                        h_flex()
+                            .gap_2()
+                            .child(
+                                div()
+                                    .flex_1()
+                                    .min_w_0()
+                                    .overflow_hidden()
+                                    .whitespace_nowrap()
+                                    .child(notification.summary.clone()),
+                            )
+                            .child(
+                                div()
+                                    .flex_none()
+                                    .text_xs()
+                                    .text_color(theme.palette.subtext)
+                                    .child(received(notification.time)),
+                            )
+                            .child(
+                                div()
+                                    .flex_none()
+                                    .rounded(theme.radius)
+                                    .hover(|this| this.bg(theme.palette.hover))
+                                    .child(
+                                        Icon::new("icons/x.svg".into())
+                                            .size(14.)
+                                            .color(theme.palette.subtext),
+                                    )
+                                    .on_mouse_down(
+                                        MouseButton::Left,
+                                        cx.listener(move |this, _, _, cx| {
+                                            cx.stop_propagation();
+                                            this.remove(vec![id], cx);
+                                        }),
+                                    ),
+                            ),
+                    )
+                    .when(!notification.body.is_empty(), |this| {
+                        this.child(div().text_sm().child(notification.body.clone()))
+                    })
+                    .when(!actions.is_empty(), |this| {
+                        this.child(h_flex().gap_2().children(actions))
+                    }),
+            )
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _, window, cx| {
+                    cx.stop_propagation();
+                    this.click(&clicked, window, cx);
+                }),
+            )
+    }
+}
+
+/// The time of day for today's notifications, the date for older ones.
+fn received(time: u64) -> String {
+    let Some(time) = DateTime::from_timestamp(time as i64, 0) else {
+        return String::new();
+    };
+    let time = time.with_timezone(&Local);
+    if time.date_naive() == Local::now().date_naive() {
+        time.format("%H:%M").to_string()
+    } else {
+        time.format("%b %-d").to_string()
+    }
+}
+
+impl Render for NotificationCenter {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let width = cx.global::<NotificationPopups>().config.width;
+
+        // grouped by app, the groups in the order of their latest notification
+        let mut groups: Vec<(String, Vec<Entry>)> = Vec::new();
+        for entry in &self.entries {
+            let app_name = &entry.notification.app_name;
+            match groups.iter_mut().find(|(name, _)| name == app_name) {
+                Some((_, entries)) => entries.push(entry.clone()),
+                None => groups.push((app_name.clone(), vec![entry.clone()])),
+            }
+        }
+        let groups: Vec<_> = groups
+            .iter()
+            .map(|(app_name, entries)| self.render_group(app_name, entries, cx))
+            .collect();
+
+        let list = if groups.is_empty() {
+            div()
+                .py_6()
+                .flex()
+                .justify_center()
+                .text_color(theme.palette.subtext)
+                .child("No notifications")
+                .into_any_element()
+        } else {
+            v_flex()
+                .id("notification-history")
+                .max_h(px(MAX_HEIGHT))
+                .overflow_y_scroll()
+                .gap_3()
+                .children(groups)
+                .into_any_element()
+        };
+
+        let panel = v_flex()
+            .w(px(width))
+            .p_2()
+            .gap_2()
+            .rounded(theme.radius)
+            .border_1()
+            .border_color(theme.palette.border)
+            .bg(theme.palette.background)
+            .occlude()
+            .child(self.render_header(cx))
+            .child(list);
+
+        div()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .size_full()
+            .text_color(theme.palette.text)
+            .font_family(theme.font_family.clone())
+            .font_weight(theme.font_weight)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _, window, cx| this.dismiss(window, cx)),
+            )
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(|this, _, window, cx| this.dismiss(window, cx)),
+            )
+            .child(Dropdown::new(self.x, window, cx).place(panel))
+    }
+}
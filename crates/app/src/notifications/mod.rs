@@ -0,0 +1,193 @@
+mod center;
+mod popups;
+
+use std::time::Duration;
+
+use futures_signals::signal::SignalExt;
+use futures_util::StreamExt;
+use gpui::{Global, WindowBackgroundAppearance, WindowHandle};
+use services::notifications::{self, Image, Notification};
+use tracing::error;
+use ui::prelude::*;
+
+pub use self::center::NotificationCenterWindow;
+use self::popups::Popups;
+use crate::clients::Clients;
+use crate::config::{Corner, NotificationsConfig};
+use crate::icons::{IconImage, find_icon};
+
+/// A notification along with its image, looked up ahead of rendering.
+#[derive(Clone)]
+pub struct Entry {
+    pub notification: Notification,
+    pub image: Option<IconImage>,
+}
+
+impl Entry {
+    pub fn new(notification: Notification) -> Self {
+        let image = match &notification.image {
+            Some(Image::Data(data)) => {
+                Some(IconImage::from_argb(data.width, data.height, &data.data))
+            }
+            Some(Image::Path(path)) => {
+                let path = path.strip_prefix("file://").unwrap_or(path);
+                find_icon(path).map(|path| IconImage::File(path.into()))
+            }
+            None => None,
+        };
+
+        Self {
+            notification,
+            image,
+        }
+    }
+}
+
+struct PopupWindow {
+    handle: WindowHandle<Popups>,
+    height: Pixels,
+}
+
+/// Runs the notification daemon and shows the latest notifications as popups, stacked
+/// in a corner of the output.
+pub struct NotificationPopups {
+    config: NotificationsConfig,
+    subscriber: Option<notifications::Subscriber>,
+    /// Every notification still around that may pop up, oldest first.
+    entries: Vec<Entry>,
+    window: Option<PopupWindow>,
+}
+
+impl Global for NotificationPopups {}
+
+impl NotificationPopups {
+    pub fn init(config: NotificationsConfig, cx: &mut App) {
+        cx.set_global(Self {
+            config,
+            subscriber: None,
+            entries: Vec::new(),
+            window: None,
+        });
+
+        let notifications = Clients::notifications(cx);
+        cx.spawn(async move |cx: &mut AsyncApp| {
+            let Some(subscriber) = notifications.await else {
+                return;
+            };
+            let registered = cx.update(|cx| {
+                let popups = cx.global_mut::<Self>();
+                subscriber.set_default_timeout(Duration::from_millis(popups.config.timeout_ms));
+                subscriber.set_history_limit(popups.config.history_size);
+                popups.subscriber = Some(subscriber.clone());
+            });
+            if registered.is_err() {
+                return;
+            }
+
+            let mut signal = subscriber.subscribe().to_stream();
+            while let Some(data) = signal.next().await {
+                // image lookups hit the disk
+                let entries = cx
+                    .background_executor()
+                    .spawn(async move {
+                        data.active
+                            .into_iter()
+                            .filter(|notification| !notification.silent)
+                            .map(Entry::new)
+                            .collect::<Vec<_>>()
+                    })
+                    .await;
+
+                let updated = cx.update(|cx| {
+                    cx.global_mut::<Self>().entries = entries;
+                    Self::sync(cx);
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
+    /// Reopens the popups in case their geometry changed.
+    pub fn reload(config: NotificationsConfig, cx: &mut App) {
+        let popups = cx.global_mut::<Self>();
+        if let Some(subscriber) = &popups.subscriber {
+            subscriber.set_default_timeout(Duration::from_millis(config.timeout_ms));
+            subscriber.set_history_limit(config.history_size);
+        }
+        popups.config = config;
+        Self::close(cx);
+        Self::sync(cx);
+    }
+
+    /// Shows the newest notifications, resizing the window to fit them.
+    fn sync(cx: &mut App) {
+        let this = cx.global::<Self>();
+        let Some(subscriber) = this.subscriber.clone() else {
+            return;
+        };
+        let config = this.config.clone();
+        let mut entries: Vec<_> = this
+            .entries
+            .iter()
+            .rev()
+            .take(config.max_popups)
+            .cloned()
+            .collect();
+        // the newest one sits closest to the corner
+        if matches!(config.position, Corner::BottomLeft | Corner::BottomRight) {
+            entries.reverse();
+        }
+
+        if entries.is_empty() {
+            Self::close(cx);
+            return;
+        }
+
+        let height = popups::height(&entries);
+        if let Some(window) = &cx.global::<Self>().window {
+            if window.height == height {
+                let handle = window.handle;
+                let updated = handle.update(cx, |popups, _, cx| popups.set_entries(entries, cx));
+                if updated.is_ok() {
+                    return;
+                }
+            }
+        }
+
+        Self::close(cx);
+        let bounds = Bounds {
+            origin: point(px(0.), px(0.)),
+            size: size(px(config.width), height),
+        };
+        let layer_shell_settings = LayerShellSettings {
+            layer: Layer::Overlay,
+            anchor: config.anchor(),
+            keyboard_interactivity: KeyboardInteractivity::None,
+            namespace: "mgs-notifications".to_string(),
+            ..Default::default()
+        };
+        let opts = WindowOptions {
+            window_bounds: Some(WindowBounds::Windowed(bounds)),
+            window_background: WindowBackgroundAppearance::Transparent,
+            kind: WindowKind::LayerShell(layer_shell_settings),
+            ..Default::default()
+        };
+
+        match cx.open_window(opts, |_, cx| cx.new(|_| Popups::new(subscriber, entries))) {
+            Ok(handle) => cx.global_mut::<Self>().window = Some(PopupWindow { handle, height }),
+            Err(err) => error!("failed to open notification window: {err:#}"),
+        }
+    }
+
+    fn close(cx: &mut App) {
+        if let Some(window) = cx.global_mut::<Self>().window.take() {
+            window
+                .handle
+                .update(cx, |_, window, _| window.remove_window())
+                .ok();
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use gpui::img;
+use services::notifications::{self, Notification, Urgency};
+use tracing::warn;
+use ui::prelude::*;
+
+use super::Entry;
+
+const PADDING: f32 = 8.;
+const GAP: f32 = 8.;
+const CARD_HEIGHT: f32 = 84.;
+const ACTIONS_HEIGHT: f32 = 32.;
+const IMAGE_SIZE: f32 = 40.;
+
+/// The key of the action to invoke when the notification itself is clicked.
+pub const DEFAULT_ACTION: &str = "default";
+
+/// Height of the window showing `entries`, the cards being of a fixed height so that it
+/// is known before laying them out.
+pub fn height(entries: &[Entry]) -> Pixels {
+    let cards: f32 = entries
+        .iter()
+        .map(|entry| card_height(&entry.notification))
+        .sum();
+    let gaps = GAP * entries.len().saturating_sub(1) as f32;
+    px(cards + gaps + 2. * PADDING)
+}
+
+fn card_height(notification: &Notification) -> f32 {
+    if buttons(notification).next().is_some() {
+        CARD_HEIGHT + ACTIONS_HEIGHT
+    } else {
+        CARD_HEIGHT
+    }
+}
+
+/// The actions shown as buttons, the default one being bound to the card instead.
+pub fn buttons(notification: &Notification) -> impl Iterator<Item = &notifications::Action> {
+    notification
+        .actions
+        .iter()
+        .filter(|action| action.key != DEFAULT_ACTION)
+}
+
+pub struct Popups {
+    subscriber: notifications::Subscriber,
+    entries: Vec<Entry>,
+}
+
+impl Popups {
+    pub fn new(subscriber: notifications::Subscriber, entries: Vec<Entry>) -> Self {
+        Self {
+            subscriber,
+            entries,
+        }
+    }
+
+    pub fn set_entries(&mut self, entries: Vec<Entry>, cx: &mut Context<Self>) {
+        self.entries = entries;
+        cx.notify();
+    }
+
+    fn dismiss(&mut self, id: u32, cx: &mut Context<Self>) {
+        let subscriber = self.subscriber.clone();
+        cx.spawn(async move |_, _| {
+            if let Err(err) = subscriber.dismiss(id).await {
+                warn!("failed to dismiss notification {id}: {err:#}");
+            }
+        })
+        .detach();
+    }
+
+    fn invoke(&mut self, id: u32, key: String, cx: &mut Context<Self>) {
+        let subscriber = self.subscriber.clone();
+        cx.spawn(async move |_, _| {
+            if let Err(err) = subscriber.invoke_action(id, &key).await {
+                warn!("failed to invoke action {key} of notification {id}: {err:#}");
+            }
+        })
+        .detach();
+    }
+
+    /// Clicking a notification invokes its default action, or dismisses it.
+    fn click(&mut self, notification: &Notification, cx: &mut Context<Self>) {
+        let has_default = notification
+            .actions
+            .iter()
+            .any(|action| action.key == DEFAULT_ACTION);
+        if has_default {
+            self.invoke(notification.id, DEFAULT_ACTION.to_string(), cx);
+        } else {
+            self.dismiss(notification.id, cx);
+        }
+    }
+
+    fn render_card(&self, entry: &Entry, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let notification = &entry.notification;
+        let id = notification.id;
+        let border = if notification.urgency == Urgency::Critical {
+            theme.palette.error
+        } else {
+            theme.palette.border
+        };
+
+        let actions: Vec<_> = buttons(notification)
+            .map(|action| {
+                let key = action.key.clone();
+                div()
+                    .flex_1()
+                    .px_2()
+                    .py_1()
+                    .rounded(theme.radius)
+                    .bg(theme.palette.surface)
+                    .hover(|this| this.bg(theme.palette.hover))
+                    .flex()
+                    .justify_center()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .child(action.label.clone())
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _, _, cx| {
+                            cx.stop_propagation();
+                            this.invoke(id, key.clone(), cx);
+                        }),
+                    )
+            })
+            .collect();
+
+        let clicked = notification.clone();
+        v_flex()
+            .h(px(card_height(notification)))
+            .p_3()
+            .gap_2()
+            .rounded(theme.radius)
+            .border_1()
+            .border_color(border)
+            .bg(theme.palette.background)
+            .overflow_hidden()
+            .child(
+                h_flex()
+                    .flex_1()
+                    .min_h_0()
+                    .gap_3()
+                    .items_start()
+                    .when_some(entry.image.as_ref(), |this, image| {
+                        this.child(img(image.source()).size(px(IMAGE_SIZE)).flex_none())
+                    })
+                    .child(
+                        v_flex()
+                            .flex_1()
+                            .min_w_0()
+                            .h_full()
+                            .overflow_hidden()
+                            .child(
+                                h_flex()
+                                    .gap_2()
+                                    .child(
+                                        div()
+                                            .flex_1()
+                                            .min_w_0()
+                                            .overflow_hidden()
+                                            .whitespace_nowrap()
+                                            .child(notification.summary.clone()),
+                                    )
+                                    .child(
+                                        div()
+                                            .flex_none()
+                                            .rounded(theme.radius)
+                                            .hover(|this| this.bg(theme.palette.hover))
+                                            .child(
+                                                Icon::new("icons/x.svg".into())
+                                                    .size(14.)
+                                                    .color(theme.palette.subtext),
+                                            )
+                                            .on_mouse_down(
+                                                MouseButton::Left,
+                                                cx.listener(move |this, _, _, cx| {
+                                                    cx.stop_propagation();
+                                                    this.dismiss(id, cx);
+                                                }),
+                                            ),
+                                    ),
+                            )
+                            .child(
+                                div()
+                                    .text_xs()
+                                    .text_color(theme.palette.subtext)
+                                    .overflow_hidden()
+                                    .whitespace_nowrap()
+                                    .child(notification.app_name.clone()),
+                            )
+                            .when(!notification.body.is_empty(), |this| {
+                                this.child(
+                                    div()
+                                        .text_sm()
+                                        .overflow_hidden()
+                                        .child(notification.body.clone()),
+                                )
+                            }),
+                    ),
+            )
+            .when(!actions.is_empty(), |this| {
+                this.child(h_flex().flex_none().gap_2().children(actions))
+            })
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _, _, cx| this.click(&clicked, cx)),
+            )
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(move |this, _, _, cx| this.dismiss(id, cx)),
+            )
+    }
+}
+
+impl Render for Popups {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let cards: Vec<_> = self
+            .entries
+            .iter()
+            .map(|entry| self.render_card(entry, cx))
+            .collect();
+
+        v_flex()
+            .size_full()
+            .p(px(PADDING))
+            .gap(px(GAP))
+            .text_color(theme.palette.text)
+            .font_family(theme.font_family.clone())
+            .font_weight(theme.font_weight)
+            .children(cards)
+    }
+}
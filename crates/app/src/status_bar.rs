@@ -1,65 +1,213 @@
-use gpui::{AnyView, FontWeight, IntoElement, ParentElement, Render, Styled, px, rems, rgb};
-use ui::{Context, h_flex, prelude::Window};
+use gpui::{AnyView, canvas};
+use tracing::debug;
+use ui::prelude::*;
 
-use crate::widgets::network::NetworkWidget;
-use crate::widgets::sysinfo::SysInfoWidget;
-use crate::widgets::volume::Volume;
-use crate::widgets::{clock::Clock, upower::Upower, workspace::Workspaces};
+use crate::config::{BarConfig, WidgetConfig};
+use crate::widgets;
+
+struct Item {
+    config: WidgetConfig,
+    view: AnyView,
+}
+
+#[derive(Clone, Copy)]
+enum Side {
+    Left,
+    Right,
+}
 
 pub struct StatusBar {
-    left_items: Vec<AnyView>,
-    right_items: Vec<AnyView>,
+    /// Name of the output the bar is on, if known.
+    output: Option<String>,
+    left_items: Vec<Item>,
+    center_items: Vec<Item>,
+    right_items: Vec<Item>,
+    /// Widths measured during the last layout, used to keep the center section
+    /// from overlapping the other two.
+    bar_width: Pixels,
+    left_width: Pixels,
+    right_width: Pixels,
 }
 
 impl Render for StatusBar {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<'_, Self>) -> impl IntoElement {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<'_, Self>) -> impl IntoElement {
+        let theme = cx.theme();
+        let padding = theme.spacing;
+        let gap = rems(8.).to_pixels(window.rem_size());
+        let view = cx.entity().clone();
+
         h_flex()
+            .relative()
             .w_full()
             .h_full()
-            .justify_between()
-            .gap_x(rems(8.))
-            .px(px(4.))
-            .bg(rgb(0xeff1f5))
-            .font_family("MonoLisa")
-            .font_weight(FontWeight::EXTRA_BOLD)
+            .gap_x(gap)
+            .px(padding)
+            .bg(theme.palette.background)
+            .text_color(theme.palette.text)
+            .font_family(theme.font_family.clone())
+            .font_weight(theme.font_weight)
             .text_sm()
             .child(self.render_left_tools(cx))
+            .child(self.render_center_tools(padding, gap, cx))
             .child(self.render_right_tools(cx))
+            .child(
+                canvas(
+                    move |bounds, _, cx| {
+                        view.update(cx, |bar, cx| {
+                            if bar.bar_width != bounds.size.width {
+                                bar.bar_width = bounds.size.width;
+                                cx.notify();
+                            }
+                        })
+                    },
+                    |_, _, _, _| {},
+                )
+                .absolute()
+                .size_full(),
+            )
     }
 }
 
 impl StatusBar {
-    fn render_left_tools(&self, _cx: &mut Context<Self>) -> impl IntoElement {
+    /// The left and right sections always share the remaining space equally, which
+    /// keeps the center section centered.
+    fn render_left_tools(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        h_flex().flex_1().min_w_0().overflow_x_hidden().child(
+            h_flex()
+                .relative()
+                .flex_none()
+                .gap_x(cx.theme().spacing)
+                .children(self.left_items.iter().map(|item| item.view.clone()))
+                .child(Self::measure(Side::Left, cx)),
+        )
+    }
+
+    fn render_center_tools(
+        &self,
+        padding: Pixels,
+        gap: Pixels,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
         h_flex()
-            .gap_x(px(4.))
+            .flex_none()
+            .min_w_0()
             .overflow_x_hidden()
-            .children(self.left_items.iter().cloned())
+            .whitespace_nowrap()
+            .when_some(self.center_max_width(padding, gap), |this, max_width| {
+                this.max_w(max_width)
+            })
+            .gap_x(cx.theme().spacing)
+            .children(self.center_items.iter().map(|item| item.view.clone()))
     }
 
-    fn render_right_tools(&self, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render_right_tools(&self, cx: &mut Context<Self>) -> impl IntoElement {
         h_flex()
-            .gap_x(px(4.))
-            .children(self.right_items.iter().cloned())
+            .flex_1()
+            .min_w_0()
+            .justify_end()
+            .overflow_x_hidden()
+            .child(
+                h_flex()
+                    .relative()
+                    .flex_none()
+                    .gap_x(cx.theme().spacing)
+                    .children(self.right_items.iter().map(|item| item.view.clone()))
+                    .child(Self::measure(Side::Right, cx)),
+            )
+    }
+
+    /// Records the width of a section's content once it is laid out.
+    fn measure(side: Side, cx: &mut Context<Self>) -> impl IntoElement {
+        let view = cx.entity().clone();
+        canvas(
+            move |bounds, _, cx| {
+                view.update(cx, |bar, cx| {
+                    let width = match side {
+                        Side::Left => &mut bar.left_width,
+                        Side::Right => &mut bar.right_width,
+                    };
+                    if *width != bounds.size.width {
+                        *width = bounds.size.width;
+                        cx.notify();
+                    }
+                })
+            },
+            |_, _, _, _| {},
+        )
+        .absolute()
+        .size_full()
+    }
+
+    /// The widest the center section can be while staying centered without covering
+    /// the widest of its neighbors, `None` before the first layout.
+    fn center_max_width(&self, padding: Pixels, gap: Pixels) -> Option<Pixels> {
+        if self.bar_width <= Pixels::ZERO {
+            return None;
+        }
+
+        let available = f32::from(self.bar_width) - f32::from(padding + gap) * 2.;
+        let sides = f32::from(self.left_width).max(f32::from(self.right_width)) * 2.;
+        Some(px((available - sides).max(0.)))
     }
 }
 
 impl StatusBar {
-    pub fn new(cx: &mut Context<Self>) -> Self {
-        let clock = Clock::new(cx).clone();
-        let upower = Upower::new(cx).clone();
-        let workspaces = Workspaces::new(cx).clone();
-        let network = NetworkWidget::new(cx).clone();
-        let volume = Volume::new(cx).clone();
-        let sysinfo = SysInfoWidget::new(cx).clone();
-        Self {
-            left_items: vec![workspaces.into()],
-            right_items: vec![
-                network.into(),
-                sysinfo.into(),
-                volume.into(),
-                upower.into(),
-                clock.into(),
-            ],
+    pub fn new(config: &BarConfig, output: Option<String>, cx: &mut Context<Self>) -> Self {
+        let mut bar = Self {
+            output,
+            left_items: Vec::new(),
+            center_items: Vec::new(),
+            right_items: Vec::new(),
+            bar_width: Pixels::ZERO,
+            left_width: Pixels::ZERO,
+            right_width: Pixels::ZERO,
+        };
+        bar.apply(config, cx);
+        bar
+    }
+
+    /// Updates the widgets to match `config`.
+    ///
+    /// Widgets whose config didn't change are kept as they are, even when they moved
+    /// to another position or section, only new or modified ones are rebuilt.
+    pub fn apply(&mut self, config: &BarConfig, cx: &mut Context<Self>) {
+        let mut current: Vec<Item> = self
+            .left_items
+            .drain(..)
+            .chain(self.center_items.drain(..))
+            .chain(self.right_items.drain(..))
+            .collect();
+
+        self.left_items = self.build_items(&config.left, &mut current, cx);
+        self.center_items = self.build_items(&config.center, &mut current, cx);
+        self.right_items = self.build_items(&config.right, &mut current, cx);
+
+        if !current.is_empty() {
+            debug!("dropping {} widgets", current.len());
         }
+        cx.notify();
+    }
+
+    fn build_items(
+        &self,
+        configs: &[WidgetConfig],
+        current: &mut Vec<Item>,
+        cx: &mut Context<Self>,
+    ) -> Vec<Item> {
+        configs
+            .iter()
+            .map(
+                |config| match current.iter().position(|item| item.config == *config) {
+                    Some(pos) => current.remove(pos),
+                    None => {
+                        debug!("building {} widget", config.name());
+                        Item {
+                            config: config.clone(),
+                            view: widgets::build(config, self.output.as_deref(), cx),
+                        }
+                    }
+                },
+            )
+            .collect()
     }
 }
@@ -0,0 +1,82 @@
+use futures::StreamExt;
+use futures_signals::signal::SignalExt;
+use gpui::{App, Global};
+use services::appearance::{AppearanceData, ColorScheme};
+use tracing::{error, info};
+use ui::prelude::*;
+
+use crate::clients::Clients;
+use crate::config::Config;
+
+/// Picks the theme from the config and the system appearance, and switches it when
+/// either of them changes.
+pub struct Themes {
+    config: Config,
+    /// Last appearance reported by the desktop portal, the default one until then or
+    /// if there is no portal.
+    system: AppearanceData,
+}
+
+impl Global for Themes {}
+
+impl Themes {
+    pub fn init(config: Config, cx: &mut App) {
+        Theme::init(cx);
+        cx.set_global(Self {
+            config,
+            system: AppearanceData::default(),
+        });
+        Self::apply(cx);
+
+        let appearance = Clients::appearance(cx);
+        cx.spawn(async move |cx: &mut AsyncApp| {
+            let Some(subscriber) = appearance.await else {
+                return;
+            };
+
+            let mut changes = subscriber.subscribe().dedupe_cloned().to_stream();
+            while let Some(system) = changes.next().await {
+                info!("system appearance changed: {system:?}");
+                let updated = cx.update(|cx| {
+                    cx.global_mut::<Self>().system = system;
+                    Self::apply(cx);
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
+    pub fn reload(config: Config, cx: &mut App) {
+        cx.global_mut::<Self>().config = config;
+        Self::apply(cx);
+    }
+
+    fn apply(cx: &mut App) {
+        let themes = cx.global::<Self>();
+        let dark = themes.system.color_scheme == ColorScheme::Dark;
+        let mut theme = match themes.config.theme(dark) {
+            Ok(theme) => theme,
+            Err(err) => {
+                error!("{err:#}, keeping the current theme");
+                return;
+            }
+        };
+        let system_accent = themes.config.theme.system_accent;
+        if let Some((r, g, b)) = themes.system.accent_color.filter(|_| system_accent) {
+            theme.palette.accent = Rgba {
+                r: r as f32,
+                g: g as f32,
+                b: b as f32,
+                a: 1.,
+            };
+        }
+
+        if *cx.theme() != theme {
+            cx.set_global(theme);
+            cx.refresh_windows();
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use futures_signals::signal::SignalExt;
+use futures_util::StreamExt;
+use gpui::{AnyElement, DisplayId, FocusHandle, Global, KeyDownEvent, WindowHandle};
+use services::tray::{Menu, MenuItem, ROOT, ToggleKind};
+use tracing::{error, warn};
+use ui::prelude::*;
+
+use crate::bars::Dropdown;
+
+/// Opens and closes the menu of a tray item.
+pub struct TrayMenuWindow {
+    handle: Option<WindowHandle<TrayMenu>>,
+}
+
+impl Global for TrayMenuWindow {}
+
+impl TrayMenuWindow {
+    pub fn init(cx: &mut App) {
+        cx.set_global(Self { handle: None });
+    }
+
+    /// Shows `menu` under the point `x` of the bar on `display`, replacing any open menu.
+    pub fn open(menu: Menu, display: Option<DisplayId>, x: Pixels, cx: &mut App) {
+        Self::close(cx);
+
+        let opts = Dropdown::window_options(display, "mgs-tray-menu", cx);
+        let handle = cx.open_window(opts, |window, cx| {
+            let tray_menu = cx.new(|cx| TrayMenu::new(menu, x, cx));
+            window.focus(&tray_menu.read(cx).focus_handle);
+            tray_menu
+        });
+        match handle {
+            Ok(handle) => cx.global_mut::<Self>().handle = Some(handle),
+            Err(err) => error!("failed to open tray menu window: {err:#}"),
+        }
+    }
+
+    pub fn close(cx: &mut App) {
+        if let Some(handle) = cx.global::<Self>().handle {
+            handle
+                .update(cx, |tray_menu, window, cx| tray_menu.dismiss(window, cx))
+                .ok();
+        }
+    }
+}
+
+pub struct TrayMenu {
+    menu: Menu,
+    root: MenuItem,
+    /// Where the menu was opened, in bar coordinates.
+    x: Pixels,
+    /// Ids of the open submenus, outermost first.
+    open: Vec<i32>,
+    focus_handle: FocusHandle,
+}
+
+impl TrayMenu {
+    fn new(menu: Menu, x: Pixels, cx: &mut Context<Self>) -> Self {
+        let root = menu.get();
+        let signal = menu.subscribe();
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let mut signal = signal.to_stream();
+            while let Some(root) = signal.next().await {
+                let updated = this.update(cx, |this, cx| {
+                    this.root = root;
+                    cx.notify();
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        let opened = menu.clone();
+        cx.spawn(async move |_, _| {
+            if let Err(err) = opened.about_to_show(ROOT).await {
+                warn!("failed to prepare tray menu: {err:#}");
+            }
+            opened.opened(ROOT).await.ok();
+        })
+        .detach();
+
+        Self {
+            menu,
+            root,
+            x,
+            open: Vec::new(),
+            focus_handle: cx.focus_handle(),
+        }
+    }
+
+    fn dismiss(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        cx.global_mut::<TrayMenuWindow>().handle = None;
+        window.remove_window();
+
+        let menu = self.menu.clone();
+        cx.spawn(async move |_, _| menu.closed(ROOT).await.ok())
+            .detach();
+    }
+
+    /// Opens the submenu of the hovered item at `depth`, closing the deeper ones.
+    fn hover_item(&mut self, depth: usize, item: &MenuItem, cx: &mut Context<Self>) {
+        let mut open = self.open.clone();
+        open.truncate(depth);
+        if item.submenu && item.enabled {
+            open.push(item.id);
+        }
+        if open == self.open {
+            return;
+        }
+
+        if let Some(&id) = open.get(depth) {
+            let menu = self.menu.clone();
+            cx.spawn(async move |_, _| {
+                if let Err(err) = menu.about_to_show(id).await {
+                    warn!("failed to prepare tray submenu: {err:#}");
+                }
+            })
+            .detach();
+        }
+        self.open = open;
+        cx.notify();
+    }
+
+    fn click(
+        &mut self,
+        depth: usize,
+        item: &MenuItem,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if !item.enabled {
+            return;
+        }
+        if item.submenu {
+            self.hover_item(depth, item, cx);
+            return;
+        }
+
+        let menu = self.menu.clone();
+        let id = item.id;
+        cx.spawn(async move |_, _| {
+            if let Err(err) = menu.clicked(id).await {
+                warn!("tray menu item {id} didn't handle the click: {err:#}");
+            }
+        })
+        .detach();
+        self.dismiss(window, cx);
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        if event.keystroke.key == "escape" {
+            self.dismiss(window, cx);
+        }
+    }
+
+    /// `flip` opens submenus to the left, for menus opened close to the right edge.
+    fn render_menu(
+        &self,
+        items: &[MenuItem],
+        depth: usize,
+        flip: bool,
+        cx: &mut Context<Self>,
+    ) -> Div {
+        let theme = cx.theme().clone();
+
+        v_flex()
+            .min_w(px(160.))
+            .p_1()
+            .rounded(theme.radius)
+            .border_1()
+            .border_color(theme.palette.border)
+            .bg(theme.palette.background)
+            .occlude()
+            .children(
+                items
+                    .iter()
+                    .filter(|item| item.visible)
+                    .map(|item| self.render_item(item, depth, flip, cx)),
+            )
+    }
+
+    fn render_item(
+        &self,
+        item: &MenuItem,
+        depth: usize,
+        flip: bool,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let theme = cx.theme().clone();
+        if item.separator {
+            return div()
+                .h(px(1.))
+                .mx_1()
+                .my_1()
+                .bg(theme.palette.border)
+                .into_any_element();
+        }
+
+        let open = self.open.get(depth) == Some(&item.id);
+        let color = if item.enabled {
+            theme.palette.text
+        } else {
+            theme.palette.subtext
+        };
+        let indicator = match (item.toggle, item.checked) {
+            (Some(ToggleKind::Checkmark), true) => Some("icons/check.svg"),
+            (Some(ToggleKind::Radio), true) => Some("icons/circle-dot.svg"),
+            (Some(ToggleKind::Radio), false) => Some("icons/circle.svg"),
+            _ => None,
+        };
+        let submenu = open.then(|| {
+            self.render_menu(&item.children, depth + 1, flip, cx)
+                .absolute()
+                .top(px(-4.))
+                .map(|this| {
+                    if flip {
+                        this.right(relative(1.))
+                    } else {
+                        this.left(relative(1.))
+                    }
+                })
+        });
+
+        let hovered = item.clone();
+        let clicked = item.clone();
+        h_flex()
+            .id(SharedString::from(format!("tray-menu-item-{}", item.id)))
+            .relative()
+            .gap_2()
+            .px_2()
+            .py_1()
+            .rounded(theme.radius)
+            .text_color(color)
+            .when(item.enabled, |this| {
+                this.hover(|this| this.bg(theme.palette.hover))
+            })
+            .when(open, |this| this.bg(theme.palette.hover))
+            .child(
+                div()
+                    .size(px(14.))
+                    .flex_none()
+                    .children(indicator.map(|icon| Icon::new(icon.into()).size(14.).color(color))),
+            )
+            .child(div().flex_1().whitespace_nowrap().child(item.label.clone()))
+            .when(item.submenu, |this| {
+                this.child(
+                    Icon::new("icons/chevron-right.svg".into())
+                        .size(14.)
+                        .color(color),
+                )
+            })
+            .on_hover(cx.listener(move |this, hovered_now: &bool, _, cx| {
+                if *hovered_now {
+                    this.hover_item(depth, &hovered, cx);
+                }
+            }))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _, window, cx| {
+                    cx.stop_propagation();
+                    this.click(depth, &clicked, window, cx);
+                }),
+            )
+            .children(submenu)
+            .into_any_element()
+    }
+}
+
+impl Render for TrayMenu {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let dropdown = Dropdown::new(self.x, window, cx);
+        let items = self.root.children.clone();
+        let menu = dropdown.place(self.render_menu(&items, 0, dropdown.flip, cx));
+
+        div()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .size_full()
+            .text_color(theme.palette.text)
+            .font_family(theme.font_family.clone())
+            .font_weight(theme.font_weight)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _, window, cx| this.dismiss(window, cx)),
+            )
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(|this, _, window, cx| this.dismiss(window, cx)),
+            )
+            .child(menu)
+    }
+}
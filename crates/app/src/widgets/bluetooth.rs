@@ -0,0 +1,95 @@
+use futures_signals::signal::SignalExt;
+use futures_util::StreamExt;
+use services::bluetooth::{self, BluetoothData};
+use ui::prelude::*;
+
+use crate::bluetooth_popup::BluetoothPopupWindow;
+use crate::clients::Clients;
+use crate::config::BluetoothConfig;
+
+pub struct BluetoothWidget {
+    subscriber: Option<bluetooth::Subscriber>,
+    data: BluetoothData,
+}
+
+impl BluetoothWidget {
+    pub fn new<V: 'static>(_config: BluetoothConfig, cx: &mut Context<V>) -> Entity<Self> {
+        cx.new(|cx| {
+            let subscriber = Clients::bluetooth(cx);
+            cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+                let Some(subscriber) = subscriber.await else {
+                    return;
+                };
+                if this
+                    .update(cx, |this, _| this.subscriber = Some(subscriber.clone()))
+                    .is_err()
+                {
+                    return;
+                }
+
+                let mut signal = subscriber.subscribe().to_stream();
+                while let Some(data) = signal.next().await {
+                    let updated = this.update(cx, |this, cx| {
+                        this.data = data;
+                        cx.notify();
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
+                }
+            })
+            .detach();
+
+            Self {
+                subscriber: None,
+                data: BluetoothData::default(),
+            }
+        })
+    }
+
+    fn on_click(&mut self, event: &MouseDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(subscriber) = self.subscriber.clone() else {
+            return;
+        };
+        let display = window.display(cx).map(|display| display.id());
+        BluetoothPopupWindow::toggle(subscriber, display, event.position.x, cx);
+    }
+}
+
+impl Render for BluetoothWidget {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        // without BlueZ there is nothing to show
+        if self.subscriber.is_none() {
+            return h_flex();
+        }
+
+        let powered = self
+            .data
+            .adapter
+            .as_ref()
+            .is_some_and(|adapter| adapter.powered);
+        let connected = self.data.connected();
+        let icon_path = match (powered, connected) {
+            (false, _) => "icons/bluetooth-off.svg",
+            (true, 0) => "icons/bluetooth.svg",
+            (true, _) => "icons/bluetooth-connected.svg",
+        };
+        let color = if powered {
+            theme.palette.text
+        } else {
+            theme.palette.subtext
+        };
+
+        h_flex()
+            .gap_1()
+            .p_1()
+            .rounded(theme.radius)
+            .hover(|this| this.bg(theme.palette.hover))
+            .child(Icon::new(icon_path.into()).size(18.).color(color))
+            .when(powered && connected > 0, |this| {
+                this.child(connected.to_string())
+            })
+            .on_mouse_down(MouseButton::Left, cx.listener(Self::on_click))
+    }
+}
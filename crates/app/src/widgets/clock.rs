@@ -4,24 +4,33 @@ use chrono::{DateTime, Local};
 use gpui::{AsyncApp, WeakEntity};
 use ui::prelude::*;
 
+use crate::config::ClockConfig;
+
 pub struct Clock {
     date: DateTime<Local>,
+    config: ClockConfig,
 }
 
 impl Clock {
-    pub fn new<V: 'static>(cx: &mut Context<V>) -> Entity<Self> {
+    pub fn new<V: 'static>(config: ClockConfig, cx: &mut Context<V>) -> Entity<Self> {
         cx.new(|cx| {
-            let clock = Self { date: Local::now() };
+            let refresh_rate = Duration::from_millis(config.refresh_rate_ms);
+            let clock = Self {
+                date: Local::now(),
+                config,
+            };
 
             cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
                 loop {
-                    this.update(cx, |this: &mut Clock, cx| {
+                    let updated = this.update(cx, |this: &mut Clock, cx| {
                         this.date = Local::now();
                         cx.notify();
-                    })
-                    .ok();
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
 
-                    Timer::after(Duration::from_secs(1)).await;
+                    Timer::after(refresh_rate).await;
                 }
             })
             .detach();
@@ -35,6 +44,6 @@ impl Render for Clock {
     fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
         div()
             .flex()
-            .child(Button::new().label(self.date.format("%H:%M").to_string()))
+            .child(Button::new().label(self.date.format(&self.config.format).to_string()))
     }
 }
@@ -0,0 +1,25 @@
+use ui::prelude::*;
+
+use crate::config::LauncherButtonConfig;
+use crate::launcher::LauncherWindow;
+
+pub struct LauncherButton;
+
+impl LauncherButton {
+    pub fn new<V: 'static>(_config: LauncherButtonConfig, cx: &mut Context<V>) -> Entity<Self> {
+        cx.new(|_| Self)
+    }
+}
+
+impl Render for LauncherButton {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme();
+
+        div()
+            .p_1()
+            .rounded(theme.radius)
+            .hover(|this| this.bg(theme.palette.hover))
+            .child(Icon::new("icons/layout-grid.svg".into()).size(18.))
+            .on_mouse_down(MouseButton::Left, |_, _, cx| LauncherWindow::toggle(cx))
+    }
+}
@@ -0,0 +1,201 @@
+use futures_signals::signal::SignalExt;
+use futures_util::StreamExt;
+use services::mpris::{self, LoopStatus, MprisData, PlaybackStatus, Player};
+use tracing::warn;
+use ui::prelude::*;
+
+use crate::clients::Clients;
+use crate::config::MediaConfig;
+use crate::media_popup::MediaPopupWindow;
+
+/// What to show for the track of `player`, falling back to the player name.
+fn track(player: &Player) -> String {
+    let metadata = &player.metadata;
+    match (&metadata.title, metadata.artists.is_empty()) {
+        (Some(title), true) => title.clone(),
+        (Some(title), false) => format!("{} - {title}", metadata.artists.join(", ")),
+        (None, _) => player.identity.clone(),
+    }
+}
+
+pub struct Media {
+    config: MediaConfig,
+    subscriber: Option<mpris::Subscriber>,
+    data: MprisData,
+}
+
+impl Media {
+    pub fn new<V: 'static>(config: MediaConfig, cx: &mut Context<V>) -> Entity<Self> {
+        cx.new(|cx| {
+            let subscriber = Clients::mpris(cx);
+            cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+                let Some(subscriber) = subscriber.await else {
+                    return;
+                };
+                if this
+                    .update(cx, |this, _| this.subscriber = Some(subscriber.clone()))
+                    .is_err()
+                {
+                    return;
+                }
+
+                let mut signal = subscriber.subscribe().to_stream();
+                while let Some(data) = signal.next().await {
+                    let updated = this.update(cx, |this, cx| {
+                        this.data = data;
+                        cx.notify();
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
+                }
+            })
+            .detach();
+
+            Self {
+                config,
+                subscriber: None,
+                data: MprisData::default(),
+            }
+        })
+    }
+
+    fn control(&mut self, control: Control, cx: &mut Context<Self>) {
+        let Some(subscriber) = self.subscriber.clone() else {
+            return;
+        };
+        let Some(name) = self.data.active.clone() else {
+            return;
+        };
+        cx.spawn(async move |_, _| control.send(&subscriber, &name).await)
+            .detach();
+    }
+
+    fn toggle_popup(
+        &mut self,
+        event: &MouseDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(subscriber) = self.subscriber.clone() else {
+            return;
+        };
+        let display = window.display(cx).map(|display| display.id());
+        MediaPopupWindow::toggle(subscriber, display, event.position.x, cx);
+    }
+
+    /// Makes the player after the active one active.
+    fn switch_player(&mut self) {
+        let Some(subscriber) = &self.subscriber else {
+            return;
+        };
+        let players = &self.data.players;
+        let index = players
+            .iter()
+            .position(|player| Some(&player.name) == self.data.active.as_ref())
+            .map_or(0, |index| (index + 1) % players.len());
+        if let Some(player) = players.get(index) {
+            subscriber.set_active(&player.name);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Control {
+    Previous,
+    PlayPause,
+    Next,
+    Shuffle(bool),
+    Loop(LoopStatus),
+}
+
+impl Control {
+    /// Sends the control to the player `name`, logging when it fails.
+    pub async fn send(self, subscriber: &mpris::Subscriber, name: &str) {
+        let result = match self {
+            Control::Previous => subscriber.previous(name).await,
+            Control::PlayPause => subscriber.play_pause(name).await,
+            Control::Next => subscriber.next(name).await,
+            Control::Shuffle(shuffle) => subscriber.set_shuffle(name, shuffle).await,
+            Control::Loop(status) => subscriber.set_loop_status(name, status).await,
+        };
+        if let Err(err) = result {
+            warn!("media player {name} didn't handle {self:?}: {err:#}");
+        }
+    }
+}
+
+impl Render for Media {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let Some(player) = self.data.active().cloned() else {
+            return h_flex();
+        };
+
+        let button =
+            |icon: &'static str, enabled: bool, action: Control, cx: &mut Context<Self>| {
+                let color = if enabled {
+                    theme.palette.text
+                } else {
+                    theme.palette.subtext
+                };
+                div()
+                    .p_0p5()
+                    .rounded(theme.radius)
+                    .when(enabled, |this| {
+                        this.hover(|this| this.bg(theme.palette.hover))
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, _, _, cx| this.control(action, cx)),
+                            )
+                    })
+                    .child(Icon::new(icon.into()).size(16.).color(color))
+            };
+        let (play_pause, can_play_pause) = if player.status == PlaybackStatus::Playing {
+            ("icons/pause.svg", player.can_pause)
+        } else {
+            ("icons/play.svg", player.can_play)
+        };
+
+        h_flex()
+            .gap_1()
+            .child(button(
+                "icons/skip-back.svg",
+                player.can_go_previous,
+                Control::Previous,
+                cx,
+            ))
+            .child(button(play_pause, can_play_pause, Control::PlayPause, cx))
+            .child(button(
+                "icons/skip-forward.svg",
+                player.can_go_next,
+                Control::Next,
+                cx,
+            ))
+            .child(
+                div()
+                    .max_w(px(self.config.max_width))
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .child(track(&player))
+                    .on_mouse_down(MouseButton::Left, cx.listener(Self::toggle_popup)),
+            )
+            .when(self.data.players.len() > 1, |this| {
+                this.child(
+                    div()
+                        .p_0p5()
+                        .rounded(theme.radius)
+                        .hover(|this| this.bg(theme.palette.hover))
+                        .child(
+                            Icon::new("icons/chevrons-up-down.svg".into())
+                                .size(14.)
+                                .color(theme.palette.subtext),
+                        )
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(|this, _, _, _| this.switch_player()),
+                        ),
+                )
+            })
+    }
+}
@@ -0,0 +1,124 @@
+use services::audio::source::Source;
+use services::audio::{self, Client};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+use ui::prelude::*;
+
+use crate::clients::Clients;
+use crate::config::MicrophoneConfig;
+
+pub struct Microphone {
+    client: Arc<Client>,
+    /// The default source.
+    source: Option<Source>,
+    /// How many streams record from a microphone.
+    recording: usize,
+    config: MicrophoneConfig,
+}
+
+impl Microphone {
+    pub fn new<V: 'static>(config: MicrophoneConfig, cx: &mut Context<V>) -> Entity<Self> {
+        cx.new(|cx| {
+            let client = Clients::audio(cx);
+
+            let mut this = Self {
+                client,
+                source: None,
+                recording: 0,
+                config,
+            };
+            // the client is shared between widgets, so it may already be connected
+            this.refresh();
+
+            let mut events = this.client.subscribe();
+            cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+                loop {
+                    match events.recv().await {
+                        Ok(
+                            audio::Event::Connected
+                            | audio::Event::Disconnected
+                            | audio::Event::AddSource(_)
+                            | audio::Event::UpdateSource(_)
+                            | audio::Event::RemoveSource(_)
+                            | audio::Event::AddOutput(_)
+                            | audio::Event::UpdateOutput(_)
+                            | audio::Event::RemoveOutput(_),
+                        )
+                        | Err(RecvError::Lagged(_)) => {}
+                        Ok(_) => continue,
+                        Err(RecvError::Closed) => break,
+                    }
+
+                    let updated = this.update(cx, |this, cx| {
+                        this.refresh();
+                        cx.notify();
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
+                }
+            })
+            .detach();
+
+            this
+        })
+    }
+
+    fn refresh(&mut self) {
+        self.source = self.client.default_source();
+        self.recording = self.client.recording().len();
+    }
+
+    fn toggle_mute(
+        &mut self,
+        _event: &MouseDownEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(source) = &mut self.source else {
+            return;
+        };
+        source.muted = !source.muted;
+        let client = self.client.clone();
+        let (name, muted) = (source.name.clone(), source.muted);
+        cx.spawn(async move |_, _| {
+            if let Err(err) = client.set_source_muted(&name, muted).await {
+                warn!("{err:#}");
+            }
+        })
+        .detach();
+        cx.notify();
+    }
+}
+
+impl Render for Microphone {
+    fn render(&mut self, _window: &mut ui::Window, cx: &mut Context<'_, Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let Some(source) = &self.source else {
+            return h_flex();
+        };
+        if self.config.hide_when_idle && self.recording == 0 {
+            return h_flex();
+        }
+
+        let icon_path = if source.muted {
+            "icons/mic-off.svg"
+        } else {
+            "icons/mic.svg"
+        };
+        // an app recording is worth noticing, even through a muted microphone
+        let color = if self.recording > 0 {
+            theme.palette.accent
+        } else {
+            theme.palette.text
+        };
+
+        h_flex()
+            .p_1()
+            .rounded(theme.radius)
+            .hover(|this| this.bg(theme.palette.hover))
+            .child(Icon::new(icon_path.into()).size(18.).color(color))
+            .on_mouse_down(MouseButton::Left, cx.listener(Self::toggle_mute))
+    }
+}
@@ -1,6 +1,54 @@
+pub mod bluetooth;
 pub mod clock;
+pub mod launcher;
+pub mod media;
+pub mod microphone;
 pub mod network;
+pub mod notifications;
 pub mod sysinfo;
+pub mod tray;
 pub mod upower;
 pub mod volume;
 pub mod workspace;
+
+use gpui::AnyView;
+use ui::prelude::*;
+
+use crate::config::WidgetConfig;
+
+use self::bluetooth::BluetoothWidget;
+use self::clock::Clock;
+use self::launcher::LauncherButton;
+use self::media::Media;
+use self::microphone::Microphone;
+use self::network::NetworkWidget;
+use self::notifications::NotificationsButton;
+use self::sysinfo::SysInfoWidget;
+use self::tray::Tray;
+use self::upower::Upower;
+use self::volume::Volume;
+use self::workspace::Workspaces;
+
+/// Creates the widget described by `config`, for the bar on `output`.
+pub fn build<V: 'static>(
+    config: &WidgetConfig,
+    output: Option<&str>,
+    cx: &mut Context<V>,
+) -> AnyView {
+    match config {
+        WidgetConfig::Workspaces(config) => {
+            Workspaces::new(config.clone(), output.map(ToOwned::to_owned), cx).into()
+        }
+        WidgetConfig::Clock(config) => Clock::new(config.clone(), cx).into(),
+        WidgetConfig::Network(config) => NetworkWidget::new(config.clone(), cx).into(),
+        WidgetConfig::SysInfo(config) => SysInfoWidget::new(config.clone(), cx).into(),
+        WidgetConfig::Volume(config) => Volume::new(config.clone(), cx).into(),
+        WidgetConfig::Microphone(config) => Microphone::new(config.clone(), cx).into(),
+        WidgetConfig::Upower(config) => Upower::new(config.clone(), cx).into(),
+        WidgetConfig::Launcher(config) => LauncherButton::new(config.clone(), cx).into(),
+        WidgetConfig::Tray(config) => Tray::new(config.clone(), cx).into(),
+        WidgetConfig::Notifications(config) => NotificationsButton::new(config.clone(), cx).into(),
+        WidgetConfig::Media(config) => Media::new(config.clone(), cx).into(),
+        WidgetConfig::Bluetooth(config) => BluetoothWidget::new(config.clone(), cx).into(),
+    }
+}
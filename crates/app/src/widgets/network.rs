@@ -7,6 +7,10 @@ use services::network::ActiveConnectionInfo::WiFi;
 use tracing::error;
 use ui::prelude::*;
 
+use crate::clients::Clients;
+use crate::config::NetworkConfig;
+use crate::wifi_popup::WifiPopupWindow;
+
 #[allow(dead_code)]
 #[derive(PartialEq)]
 enum State {
@@ -18,34 +22,51 @@ enum State {
 }
 
 pub struct NetworkWidget {
+    subscriber: Option<network::Subscriber>,
     state: State,
     icon_path: SharedString,
     rx_speed: String,
     tx_speed: String,
+    config: NetworkConfig,
 }
 
 impl NetworkWidget {
-    pub fn new<V: 'static>(cx: &mut Context<V>) -> Entity<Self> {
+    pub fn new<V: 'static>(config: NetworkConfig, cx: &mut Context<V>) -> Entity<Self> {
         cx.new(|cx| {
+            let refresh_rate_ms = config.refresh_rate_ms;
             let widget = Self {
+                subscriber: None,
                 state: State::WifiConnected,
                 icon_path: SharedString::new("icons/wifi-off.svg"),
                 rx_speed: "0.0 kb/s".to_string(),
                 tx_speed: "0.0 kb/s".to_string(),
+                config,
             };
 
+            let subscriber = Clients::network(cx);
             cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
-                let mut subscriber = network::Subscriber::new().await.unwrap();
-                // todo: move to config
-                subscriber.set_refresh_rate_ms(5000);
+                let Some(subscriber) = subscriber.await else {
+                    return;
+                };
+                let stored = this.update(cx, |this, _| {
+                    this.subscriber = Some(subscriber.clone());
+                });
+                if stored.is_err() {
+                    return;
+                }
+                if let Err(err) = subscriber.set_refresh_rate_ms(refresh_rate_ms).await {
+                    error!("failed to set network statistics refresh rate: {err:#}");
+                }
 
                 let mut signal = subscriber.subscribe().to_stream();
                 while let Some(data) = signal.next().await {
-                    this.update(cx, |this: &mut Self, cx| {
+                    let updated = this.update(cx, |this: &mut Self, cx| {
                         this.update(&data);
                         cx.notify();
-                    })
-                    .ok();
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
                 }
             })
             .detach();
@@ -74,10 +95,10 @@ impl NetworkWidget {
                 strength, device, ..
             } => {
                 match strength {
-                    s if *s < 25 => {
+                    s if *s < self.config.low_threshold => {
                         self.icon_path = SharedString::new("icons/wifi-low.svg");
                     }
-                    s if *s < 50 => {
+                    s if *s < self.config.high_threshold => {
                         self.icon_path = SharedString::new("icons/wifi-high.svg");
                     }
                     _ => {
@@ -97,6 +118,20 @@ impl NetworkWidget {
             }
         }
     }
+
+    fn on_click(&mut self, event: &MouseDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(subscriber) = self.subscriber.clone() else {
+            return;
+        };
+        let display = window.display(cx).map(|display| display.id());
+        WifiPopupWindow::toggle(
+            subscriber,
+            self.config.clone(),
+            display,
+            event.position.x,
+            cx,
+        );
+    }
 }
 
 fn format_speed(speed: f64) -> String {
@@ -108,18 +143,26 @@ fn format_speed(speed: f64) -> String {
 }
 
 impl Render for NetworkWidget {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<'_, Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<'_, Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
         h_flex()
-            .when(self.state == State::WifiConnected, |elm| {
-                elm.text_size(px(9.)).child(
-                    div()
-                        .v_flex()
-                        .w_16()
-                        .text_center()
-                        .child(self.tx_speed.to_string())
-                        .child(self.rx_speed.to_string()),
-                )
-            })
+            .p_1()
+            .rounded(theme.radius)
+            .hover(|this| this.bg(theme.palette.hover))
+            .when(
+                self.config.show_speed && self.state == State::WifiConnected,
+                |elm| {
+                    elm.text_size(px(9.)).child(
+                        div()
+                            .v_flex()
+                            .w_16()
+                            .text_center()
+                            .child(self.tx_speed.to_string())
+                            .child(self.rx_speed.to_string()),
+                    )
+                },
+            )
             .child(Icon::new(self.icon_path.clone()).size(18.))
+            .on_mouse_down(MouseButton::Left, cx.listener(Self::on_click))
     }
 }
@@ -0,0 +1,107 @@
+use futures_signals::signal::SignalExt;
+use futures_util::StreamExt;
+use services::notifications;
+use ui::prelude::*;
+
+use crate::clients::Clients;
+use crate::config::NotificationsButtonConfig;
+use crate::notifications::NotificationCenterWindow;
+
+pub struct NotificationsButton {
+    subscriber: Option<notifications::Subscriber>,
+    unread: usize,
+    do_not_disturb: bool,
+}
+
+impl NotificationsButton {
+    pub fn new<V: 'static>(
+        _config: NotificationsButtonConfig,
+        cx: &mut Context<V>,
+    ) -> Entity<Self> {
+        cx.new(|cx| {
+            let subscriber = Clients::notifications(cx);
+            cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+                let Some(subscriber) = subscriber.await else {
+                    return;
+                };
+                if this
+                    .update(cx, |this, _| this.subscriber = Some(subscriber.clone()))
+                    .is_err()
+                {
+                    return;
+                }
+
+                let mut signal = subscriber.subscribe().to_stream();
+                while let Some(data) = signal.next().await {
+                    let updated = this.update(cx, |this, cx| {
+                        this.unread = data.unread;
+                        this.do_not_disturb = data.do_not_disturb;
+                        cx.notify();
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
+                }
+            })
+            .detach();
+
+            Self {
+                subscriber: None,
+                unread: 0,
+                do_not_disturb: false,
+            }
+        })
+    }
+
+    fn on_click(&mut self, event: &MouseDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(subscriber) = self.subscriber.clone() else {
+            return;
+        };
+        let display = window.display(cx).map(|display| display.id());
+        NotificationCenterWindow::toggle(subscriber, display, event.position.x, cx);
+    }
+}
+
+impl Render for NotificationsButton {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let icon_path = if self.do_not_disturb {
+            "icons/bell-off.svg"
+        } else {
+            "icons/bell.svg"
+        };
+        // more than two digits wouldn't fit
+        let badge = match self.unread {
+            0 => None,
+            unread @ 1..100 => Some(unread.to_string()),
+            _ => Some("99+".to_string()),
+        };
+
+        div()
+            .relative()
+            .p_1()
+            .rounded(theme.radius)
+            .hover(|this| this.bg(theme.palette.hover))
+            .child(Icon::new(icon_path.into()).size(18.))
+            .when_some(badge, |this, badge| {
+                this.child(
+                    div()
+                        .absolute()
+                        .top_0()
+                        .right_0()
+                        .min_w(px(14.))
+                        .h(px(14.))
+                        .px_0p5()
+                        .rounded_full()
+                        .bg(theme.palette.accent)
+                        .text_color(theme.palette.background)
+                        .text_size(px(9.))
+                        .flex()
+                        .items_center()
+                        .justify_center()
+                        .child(badge),
+                )
+            })
+            .on_mouse_down(MouseButton::Left, cx.listener(Self::on_click))
+    }
+}
@@ -4,14 +4,16 @@ use services::sysinfo::SysInfoSubscriber;
 use std::sync::Arc;
 use ui::prelude::*;
 
+use crate::config::SysInfoConfig;
+
 pub struct SysInfoWidget {
     sysinfo: Arc<SysInfoSubscriber>,
     cpu_usage: f32,
 }
 
 impl SysInfoWidget {
-    pub fn new<V: 'static>(cx: &mut Context<V>) -> Entity<Self> {
-        let sysinfo = SysInfoSubscriber::new(1000); // 1s 刷新
+    pub fn new<V: 'static>(config: SysInfoConfig, cx: &mut Context<V>) -> Entity<Self> {
+        let sysinfo = SysInfoSubscriber::new(config.refresh_rate_ms);
         let entity = cx.new(|_| Self {
             sysinfo: sysinfo.clone(),
             cpu_usage: 0.0,
@@ -22,12 +24,13 @@ impl SysInfoWidget {
         cx.spawn(async move |_, cx: &mut AsyncApp| {
             let mut signal_stream = sysinfo.data.signal_cloned().to_stream();
             while let Some(data) = signal_stream.next().await {
-                if let Some(this) = weak.upgrade() {
-                    cx.update_entity(&this, |state, _| {
-                        state.cpu_usage = data.cpu_usage;
-                    })
-                    .ok();
-                }
+                let Some(this) = weak.upgrade() else {
+                    break;
+                };
+                cx.update_entity(&this, |state, _| {
+                    state.cpu_usage = data.cpu_usage;
+                })
+                .ok();
             }
         })
         .detach();
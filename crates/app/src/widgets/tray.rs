@@ -0,0 +1,238 @@
+use std::path::Path;
+
+use futures_signals::signal::SignalExt;
+use futures_util::StreamExt;
+use gpui::{ScrollDelta, ScrollWheelEvent, WeakEntity, img};
+use services::tray::{self, IconPixmap, Orientation, Status, TrayItem};
+use tracing::warn;
+use ui::prelude::*;
+
+use crate::clients::Clients;
+use crate::config::TrayConfig;
+use crate::icons::{IconImage, find_tray_icon};
+use crate::tray_menu::TrayMenuWindow;
+
+/// Looks up the icon for the state the item is in, preferring named icons over pixmaps
+/// and the pixmap closest to `size` pixels.
+fn find_icon(item: &TrayItem, size: f32) -> Option<IconImage> {
+    let attention = item.status == Status::NeedsAttention;
+    let names = [
+        item.attention_icon_name.as_ref().filter(|_| attention),
+        item.icon_name.as_ref(),
+    ];
+    let pixmaps = [
+        Some(&item.attention_icon_pixmaps).filter(|_| attention),
+        Some(&item.icon_pixmaps),
+    ];
+
+    let theme_path = item.icon_theme_path.as_deref().map(Path::new);
+    let file = names
+        .into_iter()
+        .flatten()
+        .find_map(|name| find_tray_icon(name, theme_path));
+    if let Some(file) = file {
+        return Some(IconImage::File(file.into()));
+    }
+
+    let pixmap = pixmaps
+        .into_iter()
+        .flatten()
+        .find_map(|pixmaps| closest_pixmap(pixmaps, size))?;
+    Some(IconImage::from_argb(
+        pixmap.width,
+        pixmap.height,
+        &pixmap.data,
+    ))
+}
+
+/// The smallest pixmap at least `size` pixels wide, or else the largest one.
+fn closest_pixmap(pixmaps: &[IconPixmap], size: f32) -> Option<&IconPixmap> {
+    pixmaps
+        .iter()
+        .filter(|pixmap| pixmap.width as f32 >= size)
+        .min_by_key(|pixmap| pixmap.width)
+        .or_else(|| pixmaps.iter().max_by_key(|pixmap| pixmap.width))
+}
+
+struct Item {
+    item: TrayItem,
+    icon: Option<IconImage>,
+}
+
+pub struct Tray {
+    config: TrayConfig,
+    subscriber: Option<tray::Subscriber>,
+    items: Vec<Item>,
+}
+
+impl Tray {
+    pub fn new<V: 'static>(config: TrayConfig, cx: &mut Context<V>) -> Entity<Self> {
+        cx.new(|cx| {
+            let subscriber = Clients::tray(cx);
+            cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+                let Some(subscriber) = subscriber.await else {
+                    return;
+                };
+                if this
+                    .update(cx, |this, _| this.subscriber = Some(subscriber.clone()))
+                    .is_err()
+                {
+                    return;
+                }
+
+                let mut signal = subscriber.subscribe().to_stream();
+                while let Some(items) = signal.next().await {
+                    let Ok(size) = this.update(cx, |this, _| this.config.icon_size * 2.) else {
+                        break;
+                    };
+                    // icon lookups hit the disk
+                    let items = cx
+                        .background_executor()
+                        .spawn(async move {
+                            items
+                                .into_iter()
+                                .map(|item| Item {
+                                    icon: find_icon(&item, size),
+                                    item,
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                        .await;
+
+                    let updated = this.update(cx, |this, cx| {
+                        this.items = items;
+                        cx.notify();
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
+                }
+            })
+            .detach();
+
+            Self {
+                config,
+                subscriber: None,
+                items: Vec::new(),
+            }
+        })
+    }
+
+    fn on_click(
+        &mut self,
+        id: &str,
+        button: MouseButton,
+        event: &MouseDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(subscriber) = self.subscriber.clone() else {
+            return;
+        };
+        let Some(item) = self.items.iter().find(|item| item.item.id == id) else {
+            return;
+        };
+        let item_is_menu = item.item.item_is_menu;
+        let has_menu = item.item.menu.is_some();
+
+        // items expect screen coordinates, the bar is assumed to sit at the output origin
+        let position = event.position;
+        let (x, y) = ((position.x / px(1.)) as i32, (position.y / px(1.)) as i32);
+        let display = window.display(cx).map(|display| display.id());
+        let id = id.to_string();
+        cx.spawn(async move |_, cx: &mut AsyncApp| {
+            let result = match button {
+                MouseButton::Left if !item_is_menu => subscriber.activate(&id, x, y).await,
+                MouseButton::Middle => subscriber.secondary_activate(&id, x, y).await,
+                // showing the menu ourselves, items without one may still draw their own
+                _ if has_menu => match subscriber.menu(&id).await {
+                    Ok(Some(menu)) => {
+                        cx.update(|cx| TrayMenuWindow::open(menu, display, position.x, cx))
+                            .ok();
+                        Ok(())
+                    }
+                    Ok(None) => subscriber.context_menu(&id, x, y).await,
+                    Err(err) => Err(err),
+                },
+                _ => subscriber.context_menu(&id, x, y).await,
+            };
+            if let Err(err) = result {
+                warn!("tray item {id} didn't handle the click: {err:#}");
+            }
+        })
+        .detach();
+    }
+
+    fn on_scroll(&mut self, id: &str, event: &ScrollWheelEvent, cx: &mut Context<Self>) {
+        let Some(subscriber) = self.subscriber.clone() else {
+            return;
+        };
+
+        let (delta, orientation) = match event.delta {
+            ScrollDelta::Lines(delta) if delta.y != 0. => (delta.y, Orientation::Vertical),
+            ScrollDelta::Lines(delta) => (delta.x, Orientation::Horizontal),
+            ScrollDelta::Pixels(delta) if delta.y != px(0.) => {
+                (delta.y / px(1.), Orientation::Vertical)
+            }
+            ScrollDelta::Pixels(delta) => (delta.x / px(1.), Orientation::Horizontal),
+        };
+        let delta = delta.round() as i32;
+        if delta == 0 {
+            return;
+        }
+
+        let id = id.to_string();
+        cx.spawn(async move |_, _| {
+            if let Err(err) = subscriber.scroll(&id, delta, orientation).await {
+                warn!("tray item {id} didn't handle scrolling: {err:#}");
+            }
+        })
+        .detach();
+    }
+}
+
+impl Render for Tray {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme();
+        let size = px(self.config.icon_size);
+
+        h_flex().gap_1().children(
+            self.items
+                .iter()
+                .filter(|item| self.config.show_passive || item.item.status != Status::Passive)
+                .map(|item| {
+                    let id = item.item.id.clone();
+                    let icon = match &item.icon {
+                        Some(icon) => img(icon.source()).size(size).into_any_element(),
+                        // better than nothing for items whose icon can't be found
+                        None => div()
+                            .size(size)
+                            .flex()
+                            .items_center()
+                            .justify_center()
+                            .child(item.item.title.chars().take(1).collect::<String>())
+                            .into_any_element(),
+                    };
+
+                    let mut button = div()
+                        .p_0p5()
+                        .rounded(theme.radius)
+                        .hover(|this| this.bg(theme.palette.hover))
+                        .child(icon);
+                    for mouse_button in [MouseButton::Left, MouseButton::Middle, MouseButton::Right]
+                    {
+                        let id = id.clone();
+                        button = button.on_mouse_down(
+                            mouse_button,
+                            cx.listener(move |this, event: &MouseDownEvent, window, cx| {
+                                this.on_click(&id, mouse_button, event, window, cx)
+                            }),
+                        );
+                    }
+                    button.on_scroll_wheel(cx.listener(
+                        move |this, event: &ScrollWheelEvent, _, cx| this.on_scroll(&id, event, cx),
+                    ))
+                }),
+        )
+    }
+}
@@ -1,36 +1,53 @@
 use futures_signals::signal::SignalExt;
 use futures_util::StreamExt;
 use gpui::{AsyncApp, WeakEntity};
-use services::upower::{self, BatteryState, UpowerData};
+use services::upower::{BatteryState, UpowerData};
 use ui::prelude::*;
 
+use crate::clients::Clients;
+use crate::config::UpowerConfig;
+
+/// Battery levels that get a highlighted icon.
+#[derive(Clone, Copy)]
+enum Alert {
+    Warning,
+    Low,
+}
+
 pub struct Upower {
     label: String,
     status: BatteryState,
     icon_path: SharedString,
-    icon_color: Option<Rgba>,
+    alert: Option<Alert>,
+    config: UpowerConfig,
 }
 
 impl Upower {
-    pub fn new<V: 'static>(cx: &mut Context<V>) -> Entity<Self> {
+    pub fn new<V: 'static>(config: UpowerConfig, cx: &mut Context<V>) -> Entity<Self> {
         cx.new(|cx| {
             let upower = Upower {
                 label: "Battery".to_string(),
                 status: BatteryState::Unknown,
                 icon_path: SharedString::new("icons/battery.svg"),
-                icon_color: None,
+                alert: None,
+                config,
             };
 
+            let subscriber = Clients::upower(cx);
             cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
-                let subscriber = upower::Subscriber::new().await.unwrap();
+                let Some(subscriber) = subscriber.await else {
+                    return;
+                };
 
                 let mut signal = subscriber.subscribe().to_stream();
                 while let Some(data) = signal.next().await {
-                    this.update(cx, |this: &mut Self, cx| {
+                    let updated = this.update(cx, |this: &mut Self, cx| {
                         this.update(&data);
                         cx.notify();
-                    })
-                    .ok();
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
                 }
             })
             .detach();
@@ -42,22 +59,22 @@ impl Upower {
     fn update(&mut self, properties: &UpowerData) {
         self.label = format!("{}", properties.percentage);
         self.status = properties.state;
-        self.icon_color = None;
+        self.alert = None;
         self.icon_path = match properties.state {
             BatteryState::FullyCharged | BatteryState::Charging | BatteryState::PendingCharge => {
                 "icons/battery-charging.svg"
             }
             BatteryState::Discharging | BatteryState::PendingDischarge => {
                 match properties.percentage {
-                    p if p <= 10.0 => {
-                        self.icon_color = Some(rgb(0xff8000));
+                    p if p <= self.config.warning_threshold => {
+                        self.alert = Some(Alert::Warning);
                         "icons/battery-warning.svg"
                     }
-                    p if p <= 30.0 => {
-                        self.icon_color = Some(rgb(0xff0000));
+                    p if p <= self.config.low_threshold => {
+                        self.alert = Some(Alert::Low);
                         "icons/battery-low.svg"
                     }
-                    p if p <= 80.0 => "icons/battery-medium.svg",
+                    p if p <= self.config.medium_threshold => "icons/battery-medium.svg",
                     _ => "icons/battery-full.svg",
                 }
             }
@@ -68,13 +85,19 @@ impl Upower {
 }
 
 impl Render for Upower {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let palette = &cx.theme().palette;
+        let icon_color = self.alert.map(|alert| match alert {
+            Alert::Warning => palette.warning,
+            Alert::Low => palette.error,
+        });
+
         h_flex()
             .gap_1()
             .child(
                 Icon::new(self.icon_path.clone())
                     .size(20.)
-                    .when_some(self.icon_color, |this, color| this.color(color)),
+                    .when_some(icon_color, |this, color| this.color(color)),
             )
             .child(div().text_center().child(self.label.to_string()))
     }
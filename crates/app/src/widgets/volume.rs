@@ -1,18 +1,26 @@
-use services::audio::{self, Client};
+use services::audio::{self, Client, sink::Sink};
 use std::sync::Arc;
-use tracing::{debug, info};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info, warn};
 use ui::prelude::*;
 
+use crate::clients::Clients;
+use crate::config::VolumeConfig;
+use crate::mixer_popup::MixerPopupWindow;
+
 pub struct Volume {
     slider: Entity<Slider>,
     muted: bool,
     client: Arc<Client>,
     volume: f32,
     sink_name: String,
+    /// Whether the audio server is there, the widget is greyed out otherwise.
+    connected: bool,
+    config: VolumeConfig,
 }
 
 impl Volume {
-    pub fn new<V: 'static>(cx: &mut Context<V>) -> Entity<Self> {
+    pub fn new<V: 'static>(config: VolumeConfig, cx: &mut Context<V>) -> Entity<Self> {
         cx.new(|cx| {
             let vol_slider = cx.new(|_| {
                 Slider::new()
@@ -22,51 +30,77 @@ impl Volume {
                     .default_value(50.)
             });
 
-            let client = audio::create_client();
+            let client = Clients::audio(cx);
 
-            let this = Self {
+            let connected = client.is_connected();
+            let mut this = Self {
                 slider: vol_slider,
                 muted: false,
                 client,
                 volume: 0.5,
                 sink_name: "default".to_string(),
+                connected,
+                config,
             };
 
+            // the client is shared between widgets, so it may already be connected
+            let active_sink = this
+                .client
+                .sinks()
+                .lock()
+                .ok()
+                .and_then(|sinks| sinks.iter().find(|sink| sink.active).cloned());
+            if let Some(sink) = active_sink {
+                this.set_sink(&sink, cx);
+            }
+
             // 订阅 slider 变化，设置系统音量
             cx.subscribe(
                 &this.slider,
                 |state: &mut Self,
                  _slider: Entity<Slider>,
                  event: &SliderEvent,
-                 _cx: &mut Context<Self>| match event {
+                 cx: &mut Context<Self>| match event {
                     SliderEvent::Change(value) => {
                         debug!("Volume changed to {}", value);
 
-                        state.client.set_sink_volume(&state.sink_name, *value);
+                        let client = state.client.clone();
+                        let (name, value) = (state.sink_name.clone(), *value);
+                        cx.spawn(async move |_, _| {
+                            if let Err(err) = client.set_sink_volume(&name, value).await {
+                                warn!("{err:#}");
+                            }
+                        })
+                        .detach();
                     }
                 },
             )
             .detach();
 
+            let mut signal = this.client.subscribe();
             cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
-                let client = audio::create_client();
-
-                let mut signal = client.subscribe();
-                while let Ok(event) = signal.recv().await {
-                    info!("Received audio signal: {:?}", event);
-
-                    if let audio::Event::UpdateSink(sink) = event {
-                        if let Some(this) = this.upgrade() {
-                            cx.update_entity(&this, |state, cx| {
-                                state.volume = sink.volume;
-                                state.muted = sink.muted;
-                                state.sink_name = sink.index.to_string();
-                                cx.update_entity(&state.slider, |slider, cx| {
-                                    slider.set_value(sink.volume, cx);
-                                });
-                            })
-                            .ok();
+                loop {
+                    let event = match signal.recv().await {
+                        Ok(event) => {
+                            info!("Received audio signal: {:?}", event);
+                            Some(event)
+                        }
+                        // missed events are caught up on from the client
+                        Err(RecvError::Lagged(_)) => None,
+                        Err(RecvError::Closed) => break,
+                    };
+
+                    let updated = this.update(cx, |state, cx| match event {
+                        Some(audio::Event::UpdateSink(sink)) => state.set_sink(&sink, cx),
+                        Some(audio::Event::Connected | audio::Event::Disconnected) => {
+                            state.connected = state.client.is_connected();
+                            cx.notify();
                         }
+                        Some(_) => {}
+                        None => state.sync(cx),
+                    });
+                    if updated.is_err() {
+                        break;
                     }
                 }
             })
@@ -76,6 +110,36 @@ impl Volume {
         })
     }
 
+    /// Catches up on the client, after missing some of its events.
+    fn sync(&mut self, cx: &mut Context<Self>) {
+        self.connected = self.client.is_connected();
+        if let Some(sink) = self.client.default_sink() {
+            self.set_sink(&sink, cx);
+        }
+        cx.notify();
+    }
+
+    fn set_sink(&mut self, sink: &Sink, cx: &mut Context<Self>) {
+        self.volume = sink.volume;
+        self.muted = sink.muted;
+        self.sink_name = sink.index.to_string();
+        cx.update_entity(&self.slider, |slider, cx| {
+            slider.set_value(sink.volume, cx);
+        });
+        cx.notify();
+    }
+
+    fn open_mixer(&mut self, event: &MouseDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let display = window.display(cx).map(|display| display.id());
+        MixerPopupWindow::toggle(
+            self.client.clone(),
+            self.config.move_inputs,
+            display,
+            event.position.x,
+            cx,
+        );
+    }
+
     fn toggle_mute(
         &mut self,
         _event: &MouseDownEvent,
@@ -83,30 +147,47 @@ impl Volume {
         cx: &mut Context<Self>,
     ) {
         self.muted = !self.muted;
-        self.client.set_sink_muted(&self.sink_name, self.muted);
+        let client = self.client.clone();
+        let (name, muted) = (self.sink_name.clone(), self.muted);
+        cx.spawn(async move |_, _| {
+            if let Err(err) = client.set_sink_muted(&name, muted).await {
+                warn!("{err:#}");
+            }
+        })
+        .detach();
         cx.notify();
     }
 }
 
 impl Render for Volume {
     fn render(&mut self, _window: &mut ui::Window, cx: &mut Context<'_, Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
         let value = self.volume;
         let icon_path = if self.muted {
             "icons/volume-mute.svg"
-        } else if value < 30. {
+        } else if value < self.config.low_threshold as f32 {
             "icons/volume-low.svg"
-        } else if value < 70. {
+        } else if value < self.config.medium_threshold as f32 {
             "icons/volume-medium.svg"
         } else {
             "icons/volume-high.svg"
         };
 
+        // greyed out while there's no audio server
+        let color = if self.connected {
+            theme.palette.text
+        } else {
+            theme.palette.subtext
+        };
+
         h_flex()
             .gap_1()
+            .text_color(color)
             .child(
                 div()
-                    .child(Icon::new(icon_path.into()).size(18.))
-                    .on_mouse_down(MouseButton::Left, cx.listener(Self::toggle_mute)),
+                    .child(Icon::new(icon_path.into()).size(18.).color(color))
+                    .on_mouse_down(MouseButton::Left, cx.listener(Self::open_mixer))
+                    .on_mouse_down(MouseButton::Right, cx.listener(Self::toggle_mute)),
             )
             // todo: only show slider on hover
             .child(div().child(self.slider.clone()).w_20())
@@ -5,29 +5,44 @@ use services::hyprland::{self, Workspace, change_workspace_to};
 
 use ui::prelude::*;
 
+use crate::clients::Clients;
+use crate::config::WorkspacesConfig;
+
 pub struct Workspaces {
     workspaces: Vec<Workspace>,
     active: usize,
+    /// Only show the workspaces of this monitor.
+    monitor: Option<String>,
 }
 
 impl Workspaces {
-    pub fn new<V: 'static>(cx: &mut Context<V>) -> Entity<Self> {
+    pub fn new<V: 'static>(
+        _config: WorkspacesConfig,
+        monitor: Option<String>,
+        cx: &mut Context<V>,
+    ) -> Entity<Self> {
         cx.new(|cx| {
             let workspaces = Self {
                 workspaces: Vec::new(),
                 active: 0,
+                monitor,
             };
 
+            let subscriber = Clients::hyprland(cx);
             cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
-                let subscriber = hyprland::Subscriber::new().await.unwrap();
+                let Some(subscriber) = subscriber.await else {
+                    return;
+                };
 
                 let mut signal = subscriber.subscribe().to_stream();
                 while let Some(data) = signal.next().await {
-                    this.update(cx, |this: &mut Self, cx| {
+                    let updated = this.update(cx, |this: &mut Self, cx| {
                         this.update(&data);
                         cx.notify();
-                    })
-                    .ok();
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
                 }
             })
             .detach();
@@ -42,26 +57,28 @@ impl Workspaces {
     }
 }
 
-fn workspace_button(w: Workspace) -> impl IntoElement {
+fn workspace_button(w: Workspace, accent: Rgba) -> impl IntoElement {
     Button::new()
         .label(w.name)
         .size(28.)
         .border(2.)
         .rounded(10.)
-        .when(w.active, |this| this.border_color(rgb(0xffc0cb)))
+        .when(w.active, |this| this.border_color(accent))
         .on_click(move |_, _, _| {
             change_workspace_to(w.id).ok();
         })
 }
 
 impl Render for Workspaces {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<'_, Self>) -> impl IntoElement {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<'_, Self>) -> impl IntoElement {
+        let accent = cx.theme().palette.accent;
         let workspaces = self.workspaces.clone();
         h_flex().justify_between().children(
             workspaces
                 .into_iter()
                 .filter(|w| w.visible)
-                .map(workspace_button),
+                .filter(|w| self.monitor.is_none() || w.monitor == self.monitor)
+                .map(|w| workspace_button(w, accent)),
         )
     }
 }
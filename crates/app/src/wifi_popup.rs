@@ -0,0 +1,542 @@
+use std::collections::HashMap;
+
+use futures_signals::signal::SignalExt;
+use futures_util::StreamExt;
+use gpui::{AnyElement, DisplayId, FocusHandle, Global, KeyDownEvent, WindowHandle};
+use services::network::{self, AccessPoint, ActiveConnectionInfo, NetworkData, Security};
+use tracing::{error, warn};
+use ui::prelude::*;
+
+use crate::bars::Dropdown;
+use crate::config::NetworkConfig;
+
+const WIDTH: f32 = 320.;
+const MAX_HEIGHT: f32 = 420.;
+/// WPA passphrases are 8 to 63 characters long.
+const MIN_PASSWORD: usize = 8;
+const MAX_PASSWORD: usize = 63;
+
+/// Opens and closes the Wi-Fi popup, the networks in range dropping down from the bar.
+pub struct WifiPopupWindow {
+    handle: Option<WindowHandle<WifiPopup>>,
+}
+
+impl Global for WifiPopupWindow {}
+
+impl WifiPopupWindow {
+    pub fn init(cx: &mut App) {
+        cx.set_global(Self { handle: None });
+    }
+
+    pub fn toggle(
+        subscriber: network::Subscriber,
+        config: NetworkConfig,
+        display: Option<DisplayId>,
+        x: Pixels,
+        cx: &mut App,
+    ) {
+        if cx.global::<Self>().handle.is_some() {
+            Self::close(cx);
+        } else {
+            Self::open(subscriber, config, display, x, cx);
+        }
+    }
+
+    /// Shows the popup under the point `x` of the bar on `display`.
+    pub fn open(
+        subscriber: network::Subscriber,
+        config: NetworkConfig,
+        display: Option<DisplayId>,
+        x: Pixels,
+        cx: &mut App,
+    ) {
+        Self::close(cx);
+
+        let opts = Dropdown::window_options(display, "mgs-wifi", cx);
+        let handle = cx.open_window(opts, |window, cx| {
+            let popup = cx.new(|cx| WifiPopup::new(subscriber, config, x, cx));
+            window.focus(&popup.read(cx).focus_handle);
+            popup
+        });
+        match handle {
+            Ok(handle) => cx.global_mut::<Self>().handle = Some(handle),
+            Err(err) => error!("failed to open wifi popup window: {err:#}"),
+        }
+    }
+
+    pub fn close(cx: &mut App) {
+        if let Some(handle) = cx.global::<Self>().handle {
+            handle
+                .update(cx, |popup, window, cx| popup.dismiss(window, cx))
+                .ok();
+        }
+    }
+}
+
+/// The password being typed for a network joined for the first time.
+struct Prompt {
+    access_point: AccessPoint,
+    password: String,
+}
+
+pub struct WifiPopup {
+    subscriber: network::Subscriber,
+    config: NetworkConfig,
+    data: Option<NetworkData>,
+    scanning: bool,
+    /// Networks being joined or forgotten, by SSID, and what is done to them.
+    pending: HashMap<String, &'static str>,
+    prompt: Option<Prompt>,
+    /// Why the last network couldn't be joined.
+    error: Option<String>,
+    /// Where the popup was opened, in bar coordinates.
+    x: Pixels,
+    focus_handle: FocusHandle,
+}
+
+impl WifiPopup {
+    fn new(
+        subscriber: network::Subscriber,
+        config: NetworkConfig,
+        x: Pixels,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let signal = subscriber.subscribe();
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let mut signal = signal.to_stream();
+            while let Some(data) = signal.next().await {
+                let updated = this.update(cx, |this, cx| {
+                    this.data = Some(data);
+                    cx.notify();
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+
+        let mut popup = Self {
+            subscriber,
+            config,
+            data: None,
+            scanning: false,
+            pending: HashMap::new(),
+            prompt: None,
+            error: None,
+            x,
+            focus_handle: cx.focus_handle(),
+        };
+        popup.scan(cx);
+        popup
+    }
+
+    fn dismiss(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        cx.global_mut::<WifiPopupWindow>().handle = None;
+        window.remove_window();
+    }
+
+    fn scan(&mut self, cx: &mut Context<Self>) {
+        if self.scanning {
+            return;
+        }
+
+        self.scanning = true;
+        let subscriber = self.subscriber.clone();
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            if let Err(err) = subscriber.scan().await {
+                warn!("failed to scan for wifi networks: {err:#}");
+            }
+            this.update(cx, |this, cx| {
+                this.scanning = false;
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+        cx.notify();
+    }
+
+    /// Joins a saved or open network right away, and asks for the password of others.
+    fn activate(&mut self, access_point: &AccessPoint, cx: &mut Context<Self>) {
+        if self.pending.contains_key(&access_point.ssid) {
+            return;
+        }
+
+        self.error = None;
+        let secured = access_point.security.is_secured();
+        let supported = access_point.security != Security::Enterprise;
+        if access_point.connection.is_none() && secured && supported {
+            self.prompt = Some(Prompt {
+                access_point: access_point.clone(),
+                password: String::new(),
+            });
+            cx.notify();
+        } else {
+            self.connect(access_point.clone(), None, cx);
+        }
+    }
+
+    fn connect(
+        &mut self,
+        access_point: AccessPoint,
+        password: Option<String>,
+        cx: &mut Context<Self>,
+    ) {
+        let ssid = access_point.ssid.clone();
+        self.pending.insert(ssid.clone(), "Connecting…");
+        let subscriber = self.subscriber.clone();
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            let result = subscriber.connect(&access_point, password.as_deref()).await;
+            this.update(cx, |this, cx| {
+                this.pending.remove(&ssid);
+                if let Err(err) = result {
+                    warn!("failed to join {ssid}: {err:#}");
+                    this.error = Some(format!("Couldn't join {ssid}"));
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+        cx.notify();
+    }
+
+    fn forget(&mut self, access_point: AccessPoint, cx: &mut Context<Self>) {
+        let ssid = access_point.ssid.clone();
+        self.pending.insert(ssid.clone(), "Forgetting…");
+        let subscriber = self.subscriber.clone();
+        cx.spawn(async move |this: WeakEntity<Self>, cx: &mut AsyncApp| {
+            if let Err(err) = subscriber.forget(&access_point).await {
+                warn!("failed to forget {ssid}: {err:#}");
+            }
+            this.update(cx, |this, cx| {
+                this.pending.remove(&ssid);
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+        cx.notify();
+    }
+
+    fn submit_password(&mut self, cx: &mut Context<Self>) {
+        let Some(prompt) = &self.prompt else {
+            return;
+        };
+        // WEP keys are shorter
+        let long_enough =
+            prompt.access_point.security == Security::Wep || prompt.password.len() >= MIN_PASSWORD;
+        if prompt.password.is_empty() || !long_enough {
+            return;
+        }
+
+        if let Some(prompt) = self.prompt.take() {
+            self.connect(prompt.access_point, Some(prompt.password), cx);
+        }
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let keystroke = &event.keystroke;
+        let Some(prompt) = &mut self.prompt else {
+            if keystroke.key == "escape" {
+                self.dismiss(window, cx);
+            }
+            return;
+        };
+
+        match keystroke.key.as_str() {
+            "escape" => self.prompt = None,
+            "enter" => self.submit_password(cx),
+            "backspace" => {
+                prompt.password.pop();
+            }
+            _ if keystroke.modifiers.control
+                || keystroke.modifiers.alt
+                || keystroke.modifiers.platform => {}
+            _ => {
+                if let Some(text) = &keystroke.key_char {
+                    if prompt.password.len() + text.len() <= MAX_PASSWORD {
+                        prompt.password.push_str(text);
+                    }
+                }
+            }
+        }
+        cx.notify();
+    }
+
+    fn strength_icon(&self, strength: u8) -> &'static str {
+        if strength < self.config.low_threshold {
+            "icons/wifi-low.svg"
+        } else if strength < self.config.high_threshold {
+            "icons/wifi-high.svg"
+        } else {
+            "icons/wifi-full.svg"
+        }
+    }
+
+    fn render_header(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+        let color = if self.scanning {
+            theme.palette.subtext
+        } else {
+            theme.palette.text
+        };
+
+        h_flex()
+            .px_2()
+            .py_1()
+            .gap_2()
+            .child(div().flex_1().child("Wi-Fi"))
+            .child(
+                div()
+                    .p_1()
+                    .rounded(theme.radius)
+                    .hover(|this| this.bg(theme.palette.hover))
+                    .child(
+                        Icon::new("icons/refresh-cw.svg".into())
+                            .size(16.)
+                            .color(color),
+                    )
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _, _, cx| {
+                            cx.stop_propagation();
+                            this.scan(cx);
+                        }),
+                    ),
+            )
+    }
+
+    fn render_access_point(
+        &self,
+        access_point: &AccessPoint,
+        connected: bool,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let theme = cx.theme().clone();
+        let security = access_point.security;
+        let status = match self.pending.get(&access_point.ssid) {
+            Some(label) => label.to_string(),
+            None if connected => "Connected".to_string(),
+            None if access_point.connection.is_some() => "Saved".to_string(),
+            None => match security {
+                Security::Open => "Open",
+                Security::Wep => "WEP",
+                Security::Wpa => "WPA",
+                Security::Sae => "WPA3",
+                Security::Enterprise => "Enterprise",
+            }
+            .to_string(),
+        };
+        let color = if connected {
+            theme.palette.accent
+        } else {
+            theme.palette.text
+        };
+        let prompt = self
+            .prompt
+            .as_ref()
+            .filter(|prompt| prompt.access_point.ssid == access_point.ssid);
+        let (activated, forgotten) = (access_point.clone(), access_point.clone());
+
+        let row = h_flex()
+            .px_2()
+            .py_1p5()
+            .gap_2()
+            .rounded(theme.radius)
+            .hover(|this| this.bg(theme.palette.hover))
+            .child(
+                Icon::new(self.strength_icon(access_point.strength).into())
+                    .size(18.)
+                    .color(color),
+            )
+            .child(
+                v_flex()
+                    .flex_1()
+                    .min_w_0()
+                    .child(
+                        div()
+                            .overflow_hidden()
+                            .whitespace_nowrap()
+                            .child(access_point.ssid.clone()),
+                    )
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(theme.palette.subtext)
+                            .child(status),
+                    ),
+            )
+            .when(security.is_secured(), |this| {
+                this.child(
+                    Icon::new("icons/lock.svg".into())
+                        .size(14.)
+                        .color(theme.palette.subtext),
+                )
+            })
+            .when(access_point.connection.is_some(), |this| {
+                this.child(
+                    div()
+                        .p_0p5()
+                        .rounded(theme.radius)
+                        .hover(|this| this.bg(theme.palette.hover))
+                        .child(
+                            Icon::new("icons/x.svg".into())
+                                .size(14.)
+                                .color(theme.palette.subtext),
+                        )
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(move |this, _, _, cx| {
+                                cx.stop_propagation();
+                                this.forget(forgotten.clone(), cx);
+                            }),
+                        ),
+                )
+            })
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _, _, cx| {
+                    cx.stop_propagation();
+                    if !connected {
+                        this.activate(&activated, cx);
+                    }
+                }),
+            );
+
+        v_flex()
+            .gap_1()
+            .child(row)
+            .when_some(prompt, |this, prompt| {
+                let password = "•".repeat(prompt.password.chars().count());
+                this.child(
+                    h_flex()
+                        .px_2()
+                        .gap_2()
+                        .child(
+                            div()
+                                .flex_1()
+                                .px_2()
+                                .py_1()
+                                .rounded(theme.radius)
+                                .bg(theme.palette.surface)
+                                .text_sm()
+                                .child(if password.is_empty() {
+                                    div().text_color(theme.palette.subtext).child("Password")
+                                } else {
+                                    div().child(password)
+                                }),
+                        )
+                        .child(
+                            div()
+                                .px_3()
+                                .py_1()
+                                .rounded(theme.radius)
+                                .bg(theme.palette.accent)
+                                .text_color(theme.palette.background)
+                                .text_sm()
+                                .child("Join")
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(|this, _, _, cx| {
+                                        cx.stop_propagation();
+                                        this.submit_password(cx);
+                                    }),
+                                ),
+                        ),
+                )
+            })
+            .into_any_element()
+    }
+}
+
+impl Render for WifiPopup {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = cx.theme().clone();
+
+        let placeholder = |text: &'static str| {
+            div()
+                .py_6()
+                .flex()
+                .justify_center()
+                .text_color(theme.palette.subtext)
+                .child(text)
+                .into_any_element()
+        };
+        let content = match &self.data {
+            None => placeholder("Looking for networks…"),
+            Some(data) if !data.wifi_enabled => placeholder("Wi-Fi is off"),
+            Some(data) => {
+                let connected: Vec<_> = data
+                    .active_connections
+                    .iter()
+                    .filter_map(|connection| match connection {
+                        ActiveConnectionInfo::WiFi { name, .. } => Some(name.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                let access_points: Vec<_> = data
+                    .wireless_access_points
+                    .iter()
+                    // hidden networks have no name to show
+                    .filter(|access_point| !access_point.ssid.is_empty())
+                    .map(|access_point| {
+                        let active = connected.contains(&access_point.ssid);
+                        self.render_access_point(access_point, active, cx)
+                    })
+                    .collect();
+
+                if access_points.is_empty() {
+                    placeholder("No networks in range")
+                } else {
+                    v_flex()
+                        .id("wifi-networks")
+                        .max_h(px(MAX_HEIGHT))
+                        .overflow_y_scroll()
+                        .gap_1()
+                        .children(access_points)
+                        .into_any_element()
+                }
+            }
+        };
+
+        let panel = v_flex()
+            .w(px(WIDTH))
+            .p_2()
+            .gap_2()
+            .rounded(theme.radius)
+            .border_1()
+            .border_color(theme.palette.border)
+            .bg(theme.palette.background)
+            .occlude()
+            .child(self.render_header(cx))
+            .when_some(self.error.clone(), |this, error| {
+                this.child(
+                    div()
+                        .px_2()
+                        .text_sm()
+                        .text_color(theme.palette.error)
+                        .child(error),
+                )
+            })
+            .child(content);
+
+        div()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .size_full()
+            .text_color(theme.palette.text)
+            .font_family(theme.font_family.clone())
+            .font_weight(theme.font_weight)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _, window, cx| this.dismiss(window, cx)),
+            )
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(|this, _, window, cx| this.dismiss(window, cx)),
+            )
+            .child(Dropdown::new(self.x, window, cx).place(panel))
+    }
+}
@@ -0,0 +1,22 @@
+use zbus::proxy;
+use zbus::zvariant::{OwnedValue, Value};
+
+#[proxy(
+    interface = "org.freedesktop.portal.Settings",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+pub trait Settings {
+    /// Reads a single value, wrapped in an extra variant. Deprecated in favor of `ReadOne`.
+    fn read(&self, namespace: &str, key: &str) -> zbus::Result<OwnedValue>;
+
+    /// Reads a single value.
+    fn read_one(&self, namespace: &str, key: &str) -> zbus::Result<OwnedValue>;
+
+    /// Emitted when a setting changes.
+    #[zbus(signal)]
+    fn setting_changed(&self, namespace: &str, key: &str, value: Value<'_>) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn version(&self) -> zbus::Result<u32>;
+}
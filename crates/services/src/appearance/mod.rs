@@ -0,0 +1,214 @@
+pub mod dbus;
+
+use futures_signals::signal::{Mutable, MutableSignalCloned};
+use futures_util::StreamExt;
+use tracing::{debug, info, warn};
+use zbus::Connection;
+use zbus::zvariant::{OwnedValue, Value};
+
+use self::dbus::SettingsProxy;
+
+const NAMESPACE: &str = "org.freedesktop.appearance";
+const COLOR_SCHEME: &str = "color-scheme";
+const ACCENT_COLOR: &str = "accent-color";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorScheme {
+    #[default]
+    NoPreference,
+    Dark,
+    Light,
+}
+
+impl From<u32> for ColorScheme {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => Self::Dark,
+            2 => Self::Light,
+            _ => Self::NoPreference,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AppearanceData {
+    pub color_scheme: ColorScheme,
+    /// sRGB components between 0 and 1, `None` when the user didn't pick one.
+    pub accent_color: Option<(f64, f64, f64)>,
+}
+
+impl AppearanceData {
+    async fn init(settings: &SettingsProxy<'_>) -> Self {
+        let mut data = Self::default();
+        for key in [COLOR_SCHEME, ACCENT_COLOR] {
+            match read(settings, key).await {
+                Ok(value) => data.set(key, value),
+                Err(err) => debug!("failed to read {NAMESPACE} {key}: {err}"),
+            }
+        }
+
+        data
+    }
+
+    fn set(&mut self, key: &str, value: OwnedValue) {
+        match key {
+            COLOR_SCHEME => {
+                self.color_scheme = u32::try_from(value).map(Into::into).unwrap_or_default();
+            }
+            ACCENT_COLOR => {
+                // out of range components mean the accent color is unset
+                self.accent_color = <(f64, f64, f64)>::try_from(value)
+                    .ok()
+                    .filter(|(r, g, b)| [r, g, b].iter().all(|c| (0.0..=1.0).contains(*c)));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Follows the appearance settings of the XDG desktop portal.
+#[derive(Debug, Clone)]
+pub struct Subscriber {
+    data: Mutable<AppearanceData>,
+}
+
+impl Subscriber {
+    pub async fn new() -> anyhow::Result<Self> {
+        let conn = Connection::session().await?;
+        Self::with_connection(conn).await
+    }
+
+    /// Uses the portal on the given bus, e.g. a private one running a mock portal.
+    pub async fn with_connection(conn: Connection) -> anyhow::Result<Self> {
+        let settings = SettingsProxy::new(&conn).await?;
+        let mut changes = settings.receive_setting_changed().await?;
+        let data = Mutable::new(AppearanceData::init(&settings).await);
+
+        let data_for_task = data.clone();
+        tokio::spawn(async move {
+            info!("Appearance subscriber start");
+
+            while let Some(signal) = changes.next().await {
+                let Ok(args) = signal.args() else {
+                    continue;
+                };
+                if args.namespace() != &NAMESPACE {
+                    continue;
+                }
+
+                match args.value().try_to_owned() {
+                    Ok(value) => {
+                        debug!("{} changed: {:?}", args.key(), value);
+                        data_for_task.lock_mut().set(args.key(), value);
+                    }
+                    Err(err) => warn!("invalid value for {NAMESPACE} {}: {err}", args.key()),
+                }
+            }
+        });
+
+        Ok(Self { data })
+    }
+
+    pub fn get(&self) -> AppearanceData {
+        self.data.get_cloned()
+    }
+
+    pub fn subscribe(&self) -> MutableSignalCloned<AppearanceData> {
+        self.data.signal_cloned()
+    }
+}
+
+/// `ReadOne` only exists since version 2 of the interface, older portals only have `Read`.
+async fn read(settings: &SettingsProxy<'_>, key: &str) -> zbus::Result<OwnedValue> {
+    match settings.read_one(NAMESPACE, key).await {
+        Err(zbus::Error::MethodError(..)) => {
+            let value = settings.read(NAMESPACE, key).await?;
+            match &*value {
+                Value::Value(inner) => Ok(inner.try_to_owned()?),
+                _ => Ok(value),
+            }
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    use zbus::object_server::SignalEmitter;
+    use zbus::{Guid, connection, interface};
+
+    use super::*;
+
+    const PATH: &str = "/org/freedesktop/portal/desktop";
+
+    /// A portal with only `Read`, like the ones before `ReadOne`.
+    struct Portal {
+        color_scheme: u32,
+    }
+
+    #[interface(name = "org.freedesktop.portal.Settings")]
+    impl Portal {
+        fn read(&self, namespace: &str, key: &str) -> zbus::fdo::Result<OwnedValue> {
+            if (namespace, key) != (NAMESPACE, COLOR_SCHEME) {
+                return Err(zbus::fdo::Error::Failed(format!("no {namespace} {key}")));
+            }
+            Value::new(Value::from(self.color_scheme))
+                .try_to_owned()
+                .map_err(|err| zbus::fdo::Error::Failed(err.to_string()))
+        }
+
+        #[zbus(signal)]
+        async fn setting_changed(
+            emitter: &SignalEmitter<'_>,
+            namespace: &str,
+            key: &str,
+            value: Value<'_>,
+        ) -> zbus::Result<()>;
+    }
+
+    #[tokio::test]
+    // `unix_stream` is deprecated for a variant named after async-io in newer zbus versions
+    #[allow(deprecated)]
+    async fn follows_the_portal_color_scheme() {
+        let (server, client) = UnixStream::pair().unwrap();
+        let portal = connection::Builder::unix_stream(server)
+            .server(Guid::generate())
+            .unwrap()
+            .p2p()
+            .serve_at(PATH, Portal { color_scheme: 1 })
+            .unwrap()
+            .build();
+        let conn = connection::Builder::unix_stream(client).p2p().build();
+        let (portal, conn) = tokio::try_join!(portal, conn).unwrap();
+
+        let subscriber = Subscriber::with_connection(conn).await.unwrap();
+        assert_eq!(subscriber.get().color_scheme, ColorScheme::Dark);
+        assert_eq!(subscriber.get().accent_color, None);
+
+        let emitter = SignalEmitter::new(&portal, PATH).unwrap();
+        // other namespaces have keys of the same name
+        Portal::setting_changed(
+            &emitter,
+            "org.gnome.desktop.interface",
+            COLOR_SCHEME,
+            Value::from("prefer-dark"),
+        )
+        .await
+        .unwrap();
+        Portal::setting_changed(&emitter, NAMESPACE, COLOR_SCHEME, Value::from(2u32))
+            .await
+            .unwrap();
+
+        let switched = async {
+            while subscriber.get().color_scheme != ColorScheme::Light {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), switched)
+            .await
+            .expect("the color scheme didn't switch to light");
+    }
+}
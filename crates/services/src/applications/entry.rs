@@ -0,0 +1,651 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, bail};
+
+/// An application from a `.desktop` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Application {
+    /// Desktop file ID, the path relative to the `applications` directory with `/`
+    /// replaced by `-`, e.g. `org.gnome.Nautilus.desktop`.
+    pub id: String,
+    pub path: PathBuf,
+    pub name: String,
+    pub generic_name: Option<String>,
+    pub comment: Option<String>,
+    pub keywords: Vec<String>,
+    pub categories: Vec<String>,
+    pub exec: String,
+    pub icon: Option<String>,
+    pub terminal: bool,
+    pub actions: Vec<Action>,
+}
+
+/// An additional way to start an application, like opening a private browser window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Action {
+    pub id: String,
+    pub name: String,
+    pub exec: String,
+    pub icon: Option<String>,
+}
+
+/// The environment entries are filtered and localized for.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    pub locale: Option<Locale>,
+    /// `$XDG_CURRENT_DESKTOP`, matched against `OnlyShowIn` and `NotShowIn`.
+    pub desktops: Vec<String>,
+    /// Directories `TryExec` programs are looked up in.
+    pub path: Vec<PathBuf>,
+}
+
+impl Environment {
+    pub fn current() -> Self {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Locale::parse(&value));
+        let desktops = std::env::var("XDG_CURRENT_DESKTOP")
+            .map(|desktops| desktops.split(':').map(ToOwned::to_owned).collect())
+            .unwrap_or_default();
+        let path = std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).collect())
+            .unwrap_or_default();
+
+        Self {
+            locale,
+            desktops,
+            path,
+        }
+    }
+
+    fn find_program(&self, program: &str) -> bool {
+        let program = Path::new(program);
+        if program.is_absolute() {
+            return is_executable(program);
+        }
+
+        self.path
+            .iter()
+            .any(|dir| is_executable(&dir.join(program)))
+    }
+}
+
+/// A POSIX locale, `lang_COUNTRY.ENCODING@MODIFIER`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    lang: String,
+    country: Option<String>,
+    modifier: Option<String>,
+}
+
+impl Locale {
+    pub fn parse(locale: &str) -> Option<Self> {
+        let (locale, modifier) = match locale.split_once('@') {
+            Some((locale, modifier)) => (locale, Some(modifier.to_string())),
+            None => (locale, None),
+        };
+        let locale = locale.split('.').next().unwrap_or_default();
+        let (lang, country) = match locale.split_once('_') {
+            Some((lang, country)) => (lang, Some(country.to_string())),
+            None => (locale, None),
+        };
+        if lang.is_empty() || lang == "C" || lang == "POSIX" {
+            return None;
+        }
+
+        Some(Self {
+            lang: lang.to_string(),
+            country,
+            modifier,
+        })
+    }
+
+    /// The locale suffixes of keys to look for, from the most to the least specific.
+    fn candidates(&self) -> Vec<String> {
+        let lang = &self.lang;
+        let mut candidates = Vec::new();
+        if let (Some(country), Some(modifier)) = (&self.country, &self.modifier) {
+            candidates.push(format!("{lang}_{country}@{modifier}"));
+        }
+        if let Some(country) = &self.country {
+            candidates.push(format!("{lang}_{country}"));
+        }
+        if let Some(modifier) = &self.modifier {
+            candidates.push(format!("{lang}@{modifier}"));
+        }
+        candidates.push(lang.clone());
+
+        candidates
+    }
+}
+
+/// What a desktop file describes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entry {
+    Application(Box<Application>),
+    /// A deleted entry, which still shadows entries with the same ID in lower precedence
+    /// directories.
+    Hidden,
+    /// Not an application, or one that shouldn't be shown in this environment.
+    Ignored,
+}
+
+/// Groups of a desktop file, with the keys in each group by name and locale.
+struct Groups<'a> {
+    groups: HashMap<&'a str, HashMap<(&'a str, Option<&'a str>), &'a str>>,
+    locales: Vec<String>,
+}
+
+impl<'a> Groups<'a> {
+    fn parse(source: &'a str, locale: Option<&Locale>) -> Self {
+        let mut groups: HashMap<_, HashMap<_, _>> = HashMap::new();
+        let mut group = None;
+
+        for line in source.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                group = Some(name);
+                continue;
+            }
+            let (Some(group), Some((key, value))) = (group, line.split_once('=')) else {
+                continue;
+            };
+
+            let key = key.trim();
+            let key = match key.split_once('[') {
+                Some((key, locale)) => (key, locale.strip_suffix(']')),
+                None => (key, None),
+            };
+            // the first occurrence of a key wins
+            groups
+                .entry(group)
+                .or_default()
+                .entry(key)
+                .or_insert(value.trim());
+        }
+
+        Self {
+            groups,
+            locales: locale.map(Locale::candidates).unwrap_or_default(),
+        }
+    }
+
+    fn raw(&self, group: &str, key: &str) -> Option<&'a str> {
+        self.groups.get(group)?.get(&(key, None)).copied()
+    }
+
+    fn string(&self, group: &str, key: &str) -> Option<String> {
+        self.raw(group, key).map(unescape)
+    }
+
+    fn localized_raw(&self, group: &str, key: &str) -> Option<&'a str> {
+        let keys = self.groups.get(group)?;
+        self.locales
+            .iter()
+            .find_map(|locale| keys.get(&(key, Some(locale.as_str()))))
+            .or_else(|| keys.get(&(key, None)))
+            .copied()
+    }
+
+    fn localized(&self, group: &str, key: &str) -> Option<String> {
+        self.localized_raw(group, key).map(unescape)
+    }
+
+    fn bool(&self, group: &str, key: &str) -> bool {
+        self.raw(group, key) == Some("true")
+    }
+
+    fn list(&self, group: &str, key: &str) -> Vec<String> {
+        self.raw(group, key).map(split_list).unwrap_or_default()
+    }
+
+    fn localized_list(&self, group: &str, key: &str) -> Vec<String> {
+        self.localized_raw(group, key)
+            .map(split_list)
+            .unwrap_or_default()
+    }
+}
+
+const ENTRY: &str = "Desktop Entry";
+
+impl Entry {
+    pub fn parse(id: String, path: PathBuf, source: &str, env: &Environment) -> Self {
+        let groups = Groups::parse(source, env.locale.as_ref());
+
+        if groups.bool(ENTRY, "Hidden") {
+            return Self::Hidden;
+        }
+        if groups.raw(ENTRY, "Type") != Some("Application") || groups.bool(ENTRY, "NoDisplay") {
+            return Self::Ignored;
+        }
+
+        let only_show_in = groups.list(ENTRY, "OnlyShowIn");
+        let not_show_in = groups.list(ENTRY, "NotShowIn");
+        let shown_in = |desktops: &[String]| env.desktops.iter().any(|d| desktops.contains(d));
+        if (!only_show_in.is_empty() && !shown_in(&only_show_in)) || shown_in(&not_show_in) {
+            return Self::Ignored;
+        }
+
+        let try_exec = groups.string(ENTRY, "TryExec");
+        if try_exec.is_some_and(|program| !env.find_program(&program)) {
+            return Self::Ignored;
+        }
+
+        let (Some(name), Some(exec)) = (
+            groups.localized(ENTRY, "Name"),
+            groups.string(ENTRY, "Exec"),
+        ) else {
+            return Self::Ignored;
+        };
+
+        let actions = groups
+            .list(ENTRY, "Actions")
+            .into_iter()
+            .filter_map(|id| {
+                let group = format!("Desktop Action {id}");
+                Some(Action {
+                    name: groups.localized(&group, "Name")?,
+                    exec: groups.string(&group, "Exec")?,
+                    icon: groups.string(&group, "Icon"),
+                    id,
+                })
+            })
+            .collect();
+
+        Self::Application(Box::new(Application {
+            id,
+            path,
+            name,
+            generic_name: groups.localized(ENTRY, "GenericName"),
+            comment: groups.localized(ENTRY, "Comment"),
+            keywords: groups.localized_list(ENTRY, "Keywords"),
+            categories: groups.list(ENTRY, "Categories"),
+            exec,
+            icon: groups.string(ENTRY, "Icon").filter(|icon| !icon.is_empty()),
+            terminal: groups.bool(ENTRY, "Terminal"),
+            actions,
+        }))
+    }
+}
+
+impl Application {
+    /// Starts the application, or one of its actions, running it in `terminal` if it
+    /// asks for one.
+    pub fn launch(&self, action: Option<&Action>, terminal: &[String]) -> Result<()> {
+        let mut args = self.exec_args(action)?;
+        if self.terminal {
+            args.splice(0..0, terminal.iter().cloned());
+        }
+
+        let Some((program, args)) = args.split_first() else {
+            bail!("{}: empty command", self.id);
+        };
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("failed to run `{program}`"))?;
+
+        // reap the child once it exits, so it doesn't linger as a zombie
+        std::thread::spawn(move || child.wait());
+
+        Ok(())
+    }
+
+    /// Splits `Exec` into arguments and expands its field codes.
+    ///
+    /// Files and URLs are never passed, so the codes for them expand to nothing.
+    pub fn exec_args(&self, action: Option<&Action>) -> Result<Vec<String>> {
+        let exec = action.map_or(&self.exec, |action| &action.exec);
+        let icon = action
+            .and_then(|action| action.icon.as_ref())
+            .or(self.icon.as_ref());
+
+        let mut args = Vec::new();
+        for arg in split_exec(exec).with_context(|| format!("{}: invalid Exec", self.id))? {
+            match arg.as_str() {
+                "%f" | "%F" | "%u" | "%U" | "%d" | "%D" | "%n" | "%N" | "%v" | "%m" => {}
+                "%i" => {
+                    if let Some(icon) = icon {
+                        args.extend(["--icon".to_string(), icon.clone()]);
+                    }
+                }
+                _ => args.push(self.expand_field_codes(&arg)),
+            }
+        }
+
+        Ok(args)
+    }
+
+    fn expand_field_codes(&self, arg: &str) -> String {
+        let mut expanded = String::with_capacity(arg.len());
+        let mut chars = arg.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('%') => expanded.push('%'),
+                Some('c') => expanded.push_str(&self.name),
+                Some('k') => expanded.push_str(&self.path.to_string_lossy()),
+                // deprecated or file codes, which can only stand alone, are dropped
+                _ => {}
+            }
+        }
+
+        expanded
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+/// Handles the escape sequences of string values.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => unescaped.push(' '),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            // kept for the `Exec` parser
+            Some(c) => {
+                unescaped.push('\\');
+                unescaped.push(c);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+/// Splits a raw `;` separated list, `\;` being a literal semicolon.
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(';') => item.push(';'),
+                // other escapes are handled by `unescape`
+                Some(c) => item.extend(['\\', c]),
+                None => item.push('\\'),
+            },
+            ';' => items.push(unescape(&std::mem::take(&mut item))),
+            c => item.push(c),
+        }
+    }
+    items.push(unescape(&item));
+    items.retain(|item| !item.is_empty());
+
+    items
+}
+
+/// Splits `Exec` into arguments, following its quoting rules: arguments containing
+/// reserved characters are enclosed in double quotes, inside which `"`, `` ` ``, `$` and
+/// `\` are escaped with a backslash.
+fn split_exec(exec: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut in_arg = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '`' | '$' | '\\')) => arg.push(c),
+                            Some(c) => bail!("invalid escape `\\{c}` in quoted argument"),
+                            None => bail!("unterminated quoted argument"),
+                        },
+                        Some(c) => arg.push(c),
+                        None => bail!("unterminated quoted argument"),
+                    }
+                }
+            }
+            ' ' | '\t' => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            c => {
+                in_arg = true;
+                arg.push(c);
+            }
+        }
+    }
+    if in_arg {
+        args.push(arg);
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str, env: &Environment) -> Entry {
+        let path = PathBuf::from("/usr/share/applications/app.desktop");
+        Entry::parse("app.desktop".to_string(), path, source, env)
+    }
+
+    fn application(source: &str, env: &Environment) -> Application {
+        match parse(source, env) {
+            Entry::Application(application) => *application,
+            entry => panic!("expected an application, got {entry:?}"),
+        }
+    }
+
+    fn env_with_locale(locale: &str) -> Environment {
+        Environment {
+            locale: Locale::parse(locale),
+            ..Default::default()
+        }
+    }
+
+    const LOCALIZED: &str = "\
+[Desktop Entry]
+Type=Application
+Name=Files
+Name[de]=Dateien
+Name[pt_BR]=Arquivos
+Name[sr@latin]=Datoteke
+Exec=files
+";
+
+    #[test]
+    fn localized_name_falls_back_to_less_specific_locales() {
+        let name = |locale| application(LOCALIZED, &env_with_locale(locale)).name;
+
+        assert_eq!(name("pt_BR.UTF-8"), "Arquivos");
+        assert_eq!(name("de_AT.UTF-8"), "Dateien");
+        assert_eq!(name("sr_RS@latin"), "Datoteke");
+        assert_eq!(name("fr_FR.UTF-8"), "Files");
+        assert_eq!(name("C"), "Files");
+    }
+
+    #[test]
+    fn actions_come_from_their_groups() {
+        let source = "\
+[Desktop Entry]
+Type=Application
+Name=Browser
+Exec=browser %u
+Icon=browser
+Actions=new-window;private;missing;
+
+[Desktop Action new-window]
+Name=New Window
+Exec=browser --new-window
+
+[Desktop Action private]
+Name=New Private Window
+Name[de]=Neues privates Fenster
+Exec=browser --private
+Icon=browser-private
+";
+        let actions = application(source, &env_with_locale("de_DE")).actions;
+
+        assert_eq!(
+            actions,
+            [
+                Action {
+                    id: "new-window".to_string(),
+                    name: "New Window".to_string(),
+                    exec: "browser --new-window".to_string(),
+                    icon: None,
+                },
+                Action {
+                    id: "private".to_string(),
+                    name: "Neues privates Fenster".to_string(),
+                    exec: "browser --private".to_string(),
+                    icon: Some("browser-private".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn try_exec_hides_missing_programs() {
+        let dir = tempfile::tempdir().unwrap();
+        let program = dir.path().join("present");
+        std::fs::write(&program, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(
+            &program,
+            std::os::unix::fs::PermissionsExt::from_mode(0o755),
+        )
+        .unwrap();
+        let env = Environment {
+            path: vec![dir.path().to_path_buf()],
+            ..Default::default()
+        };
+        let source = |try_exec: &str| {
+            format!("[Desktop Entry]\nType=Application\nName=App\nExec=app\nTryExec={try_exec}\n")
+        };
+
+        assert!(matches!(
+            parse(&source("present"), &env),
+            Entry::Application(_)
+        ));
+        assert!(matches!(
+            parse(&source(&program.to_string_lossy()), &env),
+            Entry::Application(_)
+        ));
+        assert_eq!(parse(&source("missing"), &env), Entry::Ignored);
+        assert_eq!(parse(&source("/nonexistent/app"), &env), Entry::Ignored);
+    }
+
+    #[test]
+    fn show_in_matches_current_desktops() {
+        let env = Environment {
+            desktops: vec!["Hyprland".to_string(), "wlroots".to_string()],
+            ..Default::default()
+        };
+        let source =
+            |key: &str| format!("[Desktop Entry]\nType=Application\nName=App\nExec=app\n{key}\n");
+        let shown = |key| matches!(parse(&source(key), &env), Entry::Application(_));
+
+        assert!(shown("OnlyShowIn=GNOME;wlroots;"));
+        assert!(!shown("OnlyShowIn=GNOME;KDE;"));
+        assert!(shown("NotShowIn=GNOME;"));
+        assert!(!shown("NotShowIn=KDE;Hyprland;"));
+
+        // nothing to match against, only `OnlyShowIn` entries are left out
+        let source = source("OnlyShowIn=GNOME;");
+        assert_eq!(parse(&source, &Environment::default()), Entry::Ignored);
+    }
+
+    #[test]
+    fn hidden_and_no_display_entries() {
+        let env = Environment::default();
+
+        let hidden = "[Desktop Entry]\nType=Application\nName=App\nExec=app\nHidden=true\n";
+        assert_eq!(parse(hidden, &env), Entry::Hidden);
+        let no_display = "[Desktop Entry]\nType=Application\nName=App\nExec=app\nNoDisplay=true\n";
+        assert_eq!(parse(no_display, &env), Entry::Ignored);
+        let link = "[Desktop Entry]\nType=Link\nName=Site\nURL=https://example.com\n";
+        assert_eq!(parse(link, &env), Entry::Ignored);
+    }
+
+    #[test]
+    fn exec_expands_field_codes() {
+        let source = "\
+[Desktop Entry]
+Type=Application
+Name=Editor
+Exec=editor %f %F %u %U --icon-arg %i --title=%c --file=%k --percent=100%% %d
+Icon=editor
+";
+        let application = application(source, &Environment::default());
+
+        assert_eq!(
+            application.exec_args(None).unwrap(),
+            [
+                "editor",
+                "--icon-arg",
+                "--icon",
+                "editor",
+                "--title=Editor",
+                "--file=/usr/share/applications/app.desktop",
+                "--percent=100%",
+            ]
+        );
+    }
+
+    #[test]
+    fn exec_unescapes_quoted_arguments() {
+        // a backslash in the file is escaped once for the string value, then once more
+        // inside the quoted argument
+        let source = r#"[Desktop Entry]
+Type=Application
+Name=App
+Exec="/opt/My App/app" --greeting "say \\"hi\\"" "\\$HOME" "back\\\\slash" %U
+"#;
+        let application = application(source, &Environment::default());
+
+        assert_eq!(
+            application.exec_args(None).unwrap(),
+            [
+                "/opt/My App/app",
+                "--greeting",
+                r#"say "hi""#,
+                "$HOME",
+                r"back\slash",
+            ]
+        );
+
+        let unterminated = Application {
+            exec: r#"app "open"#.to_string(),
+            ..application
+        };
+        assert!(unterminated.exec_args(None).is_err());
+    }
+}
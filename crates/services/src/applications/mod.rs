@@ -0,0 +1,244 @@
+mod entry;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, mpsc};
+use std::time::Duration;
+
+use futures_signals::signal::{Mutable, MutableSignalCloned};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use tracing::{debug, info, warn};
+
+pub use entry::{Action, Application, Entry, Environment, Locale};
+
+/// The installed applications, kept up to date as desktop files are added, changed or
+/// removed.
+#[derive(Clone)]
+pub struct Subscriber {
+    applications: Mutable<Vec<Application>>,
+    _watcher: Arc<RecommendedWatcher>,
+}
+
+impl Subscriber {
+    pub async fn new() -> anyhow::Result<Self> {
+        Self::with_dirs(application_dirs(), Environment::current()).await
+    }
+
+    /// Indexes the given `applications` directories, in order of precedence.
+    pub async fn with_dirs(dirs: Vec<PathBuf>, env: Environment) -> anyhow::Result<Self> {
+        let applications = {
+            let dirs = dirs.clone();
+            let env = env.clone();
+            tokio::task::spawn_blocking(move || load(&dirs, &env)).await?
+        };
+        let applications = Mutable::new(applications);
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) if event.kind.is_access() => {}
+                Ok(_) => {
+                    tx.send(()).ok();
+                }
+                Err(err) => warn!("applications watcher error: {err}"),
+            })?;
+        // directories created later aren't picked up until the next start
+        for dir in dirs.iter().filter(|dir| dir.is_dir()) {
+            if let Err(err) = watcher.watch(dir, RecursiveMode::Recursive) {
+                warn!("failed to watch {}: {err}", dir.display());
+            }
+        }
+
+        let applications_for_thread = applications.clone();
+        std::thread::spawn(move || {
+            info!("Applications subscriber start");
+
+            // stops once the watcher, and with it the sender, is dropped
+            while rx.recv().is_ok() {
+                // installing a package touches many files, wait for it to settle
+                while rx.recv_timeout(Duration::from_millis(500)).is_ok() {}
+
+                let loaded = load(&dirs, &env);
+                debug!("reloaded {} applications", loaded.len());
+                applications_for_thread.set(loaded);
+            }
+        });
+
+        Ok(Self {
+            applications,
+            _watcher: Arc::new(watcher),
+        })
+    }
+
+    pub fn get(&self) -> Vec<Application> {
+        self.applications.get_cloned()
+    }
+
+    pub fn subscribe(&self) -> MutableSignalCloned<Vec<Application>> {
+        self.applications.signal_cloned()
+    }
+}
+
+/// Parses the desktop files in `dirs`, the ones found first shadowing the others with the
+/// same desktop file ID. Sorted by name.
+pub fn load(dirs: &[PathBuf], env: &Environment) -> Vec<Application> {
+    let mut seen = HashSet::new();
+    let mut applications = Vec::new();
+
+    for dir in dirs {
+        let mut files = Vec::new();
+        collect_desktop_files(dir, &mut files);
+
+        for path in files {
+            let Some(id) = desktop_file_id(dir, &path) else {
+                continue;
+            };
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            let source = match std::fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(err) => {
+                    debug!("failed to read {}: {err}", path.display());
+                    continue;
+                }
+            };
+            if let Entry::Application(application) = Entry::parse(id, path, &source, env) {
+                applications.push(*application);
+            }
+        }
+    }
+
+    applications.sort_by_key(|application| application.name.to_lowercase());
+    applications
+}
+
+/// `$XDG_DATA_HOME` followed by `$XDG_DATA_DIRS`, in order of precedence.
+pub fn data_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    data_home
+        .into_iter()
+        .chain(data_dirs.split(':').map(PathBuf::from))
+        .collect()
+}
+
+/// The `applications` directory of each data dir.
+pub fn application_dirs() -> Vec<PathBuf> {
+    data_dirs()
+        .into_iter()
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+fn collect_desktop_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut paths: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            collect_desktop_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "desktop") {
+            files.push(path);
+        }
+    }
+}
+
+fn desktop_file_id(dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(dir).ok()?;
+    let parts: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect::<Option<_>>()?;
+
+    Some(parts.join("-"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, source: &str) {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, source).unwrap();
+    }
+
+    fn entry(name: &str) -> String {
+        format!("[Desktop Entry]\nType=Application\nName={name}\nExec=app\n")
+    }
+
+    #[test]
+    fn earlier_dirs_shadow_later_ones() {
+        let data_home = tempfile::tempdir().unwrap();
+        let data_dir = tempfile::tempdir().unwrap();
+        write(data_home.path(), "app.desktop", &entry("Home App"));
+        write(data_dir.path(), "app.desktop", &entry("System App"));
+        write(data_dir.path(), "other.desktop", &entry("Other"));
+
+        let dirs = [
+            data_home.path().to_path_buf(),
+            data_dir.path().to_path_buf(),
+        ];
+        let applications = load(&dirs, &Environment::default());
+        let names: Vec<_> = applications.iter().map(|a| a.name.as_str()).collect();
+
+        assert_eq!(names, ["Home App", "Other"]);
+        assert_eq!(applications[0].path, data_home.path().join("app.desktop"));
+    }
+
+    #[test]
+    fn hidden_entries_shadow_later_ones() {
+        let data_home = tempfile::tempdir().unwrap();
+        let data_dir = tempfile::tempdir().unwrap();
+        write(
+            data_home.path(),
+            "app.desktop",
+            "[Desktop Entry]\nType=Application\nName=App\nExec=app\nHidden=true\n",
+        );
+        write(data_dir.path(), "app.desktop", &entry("App"));
+
+        let dirs = [
+            data_home.path().to_path_buf(),
+            data_dir.path().to_path_buf(),
+        ];
+        assert!(load(&dirs, &Environment::default()).is_empty());
+    }
+
+    #[test]
+    fn ids_of_subdirectories_are_prefixed() {
+        let data_home = tempfile::tempdir().unwrap();
+        let data_dir = tempfile::tempdir().unwrap();
+        write(data_home.path(), "kde-app.desktop", &entry("Home App"));
+        write(data_dir.path(), "kde/app.desktop", &entry("System App"));
+        write(data_dir.path(), "gnome/app.desktop", &entry("GNOME App"));
+
+        let dirs = [
+            data_home.path().to_path_buf(),
+            data_dir.path().to_path_buf(),
+        ];
+        let applications = load(&dirs, &Environment::default());
+        let ids: Vec<_> = applications
+            .iter()
+            .map(|a| (a.id.as_str(), a.name.as_str()))
+            .collect();
+
+        assert_eq!(
+            ids,
+            [
+                ("gnome-app.desktop", "GNOME App"),
+                ("kde-app.desktop", "Home App"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{lock, send};
+
+use super::{ArcMutVec, Client, Event};
+use anyhow::Result;
+use libpulse_binding::callbacks::ListResult;
+use libpulse_binding::context::Context;
+use libpulse_binding::context::introspect::{CardInfo, CardProfileInfo};
+use libpulse_binding::context::subscribe::Operation;
+use libpulse_binding::proplist::properties;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::{debug, error, trace};
+
+/// A sound card, whose profile decides which sinks and sources it offers.
+#[derive(Debug, Clone)]
+pub struct Card {
+    pub index: u32,
+    pub name: String,
+    pub description: String,
+    pub profiles: Vec<Profile>,
+    /// The name of the active profile.
+    pub active_profile: Option<String>,
+}
+
+/// A way to use a card, as `output:hdmi-stereo` or `off`.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub description: String,
+    /// Whether something is plugged in to use the profile with.
+    pub available: bool,
+}
+
+impl From<&CardProfileInfo<'_>> for Profile {
+    fn from(value: &CardProfileInfo) -> Self {
+        let name = value
+            .name
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        Self {
+            description: value
+                .description
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| name.clone()),
+            name,
+            available: value.available,
+        }
+    }
+}
+
+impl From<&CardInfo<'_>> for Card {
+    fn from(value: &CardInfo) -> Self {
+        let name = value
+            .name
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        Self {
+            index: value.index,
+            description: value
+                .proplist
+                .get_str(properties::DEVICE_DESCRIPTION)
+                .unwrap_or_else(|| name.clone()),
+            name,
+            profiles: value.profiles.iter().map(Profile::from).collect(),
+            active_profile: value
+                .active_profile
+                .as_ref()
+                .and_then(|profile| profile.name.as_ref())
+                .map(ToString::to_string),
+        }
+    }
+}
+
+impl Client {
+    pub fn cards(&self) -> Arc<Mutex<Vec<Card>>> {
+        self.data.cards.clone()
+    }
+
+    /// Switches the card to `profile`, which replaces its sinks and sources.
+    pub async fn set_card_profile(&self, index: u32, profile: &str) -> Result<()> {
+        debug!("set card profile: {index} {profile}");
+        let profile = profile.to_string();
+        self.request(
+            format!("set the profile of card {index} to {profile}"),
+            move |context, done| {
+                context.introspect().set_card_profile_by_index(
+                    index,
+                    &profile,
+                    Some(done.callback()),
+                );
+            },
+        )
+        .await
+    }
+}
+
+pub fn on_event(
+    context: &Arc<Mutex<Context>>,
+    cards: &ArcMutVec<Card>,
+    tx: &broadcast::Sender<Event>,
+    op: Operation,
+    i: u32,
+) {
+    let introspect = lock!(context).introspect();
+
+    match op {
+        Operation::New => {
+            introspect.get_card_info_by_index(i, {
+                let cards = cards.clone();
+                let tx = tx.clone();
+
+                move |info| add(info, &cards, &tx)
+            });
+        }
+        Operation::Changed => {
+            introspect.get_card_info_by_index(i, {
+                let cards = cards.clone();
+                let tx = tx.clone();
+
+                move |info| update(info, &cards, &tx)
+            });
+        }
+        Operation::Removed => {
+            remove(i, cards, tx);
+        }
+    }
+}
+
+pub fn add(info: ListResult<&CardInfo>, cards: &ArcMutVec<Card>, tx: &broadcast::Sender<Event>) {
+    let ListResult::Item(info) = info else {
+        return;
+    };
+
+    trace!("adding {info:?}");
+
+    lock!(cards).push(info.into());
+    send!(tx, Event::AddCard(info.into()));
+}
+
+fn update(info: ListResult<&CardInfo>, cards: &ArcMutVec<Card>, tx: &broadcast::Sender<Event>) {
+    let ListResult::Item(info) = info else {
+        return;
+    };
+
+    trace!("updating {info:?}");
+
+    {
+        let mut cards = lock!(cards);
+        let Some(pos) = cards.iter().position(|card| card.index == info.index) else {
+            error!("received update to untracked card");
+            return;
+        };
+
+        cards[pos] = info.into();
+    }
+
+    send!(tx, Event::UpdateCard(info.into()));
+}
+
+fn remove(index: u32, cards: &ArcMutVec<Card>, tx: &broadcast::Sender<Event>) {
+    trace!("removing {index}");
+
+    let mut cards = lock!(cards);
+
+    if let Some(pos) = cards.iter().position(|card| card.index == index) {
+        let info = cards.remove(pos);
+        send!(tx, Event::RemoveCard(info.index));
+    }
+}
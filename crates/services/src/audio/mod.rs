@@ -1,24 +1,36 @@
+use anyhow::{Result, anyhow};
+use card::Card;
 use libpulse_binding::callbacks::ListResult;
 use libpulse_binding::context::Context;
-use libpulse_binding::context::introspect::{Introspector, ServerInfo};
+use libpulse_binding::context::introspect::ServerInfo;
 use libpulse_binding::context::subscribe::{Facility, InterestMaskSet, Operation};
 use libpulse_binding::context::{FlagSet, State};
-use libpulse_binding::mainloop::standard::IterateResult;
-use libpulse_binding::mainloop::standard::Mainloop;
+use libpulse_binding::mainloop::threaded::Mainloop;
 use libpulse_binding::proplist::Proplist;
 use libpulse_binding::volume::{ChannelVolumes, Volume};
 use sink::Sink;
 use sink_input::SinkInput;
-use std::fmt::Formatter;
-use std::sync::{Arc, Mutex};
-use tokio::sync::broadcast;
+use source::Source;
+use source_output::SourceOutput;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak, mpsc};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, oneshot};
 use tokio::task::spawn_blocking;
 use tracing::{debug, error, info, trace, warn};
 
+use crate::APP_ID;
+
+pub mod card;
 pub mod sink;
 pub mod sink_input;
+pub mod source;
+pub mod source_output;
 
-const APP_ID: &str = "dev.gallon.mgs";
+/// How long to wait before reconnecting to the server the first time, doubled for every
+/// failed attempt.
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
 
 #[macro_export]
 macro_rules! lock {
@@ -36,9 +48,10 @@ macro_rules! lock {
 
 #[macro_export]
 macro_rules! send {
-    ($tx:expr, $msg:expr) => {
-        $tx.send($msg).expect("Failed to send message to channel")
-    };
+    ($tx:expr, $msg:expr) => {{
+        // nobody listening is fine
+        $tx.send($msg).ok();
+    }};
 }
 
 #[macro_export]
@@ -50,6 +63,11 @@ macro_rules! arc_mut {
 
 #[derive(Debug, Clone)]
 pub enum Event {
+    /// The client connected to the server, what it has is sent as `Add*` events.
+    Connected,
+    /// The client lost the server, everything it had is gone.
+    Disconnected,
+
     AddSink(Sink),
     UpdateSink(Sink),
     RemoveSink(String),
@@ -57,70 +75,130 @@ pub enum Event {
     AddInput(SinkInput),
     UpdateInput(SinkInput),
     RemoveInput(u32),
+
+    AddSource(Source),
+    UpdateSource(Source),
+    RemoveSource(String),
+
+    AddOutput(SourceOutput),
+    UpdateOutput(SourceOutput),
+    RemoveOutput(u32),
+
+    AddCard(Card),
+    UpdateCard(Card),
+    RemoveCard(u32),
 }
 
-pub enum ConnectionState {
-    Disconnected,
-    Connected {
-        context: Arc<Mutex<Context>>,
-        introspector: Introspector,
-    },
+/// A request to the server, made on the client thread with the mainloop locked.
+type Command = Box<dyn FnOnce(&mut Context, Done) + Send>;
+
+/// What the client thread is told, the connections numbered as they're made.
+enum Message {
+    Request(Command, Done),
+    Connected(u64),
+    Disconnected(u64),
+}
+
+/// Where the outcome of a request goes once the server is done with it.
+struct Done {
+    /// What was asked, as in "failed to ...".
+    what: String,
+    tx: oneshot::Sender<Result<()>>,
 }
 
-impl std::fmt::Debug for ConnectionState {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Self::Disconnected => "Disconnected",
-                Self::Connected { .. } => "Connected",
+impl Done {
+    fn finish(self, success: bool) {
+        let result = if success {
+            Ok(())
+        } else {
+            Err(anyhow!("failed to {}", self.what))
+        };
+        self.tx.send(result).ok();
+    }
+
+    fn fail(self, reason: &str) {
+        let result = Err(anyhow!("failed to {}: {reason}", self.what));
+        self.tx.send(result).ok();
+    }
+
+    /// Finishes with the success of an operation.
+    fn callback(self) -> Box<dyn FnMut(bool)> {
+        let mut done = Some(self);
+        Box::new(move |success| {
+            if let Some(done) = done.take() {
+                done.finish(success);
             }
-        )
+        })
     }
 }
 
 type ArcMutVec<T> = Arc<Mutex<Vec<T>>>;
 
 #[derive(Debug, Default, Clone)]
-struct SinkData {
+struct AudioData {
     sinks: ArcMutVec<Sink>,
     sink_inputs: ArcMutVec<SinkInput>,
+    sources: ArcMutVec<Source>,
+    source_outputs: ArcMutVec<SourceOutput>,
+    cards: ArcMutVec<Card>,
 
     default_sink_name: Arc<Mutex<Option<String>>>,
+    default_source_name: Arc<Mutex<Option<String>>>,
+
+    connected: Arc<AtomicBool>,
+}
+
+impl AudioData {
+    /// Forgets everything the server told, once it's gone.
+    fn clear(&self) {
+        lock!(self.sinks).clear();
+        lock!(self.sink_inputs).clear();
+        lock!(self.sources).clear();
+        lock!(self.source_outputs).clear();
+        lock!(self.cards).clear();
+        *lock!(self.default_sink_name) = None;
+        *lock!(self.default_source_name) = None;
+    }
 }
 
 #[derive(Debug)]
 pub struct Client {
-    connection: Arc<Mutex<ConnectionState>>,
-    sink_data: SinkData,
+    /// The only sender, dropping the client closes the channel and stops its thread.
+    messages: Arc<mpsc::Sender<Message>>,
+    data: AudioData,
 
     tx: broadcast::Sender<Event>,
     _rx: broadcast::Receiver<Event>,
 }
 
 impl Client {
-    pub fn new() -> Self {
-        let (tx, rx) = broadcast::channel(32);
+    fn new() -> (Self, mpsc::Receiver<Message>) {
+        let (tx, rx) = broadcast::channel(64);
+        let (messages, messages_rx) = mpsc::channel();
 
-        Self {
-            connection: arc_mut!(ConnectionState::Disconnected),
-            sink_data: SinkData::default(),
+        let client = Self {
+            messages: Arc::new(messages),
+            data: AudioData::default(),
             tx,
             _rx: rx,
-        }
+        };
+        (client, messages_rx)
     }
 }
 
-impl Default for Client {
-    fn default() -> Self {
-        Self::new()
-    }
+/// The client thread, owning the connection to the server.
+struct Runner {
+    data: AudioData,
+    tx: broadcast::Sender<Event>,
+    /// Tells the thread about the connection from the mainloop callbacks, without keeping
+    /// the channel open once the client is gone.
+    messages: Weak<mpsc::Sender<Message>>,
 }
 
-impl Client {
-    /// Starts the client.
-    fn run(&self) {
+impl Runner {
+    /// Connects to the server, then runs the requests from `messages` until the client is
+    /// dropped, reconnecting whenever the server goes away.
+    fn run(&self, messages: mpsc::Receiver<Message>) {
         let Some(mut proplist) = Proplist::new() else {
             error!("Failed to create PA proplist");
             return;
@@ -135,46 +213,139 @@ impl Client {
             return;
         };
 
-        let Some(context) = Context::new_with_proplist(&mainloop, "Ironbar Context", &proplist)
+        // the mainloop dispatches on a thread of its own, so requests lock it not to race
+        // its callbacks, and nothing here waits on the server
+        if let Err(err) = mainloop.start() {
+            error!("Failed to start PA mainloop: {err:?}");
+            return;
+        }
+
+        let mut delay = MIN_RETRY_DELAY;
+        for id in 0.. {
+            mainloop.lock();
+            let context = self.connect(&mainloop, &proplist, id);
+            mainloop.unlock();
+
+            if let Some(context) = context {
+                let running = self.serve(&mut mainloop, &context, &messages, id, &mut delay);
+
+                // the last reference unrefs the context, which the mainloop may be using
+                mainloop.lock();
+                disconnect(&context);
+                drop(context);
+                mainloop.unlock();
+
+                if !running {
+                    break;
+                }
+            }
+
+            info!("reconnecting to audio server in {delay:?}");
+            if !wait(&messages, delay) {
+                break;
+            }
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+        }
+
+        mainloop.stop();
+    }
+
+    /// Starts connecting to the server, as connection `id`.
+    fn connect(
+        &self,
+        mainloop: &Mainloop,
+        proplist: &Proplist,
+        id: u64,
+    ) -> Option<Arc<Mutex<Context>>> {
+        let Some(context) = Context::new_with_proplist(mainloop, "Ironbar Context", proplist)
         else {
             error!("Failed to create PA context");
-            return;
+            return None;
         };
 
         let context = arc_mut!(context);
 
         let state_callback = Box::new({
             let context = context.clone();
-            let data = self.sink_data.clone();
+            let data = self.data.clone();
             let tx = self.tx.clone();
+            let messages = self.messages.clone();
 
-            move || on_state_change(&context, &data, &tx)
+            move || on_state_change(&context, id, &data, &tx, &messages)
         });
 
         lock!(context).set_state_callback(Some(state_callback));
 
-        if let Err(err) = lock!(context).connect(None, FlagSet::NOAUTOSPAWN, None) {
-            error!("{err:?}");
+        let connected = lock!(context).connect(None, FlagSet::NOAUTOSPAWN, None);
+        if let Err(err) = connected {
+            error!("Failed to connect to audio server: {err:?}");
+            disconnect(&context);
+            return None;
         }
 
-        let introspector = lock!(context).introspect();
-
-        {
-            let mut inner = lock!(self.connection);
-            *inner = ConnectionState::Connected {
-                context,
-                introspector,
-            };
-        }
+        Some(context)
+    }
 
+    /// Runs the requests on connection `id` until it's lost, returns whether the client is
+    /// still there.
+    fn serve(
+        &self,
+        mainloop: &mut Mainloop,
+        context: &Arc<Mutex<Context>>,
+        messages: &mpsc::Receiver<Message>,
+        id: u64,
+        delay: &mut Duration,
+    ) -> bool {
         loop {
-            match mainloop.iterate(true) {
-                IterateResult::Success(_) => {}
-                IterateResult::Err(err) => error!("{err:?}"),
-                IterateResult::Quit(_) => break,
+            match messages.recv() {
+                Ok(Message::Request(command, done)) => {
+                    mainloop.lock();
+                    {
+                        let mut context = lock!(context);
+                        if context.get_state() == State::Ready {
+                            command(&mut *context, done);
+                        } else {
+                            done.fail("not connected to the audio server");
+                        }
+                    }
+                    mainloop.unlock();
+                }
+                Ok(Message::Connected(connection)) if connection == id => {
+                    *delay = MIN_RETRY_DELAY;
+                }
+                Ok(Message::Disconnected(connection)) if connection == id => return true,
+                // left over from an earlier connection
+                Ok(_) => {}
+                Err(_) => return false,
             }
         }
     }
+}
+
+impl Client {
+    /// Whether the client is connected to the server.
+    pub fn is_connected(&self) -> bool {
+        self.data.connected.load(Ordering::Relaxed)
+    }
+
+    /// Runs `command` on the client thread, and waits for the server to be done with it.
+    async fn request(
+        &self,
+        what: String,
+        command: impl FnOnce(&mut Context, Done) + Send + 'static,
+    ) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        let done = Done {
+            what: what.clone(),
+            tx,
+        };
+        self.messages
+            .send(Message::Request(Box::new(command), done))
+            .map_err(|_| anyhow!("failed to {what}: the audio client isn't running"))?;
+        // the callback is dropped unanswered when the connection goes away
+        rx.await
+            .map_err(|_| anyhow!("failed to {what}: the audio server went away"))?
+    }
 
     /// Gets an event receiver.
     pub fn subscribe(&self) -> broadcast::Receiver<Event> {
@@ -184,19 +355,54 @@ impl Client {
 
 /// Creates a new Pulse volume client.
 pub fn create_client() -> Arc<Client> {
-    let client = Arc::new(Client::new());
+    let (client, messages) = Client::new();
+    let runner = Runner {
+        data: client.data.clone(),
+        tx: client.tx.clone(),
+        messages: Arc::downgrade(&client.messages),
+    };
+    spawn_blocking(move || runner.run(messages));
 
-    {
-        let client = client.clone();
-        spawn_blocking(move || {
-            client.run();
-        });
+    Arc::new(client)
+}
+
+/// Drops the connection, and the callbacks keeping it alive.
+fn disconnect(context: &Arc<Mutex<Context>>) {
+    let mut context = lock!(context);
+    context.set_state_callback(None);
+    context.set_subscribe_callback(None);
+    context.disconnect();
+}
+
+/// Waits `delay` before reconnecting, failing the requests made meanwhile. Returns whether
+/// the client is still there.
+fn wait(messages: &mpsc::Receiver<Message>, delay: Duration) -> bool {
+    let deadline = Instant::now() + delay;
+    loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match messages.recv_timeout(timeout) {
+            Ok(Message::Request(_, done)) => done.fail("not connected to the audio server"),
+            Ok(_) => {}
+            Err(mpsc::RecvTimeoutError::Timeout) => return true,
+            Err(mpsc::RecvTimeoutError::Disconnected) => return false,
+        }
     }
+}
 
-    client
+/// Sends to the client thread, unless the client is gone.
+fn notify(messages: &Weak<mpsc::Sender<Message>>, message: Message) {
+    if let Some(messages) = messages.upgrade() {
+        messages.send(message).ok();
+    }
 }
 
-fn on_state_change(context: &Arc<Mutex<Context>>, data: &SinkData, tx: &broadcast::Sender<Event>) {
+fn on_state_change(
+    context: &Arc<Mutex<Context>>,
+    id: u64,
+    data: &AudioData,
+    tx: &broadcast::Sender<Event>,
+    messages: &Weak<mpsc::Sender<Message>>,
+) {
     let Ok(state) = context.try_lock().map(|lock| lock.get_state()) else {
         return;
     };
@@ -205,6 +411,12 @@ fn on_state_change(context: &Arc<Mutex<Context>>, data: &SinkData, tx: &broadcas
         State::Ready => {
             info!("connected to server");
 
+            // anything left from a lost connection is stale
+            data.clear();
+            data.connected.store(true, Ordering::Relaxed);
+            send!(tx, Event::Connected);
+            notify(messages, Message::Connected(id));
+
             let introspect = lock!(context).introspect();
             let introspect2 = lock!(context).introspect();
 
@@ -236,6 +448,42 @@ fn on_state_change(context: &Arc<Mutex<Context>>, data: &SinkData, tx: &broadcas
                 move |info| sink_input::add(info, &inputs, &tx)
             });
 
+            let introspect3 = lock!(context).introspect();
+            introspect.get_source_info_list({
+                let sources = data.sources.clone();
+                let default_source = data.default_source_name.clone();
+
+                let tx = tx.clone();
+
+                move |info| match info {
+                    ListResult::Item(_) => source::add(info, &sources, &tx),
+                    ListResult::End => {
+                        introspect3.get_server_info({
+                            let sources = sources.clone();
+                            let default_source = default_source.clone();
+                            let tx = tx.clone();
+
+                            move |info| set_default_source(info, &sources, &default_source, &tx)
+                        });
+                    }
+                    ListResult::Error => error!("Error while receiving sources"),
+                }
+            });
+
+            introspect.get_source_output_info_list({
+                let outputs = data.source_outputs.clone();
+                let tx = tx.clone();
+
+                move |info| source_output::add(info, &outputs, &tx)
+            });
+
+            introspect.get_card_info_list({
+                let cards = data.cards.clone();
+                let tx = tx.clone();
+
+                move |info| card::add(info, &cards, &tx)
+            });
+
             let subscribe_callback = Box::new({
                 let context = context.clone();
                 let data = data.clone();
@@ -246,19 +494,32 @@ fn on_state_change(context: &Arc<Mutex<Context>>, data: &SinkData, tx: &broadcas
 
             lock!(context).set_subscribe_callback(Some(subscribe_callback));
             lock!(context).subscribe(
-                InterestMaskSet::SERVER | InterestMaskSet::SINK_INPUT | InterestMaskSet::SINK,
+                InterestMaskSet::SERVER
+                    | InterestMaskSet::SINK_INPUT
+                    | InterestMaskSet::SINK
+                    | InterestMaskSet::SOURCE_OUTPUT
+                    | InterestMaskSet::SOURCE
+                    | InterestMaskSet::CARD,
                 |_| (),
             );
         }
-        State::Failed => error!("Failed to connect to audio server"),
-        State::Terminated => error!("Connection to audio server terminated"),
+        State::Failed | State::Terminated => {
+            if data.connected.swap(false, Ordering::Relaxed) {
+                error!("Connection to audio server terminated");
+                data.clear();
+                send!(tx, Event::Disconnected);
+            } else {
+                error!("Failed to connect to audio server");
+            }
+            notify(messages, Message::Disconnected(id));
+        }
         _ => {}
     }
 }
 
 fn on_event(
     context: &Arc<Mutex<Context>>,
-    data: &SinkData,
+    data: &AudioData,
     tx: &broadcast::Sender<Event>,
     facility: Option<Facility>,
     op: Option<Operation>,
@@ -271,25 +532,27 @@ fn on_event(
     trace!("server event: {facility:?}, op: {op:?}, i: {i}");
 
     match facility {
-        Facility::Server => on_server_event(context, &data.sinks, &data.default_sink_name, tx),
+        Facility::Server => on_server_event(context, data, tx),
         Facility::Sink => sink::on_event(context, &data.sinks, &data.default_sink_name, tx, op, i),
         Facility::SinkInput => sink_input::on_event(context, &data.sink_inputs, tx, op, i),
+        Facility::Source => {
+            source::on_event(context, &data.sources, &data.default_source_name, tx, op, i)
+        }
+        Facility::SourceOutput => source_output::on_event(context, &data.source_outputs, tx, op, i),
+        Facility::Card => card::on_event(context, &data.cards, tx, op, i),
         _ => error!("Received unhandled facility: {facility:?}"),
     }
 }
 
-fn on_server_event(
-    context: &Arc<Mutex<Context>>,
-    sinks: &ArcMutVec<Sink>,
-    default_sink: &Arc<Mutex<Option<String>>>,
-    tx: &broadcast::Sender<Event>,
-) {
+fn on_server_event(context: &Arc<Mutex<Context>>, data: &AudioData, tx: &broadcast::Sender<Event>) {
     lock!(context).introspect().get_server_info({
-        let sinks = sinks.clone();
-        let default_sink = default_sink.clone();
+        let data = data.clone();
         let tx = tx.clone();
 
-        move |info| set_default_sink(info, &sinks, &default_sink, &tx)
+        move |info| {
+            set_default_sink(info, &data.sinks, &data.default_sink_name, &tx);
+            set_default_source(info, &data.sources, &data.default_source_name, &tx);
+        }
     });
 }
 
@@ -319,6 +582,31 @@ fn set_default_sink(
     *lock!(default_sink) = default_sink_name;
 }
 
+fn set_default_source(
+    info: &ServerInfo,
+    sources: &ArcMutVec<Source>,
+    default_source: &Arc<Mutex<Option<String>>>,
+    tx: &broadcast::Sender<Event>,
+) {
+    let default_source_name = info.default_source_name.as_ref().map(ToString::to_string);
+
+    if default_source_name != *lock!(default_source) {
+        if let Some(ref default_source_name) = default_source_name {
+            // the default source may be a monitor, which isn't tracked
+            if let Some(source) = lock!(sources)
+                .iter_mut()
+                .find(|s| s.name.as_str() == default_source_name.as_str())
+            {
+                source.active = true;
+                debug!("Set source active: {}", source.name);
+                send!(tx, Event::UpdateSource(source.clone()));
+            }
+        }
+    }
+
+    *lock!(default_source) = default_source_name;
+}
+
 /// Converts a Pulse `ChannelVolumes` struct into a single percentage value,
 /// representing the average value across all channels.
 fn volume_to_percent(volume: ChannelVolumes) -> f32 {
@@ -328,6 +616,14 @@ fn volume_to_percent(volume: ChannelVolumes) -> f32 {
     ((avg - Volume::MUTED.0) as f32 / base_delta).round()
 }
 
+/// Sets every channel of `volume` to `percent`.
+fn set_percent(volume: &mut ChannelVolumes, percent: f32) {
+    let new_volume = percent_to_volume(percent);
+    for v in volume.get_mut() {
+        v.0 = new_volume;
+    }
+}
+
 /// Converts a percentage volume into a Pulse volume value,
 /// which can be used for setting channel volumes.
 pub fn percent_to_volume(target_percent: f32) -> u32 {
@@ -1,14 +1,15 @@
 use crate::{lock, send};
 
-use super::{ArcMutVec, Client, ConnectionState, Event, percent_to_volume, volume_to_percent};
+use super::{ArcMutVec, Client, Event, set_percent, volume_to_percent};
+use anyhow::Result;
 use libpulse_binding::callbacks::ListResult;
 use libpulse_binding::context::Context;
-use libpulse_binding::context::introspect::SinkInfo;
+use libpulse_binding::context::introspect::{SinkInfo, SinkPortInfo};
 use libpulse_binding::context::subscribe::Operation;
-use libpulse_binding::def::SinkState;
-use std::sync::{Arc, Mutex, mpsc};
+use libpulse_binding::def::{PortAvailable, SinkState};
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
-use tracing::{debug, error, trace};
+use tracing::{debug, error, trace, warn};
 
 #[derive(Debug, Clone)]
 pub struct Sink {
@@ -18,6 +19,39 @@ pub struct Sink {
     pub volume: f32,
     pub muted: bool,
     pub active: bool,
+    /// The index of the card the sink belongs to.
+    pub card: Option<u32>,
+    pub ports: Vec<Port>,
+    /// The name of the active port.
+    pub active_port: Option<String>,
+}
+
+/// Where a sink plays, as the speakers or the headphones of a card.
+#[derive(Debug, Clone)]
+pub struct Port {
+    pub name: String,
+    pub description: String,
+    /// Whether something is plugged in, when the card can tell.
+    pub available: bool,
+}
+
+impl From<&SinkPortInfo<'_>> for Port {
+    fn from(value: &SinkPortInfo) -> Self {
+        let name = value
+            .name
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        Self {
+            description: value
+                .description
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| name.clone()),
+            name,
+            available: value.available != PortAvailable::No,
+        }
+    }
 }
 
 impl From<&SinkInfo<'_>> for Sink {
@@ -37,49 +71,116 @@ impl From<&SinkInfo<'_>> for Sink {
             muted: value.mute,
             volume: volume_to_percent(value.volume),
             active: value.state == SinkState::Running,
+            card: value.card,
+            ports: value.ports.iter().map(Port::from).collect(),
+            active_port: value
+                .active_port
+                .as_ref()
+                .and_then(|port| port.name.as_ref())
+                .map(ToString::to_string),
         }
     }
 }
 
 impl Client {
     pub fn sinks(&self) -> Arc<Mutex<Vec<Sink>>> {
-        self.sink_data.sinks.clone()
+        self.data.sinks.clone()
     }
 
-    pub fn set_default_sink(&self, name: &str) {
-        if let ConnectionState::Connected { context, .. } = &*lock!(self.connection) {
-            lock!(context).set_default_sink(name, |_| {});
-        }
+    /// The sink new streams play on.
+    pub fn default_sink(&self) -> Option<Sink> {
+        let default_sink_name = lock!(self.data.default_sink_name).clone();
+        let sinks = lock!(self.data.sinks);
+        sinks
+            .iter()
+            .find(|sink| Some(&sink.name) == default_sink_name.as_ref())
+            .or_else(|| sinks.iter().find(|sink| sink.active))
+            .cloned()
     }
 
-    pub fn set_sink_volume(&self, name: &str, volume_percent: f32) {
-        debug!("set volume: {name} {volume_percent}");
-        if let ConnectionState::Connected { introspector, .. } = &mut *lock!(self.connection) {
-            let (tx, rx) = mpsc::channel();
-
-            introspector.get_sink_info_by_name(name, move |info| {
-                let ListResult::Item(info) = info else {
-                    return;
-                };
-                send!(tx, info.volume);
-            });
+    pub async fn set_default_sink(&self, name: &str) -> Result<()> {
+        let name = name.to_string();
+        self.request(
+            format!("set the default sink to {name}"),
+            move |context, done| {
+                context.set_default_sink(&name, done.callback());
+            },
+        )
+        .await
+    }
 
-            let new_volume = percent_to_volume(volume_percent);
+    /// Makes `sink` the default one, moving the playing streams to it if `move_inputs`.
+    ///
+    /// Streams that can't be moved, like the ones asking not to be, stay where they are.
+    pub async fn switch_sink(&self, sink: &Sink, move_inputs: bool) -> Result<()> {
+        self.set_default_sink(&sink.name).await?;
+        if !move_inputs {
+            return Ok(());
+        }
 
-            let mut volume = rx.recv().expect("to receive info");
-            for v in volume.get_mut() {
-                v.0 = new_volume;
+        let inputs: Vec<u32> = lock!(self.data.sink_inputs)
+            .iter()
+            .filter(|input| input.sink != sink.index)
+            .map(|input| input.index)
+            .collect();
+        for index in inputs {
+            if let Err(err) = self.move_sink_input(index, sink.index).await {
+                warn!("{err:#}");
             }
-
-            introspector.set_sink_volume_by_name(name, &volume, None);
         }
+        Ok(())
     }
 
-    pub fn set_sink_muted(&self, name: &str, muted: bool) {
+    pub async fn set_sink_port(&self, name: &str, port: &str) -> Result<()> {
+        debug!("set port: {name} {port}");
+        let (name, port) = (name.to_string(), port.to_string());
+        self.request(
+            format!("set the port of {name} to {port}"),
+            move |context, done| {
+                context
+                    .introspect()
+                    .set_sink_port_by_name(&name, &port, Some(done.callback()));
+            },
+        )
+        .await
+    }
+
+    pub async fn set_sink_volume(&self, name: &str, volume_percent: f32) -> Result<()> {
+        debug!("set volume: {name} {volume_percent}");
+        let name = name.to_string();
+        self.request(format!("set the volume of {name}"), move |context, done| {
+            // the volume is set per channel, so the sink is asked for them first
+            let mut introspector = context.introspect();
+            let mut done = Some(done);
+            context
+                .introspect()
+                .get_sink_info_by_name(&name.clone(), move |info| {
+                    let Some(done) = done.take() else {
+                        return;
+                    };
+                    let ListResult::Item(info) = info else {
+                        done.fail("no such sink");
+                        return;
+                    };
+
+                    let mut volume = info.volume;
+                    set_percent(&mut volume, volume_percent);
+                    introspector.set_sink_volume_by_name(&name, &volume, Some(done.callback()));
+                });
+        })
+        .await
+    }
+
+    pub async fn set_sink_muted(&self, name: &str, muted: bool) -> Result<()> {
         debug!("set muted: {name} {muted}");
-        if let ConnectionState::Connected { introspector, .. } = &mut *lock!(self.connection) {
-            introspector.set_sink_mute_by_name(name, muted, None);
-        }
+        let name = name.to_string();
+        let what = if muted { "mute" } else { "unmute" };
+        self.request(format!("{what} {name}"), move |context, done| {
+            context
+                .introspect()
+                .set_sink_mute_by_name(&name, muted, Some(done.callback()));
+        })
+        .await
     }
 }
 
@@ -1,20 +1,28 @@
 use crate::{lock, send};
 
-use super::{ArcMutVec, Client, ConnectionState, Event, percent_to_volume, volume_to_percent};
+use super::{ArcMutVec, Client, Event, set_percent, volume_to_percent};
+use anyhow::Result;
 use libpulse_binding::callbacks::ListResult;
 use libpulse_binding::context::Context;
 use libpulse_binding::context::introspect::SinkInputInfo;
 use libpulse_binding::context::subscribe::Operation;
-use std::sync::{Arc, Mutex, mpsc};
+use libpulse_binding::proplist::properties;
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 use tracing::{debug, error, trace};
 
 #[derive(Debug, Clone)]
 pub struct SinkInput {
     pub index: u32,
+    /// Index of the sink it plays on.
+    pub sink: u32,
     pub name: String,
     pub volume: f32,
     pub muted: bool,
+    /// The `application.name` of the playing application.
+    pub application_name: Option<String>,
+    /// The `application.icon_name` of the playing application.
+    pub icon_name: Option<String>,
 
     pub can_set_volume: bool,
 }
@@ -23,6 +31,7 @@ impl From<&SinkInputInfo<'_>> for SinkInput {
     fn from(value: &SinkInputInfo) -> Self {
         Self {
             index: value.index,
+            sink: value.sink,
             name: value
                 .name
                 .as_ref()
@@ -30,6 +39,8 @@ impl From<&SinkInputInfo<'_>> for SinkInput {
                 .unwrap_or_default(),
             muted: value.mute,
             volume: volume_to_percent(value.volume),
+            application_name: value.proplist.get_str(properties::APPLICATION_NAME),
+            icon_name: value.proplist.get_str(properties::APPLICATION_ICON_NAME),
             can_set_volume: value.has_volume && value.volume_writable,
         }
     }
@@ -37,35 +48,63 @@ impl From<&SinkInputInfo<'_>> for SinkInput {
 
 impl Client {
     pub fn sink_inputs(&self) -> Arc<Mutex<Vec<SinkInput>>> {
-        self.sink_data.sink_inputs.clone()
+        self.data.sink_inputs.clone()
     }
 
-    pub fn set_input_volume(&self, index: u32, volume_percent: f32) {
-        if let ConnectionState::Connected { introspector, .. } = &mut *lock!(self.connection) {
-            let (tx, rx) = mpsc::channel();
-
-            introspector.get_sink_input_info(index, move |info| {
-                let ListResult::Item(info) = info else {
-                    return;
-                };
-                send!(tx, info.volume);
-            });
-
-            let new_volume = percent_to_volume(volume_percent);
-
-            let mut volume = rx.recv().expect("to receive info");
-            for v in volume.get_mut() {
-                v.0 = new_volume;
-            }
+    pub async fn set_input_volume(&self, index: u32, volume_percent: f32) -> Result<()> {
+        self.request(
+            format!("set the volume of sink input {index}"),
+            move |context, done| {
+                // the volume is set per channel, so the stream is asked for them first
+                let mut introspector = context.introspect();
+                let mut done = Some(done);
+                context
+                    .introspect()
+                    .get_sink_input_info(index, move |info| {
+                        let Some(done) = done.take() else {
+                            return;
+                        };
+                        let ListResult::Item(info) = info else {
+                            done.fail("no such sink input");
+                            return;
+                        };
+
+                        let mut volume = info.volume;
+                        set_percent(&mut volume, volume_percent);
+                        introspector.set_sink_input_volume(index, &volume, Some(done.callback()));
+                    });
+            },
+        )
+        .await
+    }
 
-            introspector.set_sink_input_volume(index, &volume, None);
-        }
+    /// Moves the stream to the sink `sink_index`.
+    pub async fn move_sink_input(&self, index: u32, sink_index: u32) -> Result<()> {
+        debug!("move sink input: {index} {sink_index}");
+        self.request(
+            format!("move sink input {index} to sink {sink_index}"),
+            move |context, done| {
+                context.introspect().move_sink_input_by_index(
+                    index,
+                    sink_index,
+                    Some(done.callback()),
+                );
+            },
+        )
+        .await
     }
 
-    pub fn set_input_muted(&self, index: u32, muted: bool) {
-        if let ConnectionState::Connected { introspector, .. } = &mut *lock!(self.connection) {
-            introspector.set_sink_input_mute(index, muted, None);
-        }
+    pub async fn set_input_muted(&self, index: u32, muted: bool) -> Result<()> {
+        let what = if muted { "mute" } else { "unmute" };
+        self.request(
+            format!("{what} sink input {index}"),
+            move |context, done| {
+                context
+                    .introspect()
+                    .set_sink_input_mute(index, muted, Some(done.callback()));
+            },
+        )
+        .await
     }
 }
 
@@ -0,0 +1,218 @@
+use crate::{lock, send};
+
+use super::{ArcMutVec, Client, Event, set_percent, volume_to_percent};
+use anyhow::Result;
+use libpulse_binding::callbacks::ListResult;
+use libpulse_binding::context::Context;
+use libpulse_binding::context::introspect::SourceInfo;
+use libpulse_binding::context::subscribe::Operation;
+use libpulse_binding::def::SourceState;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::{debug, error, trace};
+
+/// A microphone or another input, the monitors of the sinks left out.
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub index: u32,
+    pub name: String,
+    pub description: String,
+    pub volume: f32,
+    pub muted: bool,
+    pub active: bool,
+}
+
+impl From<&SourceInfo<'_>> for Source {
+    fn from(value: &SourceInfo) -> Self {
+        Self {
+            index: value.index,
+            name: value
+                .name
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            description: value
+                .description
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            muted: value.mute,
+            volume: volume_to_percent(value.volume),
+            active: value.state == SourceState::Running,
+        }
+    }
+}
+
+/// Whether the source records what a sink plays, rather than a device.
+fn is_monitor(info: &SourceInfo) -> bool {
+    info.monitor_of_sink.is_some()
+        || info
+            .name
+            .as_ref()
+            .is_some_and(|name| name.ends_with(".monitor"))
+}
+
+impl Client {
+    pub fn sources(&self) -> Arc<Mutex<Vec<Source>>> {
+        self.data.sources.clone()
+    }
+
+    /// The source new streams record from.
+    pub fn default_source(&self) -> Option<Source> {
+        let default_source_name = lock!(self.data.default_source_name).clone();
+        let sources = lock!(self.data.sources);
+        sources
+            .iter()
+            .find(|source| Some(&source.name) == default_source_name.as_ref())
+            .or_else(|| sources.iter().find(|source| source.active))
+            .cloned()
+    }
+
+    pub async fn set_default_source(&self, name: &str) -> Result<()> {
+        let name = name.to_string();
+        self.request(
+            format!("set the default source to {name}"),
+            move |context, done| {
+                context.set_default_source(&name, done.callback());
+            },
+        )
+        .await
+    }
+
+    pub async fn set_source_volume(&self, name: &str, volume_percent: f32) -> Result<()> {
+        debug!("set source volume: {name} {volume_percent}");
+        let name = name.to_string();
+        self.request(format!("set the volume of {name}"), move |context, done| {
+            // the volume is set per channel, so the source is asked for them first
+            let mut introspector = context.introspect();
+            let mut done = Some(done);
+            context
+                .introspect()
+                .get_source_info_by_name(&name.clone(), move |info| {
+                    let Some(done) = done.take() else {
+                        return;
+                    };
+                    let ListResult::Item(info) = info else {
+                        done.fail("no such source");
+                        return;
+                    };
+
+                    let mut volume = info.volume;
+                    set_percent(&mut volume, volume_percent);
+                    introspector.set_source_volume_by_name(&name, &volume, Some(done.callback()));
+                });
+        })
+        .await
+    }
+
+    pub async fn set_source_muted(&self, name: &str, muted: bool) -> Result<()> {
+        debug!("set source muted: {name} {muted}");
+        let name = name.to_string();
+        let what = if muted { "mute" } else { "unmute" };
+        self.request(format!("{what} {name}"), move |context, done| {
+            context
+                .introspect()
+                .set_source_mute_by_name(&name, muted, Some(done.callback()));
+        })
+        .await
+    }
+}
+
+pub fn on_event(
+    context: &Arc<Mutex<Context>>,
+    sources: &ArcMutVec<Source>,
+    default_source: &Arc<Mutex<Option<String>>>,
+    tx: &broadcast::Sender<Event>,
+    op: Operation,
+    i: u32,
+) {
+    let introspect = lock!(context).introspect();
+
+    match op {
+        Operation::New => {
+            introspect.get_source_info_by_index(i, {
+                let sources = sources.clone();
+                let tx = tx.clone();
+
+                move |info| add(info, &sources, &tx)
+            });
+        }
+        Operation::Changed => {
+            introspect.get_source_info_by_index(i, {
+                let sources = sources.clone();
+                let default_source = default_source.clone();
+                let tx = tx.clone();
+
+                move |info| update(info, &sources, &default_source, &tx)
+            });
+        }
+        Operation::Removed => {
+            remove(i, sources, tx);
+        }
+    }
+}
+
+pub fn add(
+    info: ListResult<&SourceInfo>,
+    sources: &ArcMutVec<Source>,
+    tx: &broadcast::Sender<Event>,
+) {
+    let ListResult::Item(info) = info else {
+        return;
+    };
+    if is_monitor(info) {
+        return;
+    }
+
+    trace!("adding {info:?}");
+
+    lock!(sources).push(info.into());
+    send!(tx, Event::AddSource(info.into()));
+}
+
+fn update(
+    info: ListResult<&SourceInfo>,
+    sources: &ArcMutVec<Source>,
+    default_source: &Arc<Mutex<Option<String>>>,
+    tx: &broadcast::Sender<Event>,
+) {
+    let ListResult::Item(info) = info else {
+        return;
+    };
+    if is_monitor(info) {
+        return;
+    }
+
+    trace!("updating {info:?}");
+
+    let mut source: Source = info.into();
+    if !source.active {
+        if let Some(default_source) = &*lock!(default_source) {
+            source.active = &source.name == default_source;
+        }
+    }
+
+    {
+        let mut sources = lock!(sources);
+        let Some(pos) = sources.iter().position(|known| known.index == info.index) else {
+            error!("received update to untracked source");
+            return;
+        };
+
+        sources[pos] = source.clone();
+    }
+
+    send!(tx, Event::UpdateSource(source));
+}
+
+fn remove(index: u32, sources: &ArcMutVec<Source>, tx: &broadcast::Sender<Event>) {
+    trace!("removing {index}");
+
+    let mut sources = lock!(sources);
+
+    // monitors were never tracked
+    if let Some(pos) = sources.iter().position(|s| s.index == index) {
+        let info = sources.remove(pos);
+        send!(tx, Event::RemoveSource(info.name));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{lock, send};
+
+use super::{ArcMutVec, Client, Event, volume_to_percent};
+use libpulse_binding::callbacks::ListResult;
+use libpulse_binding::context::Context;
+use libpulse_binding::context::introspect::SourceOutputInfo;
+use libpulse_binding::context::subscribe::Operation;
+use libpulse_binding::proplist::properties;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::{debug, error, trace};
+
+/// A stream recording from a source.
+#[derive(Debug, Clone)]
+pub struct SourceOutput {
+    pub index: u32,
+    /// The name of the recording application, or of the stream when it has none.
+    pub name: String,
+    /// The index of the source recorded from.
+    pub source: u32,
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl From<&SourceOutputInfo<'_>> for SourceOutput {
+    fn from(value: &SourceOutputInfo) -> Self {
+        let name = value
+            .proplist
+            .get_str(properties::APPLICATION_NAME)
+            .or_else(|| value.name.as_ref().map(ToString::to_string))
+            .unwrap_or_default();
+
+        Self {
+            index: value.index,
+            name,
+            source: value.source,
+            muted: value.mute,
+            volume: volume_to_percent(value.volume),
+        }
+    }
+}
+
+impl Client {
+    pub fn source_outputs(&self) -> Arc<Mutex<Vec<SourceOutput>>> {
+        self.data.source_outputs.clone()
+    }
+
+    /// The streams recording from a source, leaving out those recording sink monitors.
+    pub fn recording(&self) -> Vec<SourceOutput> {
+        let sources = lock!(self.data.sources);
+        lock!(self.data.source_outputs)
+            .iter()
+            .filter(|output| sources.iter().any(|source| source.index == output.source))
+            .cloned()
+            .collect()
+    }
+}
+
+pub fn on_event(
+    context: &Arc<Mutex<Context>>,
+    outputs: &ArcMutVec<SourceOutput>,
+    tx: &broadcast::Sender<Event>,
+    op: Operation,
+    i: u32,
+) {
+    let introspect = lock!(context).introspect();
+
+    match op {
+        Operation::New => {
+            debug!("new source output");
+            introspect.get_source_output_info(i, {
+                let outputs = outputs.clone();
+                let tx = tx.clone();
+
+                move |info| add(info, &outputs, &tx)
+            });
+        }
+        Operation::Changed => {
+            debug!("source output changed");
+            introspect.get_source_output_info(i, {
+                let outputs = outputs.clone();
+                let tx = tx.clone();
+
+                move |info| update(info, &outputs, &tx)
+            });
+        }
+        Operation::Removed => {
+            debug!("source output removed");
+            remove(i, outputs, tx);
+        }
+    }
+}
+
+pub fn add(
+    info: ListResult<&SourceOutputInfo>,
+    outputs: &ArcMutVec<SourceOutput>,
+    tx: &broadcast::Sender<Event>,
+) {
+    let ListResult::Item(info) = info else {
+        return;
+    };
+
+    trace!("adding {info:?}");
+
+    lock!(outputs).push(info.into());
+    send!(tx, Event::AddOutput(info.into()));
+}
+
+fn update(
+    info: ListResult<&SourceOutputInfo>,
+    outputs: &ArcMutVec<SourceOutput>,
+    tx: &broadcast::Sender<Event>,
+) {
+    let ListResult::Item(info) = info else {
+        return;
+    };
+
+    trace!("updating {info:?}");
+
+    {
+        let mut outputs = lock!(outputs);
+        let Some(pos) = outputs.iter().position(|output| output.index == info.index) else {
+            error!("received update to untracked source output");
+            return;
+        };
+
+        outputs[pos] = info.into();
+    }
+
+    send!(tx, Event::UpdateOutput(info.into()));
+}
+
+fn remove(index: u32, outputs: &ArcMutVec<SourceOutput>, tx: &broadcast::Sender<Event>) {
+    let mut outputs = lock!(outputs);
+
+    trace!("removing {index}");
+
+    if let Some(pos) = outputs.iter().position(|s| s.index == index) {
+        let info = outputs.remove(pos);
+        send!(tx, Event::RemoveOutput(info.index));
+    }
+}
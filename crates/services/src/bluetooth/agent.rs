@@ -0,0 +1,105 @@
+//! Our `org.bluez.Agent1`, which BlueZ asks for PIN codes and confirmations while pairing.
+
+use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+use zbus::{Connection, DBusError, interface};
+
+use super::dbus::AgentManager1Proxy;
+use super::{Reply, RequestKind, Subscriber};
+
+const PATH: &str = "/dev/gallon/mgs/BluetoothAgent";
+/// What the agent can do with the user, which lets BlueZ pick the pairing method.
+const CAPABILITY: &str = "KeyboardDisplay";
+
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "org.bluez.Error")]
+enum Error {
+    Rejected(String),
+    Canceled(String),
+}
+
+struct Agent {
+    subscriber: Subscriber,
+}
+
+impl Agent {
+    /// Asks the user about `device`, failing when they refuse or the request is canceled.
+    async fn ask(&self, device: &ObjectPath<'_>, kind: RequestKind) -> Result<Reply, Error> {
+        match self.subscriber.ask(device, kind).await {
+            Some(Reply::Reject) => Err(Error::Rejected("rejected by the user".to_string())),
+            Some(reply) => Ok(reply),
+            None => Err(Error::Canceled("canceled".to_string())),
+        }
+    }
+
+    async fn accept(&self, device: &ObjectPath<'_>, kind: RequestKind) -> Result<(), Error> {
+        self.ask(device, kind).await.map(|_| ())
+    }
+}
+
+#[interface(name = "org.bluez.Agent1")]
+impl Agent {
+    fn release(&self) {
+        self.subscriber.cancel_request();
+    }
+
+    async fn request_pin_code(&self, device: ObjectPath<'_>) -> Result<String, Error> {
+        match self.ask(&device, RequestKind::PinCode).await? {
+            Reply::PinCode(pin_code) => Ok(pin_code),
+            _ => Err(Error::Rejected("no PIN code given".to_string())),
+        }
+    }
+
+    fn display_pin_code(&self, device: ObjectPath<'_>, pincode: String) {
+        self.subscriber
+            .show(&device, RequestKind::DisplayPinCode(pincode));
+    }
+
+    async fn request_passkey(&self, device: ObjectPath<'_>) -> Result<u32, Error> {
+        match self.ask(&device, RequestKind::Passkey).await? {
+            Reply::Passkey(passkey) => Ok(passkey),
+            _ => Err(Error::Rejected("no passkey given".to_string())),
+        }
+    }
+
+    fn display_passkey(&self, device: ObjectPath<'_>, passkey: u32, entered: u16) {
+        self.subscriber
+            .show(&device, RequestKind::DisplayPasskey { passkey, entered });
+    }
+
+    async fn request_confirmation(
+        &self,
+        device: ObjectPath<'_>,
+        passkey: u32,
+    ) -> Result<(), Error> {
+        self.accept(&device, RequestKind::Confirmation(passkey))
+            .await
+    }
+
+    async fn request_authorization(&self, device: ObjectPath<'_>) -> Result<(), Error> {
+        self.accept(&device, RequestKind::Authorization).await
+    }
+
+    async fn authorize_service(&self, device: ObjectPath<'_>, uuid: String) -> Result<(), Error> {
+        self.accept(&device, RequestKind::Service(uuid)).await
+    }
+
+    fn cancel(&self) {
+        self.subscriber.cancel_request();
+    }
+}
+
+/// Serves the agent on the connection of `subscriber`, and registers it.
+pub async fn start(subscriber: Subscriber) -> anyhow::Result<()> {
+    let conn = subscriber.conn.clone();
+    conn.object_server().at(PATH, Agent { subscriber }).await?;
+    register(&conn).await
+}
+
+/// Makes the agent the default one, which BlueZ forgets when it restarts.
+pub async fn register(conn: &Connection) -> anyhow::Result<()> {
+    let path = OwnedObjectPath::try_from(PATH)?;
+    let manager = AgentManager1Proxy::new(conn).await?;
+    manager.register_agent(&path, CAPABILITY).await?;
+    manager.request_default_agent(&path).await?;
+    Ok(())
+}
@@ -0,0 +1,546 @@
+mod agent;
+pub mod dbus;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use futures_signals::signal::{Mutable, MutableSignalCloned};
+use futures_util::stream::select_all;
+use futures_util::{Stream, StreamExt};
+use tokio::sync::oneshot;
+use tracing::{debug, error, info, warn};
+use zbus::fdo::{ObjectManagerProxy, PropertiesChanged};
+use zbus::message::Type;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue};
+use zbus::{Connection, MatchRule, MessageStream};
+
+use self::dbus::{Adapter1Proxy, Device1Proxy};
+
+const SERVICE: &str = "org.bluez";
+const ADAPTER: &str = "org.bluez.Adapter1";
+const DEVICE: &str = "org.bluez.Device1";
+const BATTERY: &str = "org.bluez.Battery1";
+const AGENT_MANAGER: &str = "org.bluez.AgentManager1";
+
+type Properties = HashMap<String, OwnedValue>;
+/// The interfaces of each BlueZ object, with their properties.
+type Objects = HashMap<OwnedObjectPath, HashMap<String, Properties>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adapter {
+    pub path: String,
+    pub name: String,
+    pub powered: bool,
+    /// Whether the adapter looks for devices around.
+    pub discovering: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    pub path: String,
+    pub address: String,
+    /// The alias, which is the name unless the user renamed the device.
+    pub name: String,
+    /// A freedesktop icon name, e.g. `audio-headset`.
+    pub icon: Option<String>,
+    pub paired: bool,
+    /// Whether the device may connect without asking.
+    pub trusted: bool,
+    pub connected: bool,
+    /// Battery percentage, for devices that tell.
+    pub battery: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BluetoothData {
+    /// The first adapter, `None` when there is none.
+    pub adapter: Option<Adapter>,
+    /// The devices of the adapter, connected ones first, then paired ones.
+    pub devices: Vec<Device>,
+}
+
+impl BluetoothData {
+    fn new(objects: &Objects) -> Self {
+        let string = |properties: &Properties, key: &str| {
+            properties
+                .get(key)
+                .and_then(|value| <&str>::try_from(value).ok())
+                .filter(|value| !value.is_empty())
+                .map(ToString::to_string)
+        };
+        let flag = |properties: &Properties, key: &str| {
+            properties
+                .get(key)
+                .and_then(|value| bool::try_from(value).ok())
+                .unwrap_or_default()
+        };
+
+        let mut adapters: Vec<_> = objects
+            .iter()
+            .filter_map(|(path, interfaces)| Some((path, interfaces.get(ADAPTER)?)))
+            .collect();
+        // hci0 before hci1
+        adapters.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+        let Some((adapter_path, adapter)) = adapters.first() else {
+            return Self::default();
+        };
+
+        // the separator keeps hci1 from taking the devices of hci10
+        let prefix = format!("{}/", adapter_path.as_str());
+        let mut devices: Vec<_> = objects
+            .iter()
+            .filter(|(path, _)| path.as_str().starts_with(&prefix))
+            .filter_map(|(path, interfaces)| {
+                let device = interfaces.get(DEVICE)?;
+                let address = string(device, "Address").unwrap_or_default();
+                Some(Device {
+                    path: path.to_string(),
+                    name: string(device, "Alias")
+                        .or_else(|| string(device, "Name"))
+                        .unwrap_or_else(|| address.clone()),
+                    address,
+                    icon: string(device, "Icon"),
+                    paired: flag(device, "Paired"),
+                    trusted: flag(device, "Trusted"),
+                    connected: flag(device, "Connected"),
+                    battery: interfaces
+                        .get(BATTERY)
+                        .and_then(|battery| battery.get("Percentage"))
+                        .and_then(|percentage| u8::try_from(percentage).ok()),
+                })
+            })
+            .collect();
+        devices.sort_by(|a, b| {
+            (!a.connected, !a.paired, a.name.to_lowercase()).cmp(&(
+                !b.connected,
+                !b.paired,
+                b.name.to_lowercase(),
+            ))
+        });
+
+        Self {
+            adapter: Some(Adapter {
+                path: adapter_path.to_string(),
+                name: string(adapter, "Alias")
+                    .or_else(|| string(adapter, "Name"))
+                    .unwrap_or_else(|| adapter_path.to_string()),
+                powered: flag(adapter, "Powered"),
+                discovering: flag(adapter, "Discovering"),
+            }),
+            devices,
+        }
+    }
+
+    pub fn connected(&self) -> usize {
+        self.devices
+            .iter()
+            .filter(|device| device.connected)
+            .count()
+    }
+}
+
+/// What BlueZ asks the user while pairing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestKind {
+    /// Answered with [`Reply::PinCode`].
+    PinCode,
+    /// Answered with [`Reply::Passkey`].
+    Passkey,
+    /// A PIN code to type on the device.
+    DisplayPinCode(String),
+    /// A passkey to type on the device, of which `entered` digits were typed.
+    DisplayPasskey { passkey: u32, entered: u16 },
+    /// Whether the device shows the same passkey.
+    Confirmation(u32),
+    /// Whether to pair with a device that asked to.
+    Authorization,
+    /// Whether the device may use the service with this UUID.
+    Service(String),
+}
+
+impl RequestKind {
+    /// Whether the request only shows something, with nothing to answer.
+    pub fn is_display(&self) -> bool {
+        matches!(self, Self::DisplayPinCode(_) | Self::DisplayPasskey { .. })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentRequest {
+    pub id: u64,
+    /// The path of the device.
+    pub device: String,
+    pub name: String,
+    pub kind: RequestKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    Accept,
+    Reject,
+    PinCode(String),
+    Passkey(u32),
+}
+
+/// The name of the common profiles, from the UUID a device asks to use.
+pub fn service_name(uuid: &str) -> Option<&'static str> {
+    let short = uuid
+        .strip_prefix("0000")?
+        .strip_suffix("-0000-1000-8000-00805f9b34fb")?;
+    let name = match short.to_lowercase().as_str() {
+        "1105" => "Object Push",
+        "1106" => "File Transfer",
+        "1108" | "1112" => "Headset",
+        "110a" => "Audio Source",
+        "110b" => "Audio Sink",
+        "110c" | "110e" => "Remote Control",
+        "1115" => "Personal Area Network",
+        "1116" => "Network Access Point",
+        "111e" | "111f" => "Hands-Free",
+        "1124" => "Human Interface Device",
+        "112f" => "Phone Book Access",
+        "1132" => "Message Access",
+        _ => return None,
+    };
+    Some(name)
+}
+
+enum Change {
+    Added(OwnedObjectPath, HashMap<String, Properties>),
+    Removed(OwnedObjectPath, Vec<String>),
+    Properties {
+        path: OwnedObjectPath,
+        interface: String,
+        changed: Properties,
+        invalidated: Vec<String>,
+    },
+}
+
+/// The id of the request the agent waits on, and where its answer goes.
+type PendingReply = Option<(u64, oneshot::Sender<Reply>)>;
+
+/// Tracks the Bluetooth adapter and its devices through BlueZ, and serves the agent it
+/// asks while pairing.
+#[derive(Debug, Clone)]
+pub struct Subscriber {
+    data: Mutable<BluetoothData>,
+    /// What the agent waits for the user to answer, or shows them.
+    request: Mutable<Option<AgentRequest>>,
+    /// Dropping the sender cancels the request.
+    reply: Arc<Mutex<PendingReply>>,
+    next_request: Arc<AtomicU64>,
+    conn: Connection,
+}
+
+impl Subscriber {
+    pub async fn new() -> anyhow::Result<Self> {
+        let conn = Connection::system().await?;
+        let object_manager = ObjectManagerProxy::builder(&conn)
+            .destination(SERVICE)?
+            .path("/")?
+            .build()
+            .await?;
+        // subscribe before reading the objects, so no change slips through
+        let changes = changes(&conn, &object_manager).await?;
+        let objects: Objects = object_manager
+            .get_managed_objects()
+            .await?
+            .into_iter()
+            .map(|(path, interfaces)| {
+                let interfaces = interfaces
+                    .into_iter()
+                    .map(|(name, properties)| (name.to_string(), properties))
+                    .collect();
+                (path, interfaces)
+            })
+            .collect();
+
+        let subscriber = Self {
+            data: Mutable::new(BluetoothData::new(&objects)),
+            request: Mutable::new(None),
+            reply: Arc::new(Mutex::new(None)),
+            next_request: Arc::new(AtomicU64::new(1)),
+            conn,
+        };
+        // pairing still works from a device asking for nothing
+        if let Err(err) = agent::start(subscriber.clone()).await {
+            warn!("failed to register the bluetooth agent: {err:#}");
+        }
+
+        let subscriber_for_task = subscriber.clone();
+        tokio::spawn(async move {
+            if let Err(err) = subscriber_for_task.run(objects, changes).await {
+                error!("Error in bluetooth subscriber: {err:?}");
+            }
+        });
+
+        Ok(subscriber)
+    }
+
+    async fn run(
+        &self,
+        mut objects: Objects,
+        mut changes: impl Stream<Item = Change> + Unpin,
+    ) -> anyhow::Result<()> {
+        info!("Bluetooth subscriber start");
+
+        while let Some(change) = changes.next().await {
+            match change {
+                Change::Added(path, interfaces) => {
+                    debug!("bluetooth object added: {path}");
+                    if interfaces.contains_key(AGENT_MANAGER) {
+                        info!("BlueZ restarted, registering the agent again");
+                        if let Err(err) = agent::register(&self.conn).await {
+                            warn!("failed to register the bluetooth agent: {err:#}");
+                        }
+                    }
+                    objects.entry(path).or_default().extend(interfaces);
+                }
+                Change::Removed(path, interfaces) => {
+                    debug!("bluetooth object removed: {path}");
+                    if let Some(existing) = objects.get_mut(&path) {
+                        for interface in &interfaces {
+                            existing.remove(interface);
+                        }
+                        if existing.is_empty() {
+                            objects.remove(&path);
+                        }
+                    }
+                }
+                Change::Properties {
+                    path,
+                    interface,
+                    changed,
+                    invalidated,
+                } => {
+                    let Some(properties) = objects
+                        .get_mut(&path)
+                        .and_then(|interfaces| interfaces.get_mut(&interface))
+                    else {
+                        continue;
+                    };
+                    properties.extend(changed);
+                    for name in &invalidated {
+                        properties.remove(name);
+                    }
+                }
+            }
+
+            let data = BluetoothData::new(&objects);
+            // nothing is left to show once the device paired
+            self.request.lock_mut().take_if(|request| {
+                request.kind.is_display()
+                    && data
+                        .devices
+                        .iter()
+                        .any(|device| device.path == request.device && device.paired)
+            });
+            self.data.set_neq(data);
+        }
+
+        Ok(())
+    }
+
+    fn adapter(&self) -> anyhow::Result<String> {
+        self.data
+            .lock_ref()
+            .adapter
+            .as_ref()
+            .map(|adapter| adapter.path.clone())
+            .context("there is no bluetooth adapter")
+    }
+
+    async fn adapter_proxy(&self) -> anyhow::Result<Adapter1Proxy<'static>> {
+        Ok(Adapter1Proxy::builder(&self.conn)
+            .path(self.adapter()?)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?)
+    }
+
+    async fn device(&self, path: &str) -> anyhow::Result<Device1Proxy<'static>> {
+        Ok(Device1Proxy::builder(&self.conn)
+            .path(path.to_string())?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?)
+    }
+
+    pub async fn set_powered(&self, powered: bool) -> anyhow::Result<()> {
+        self.adapter_proxy().await?.set_powered(powered).await?;
+        Ok(())
+    }
+
+    /// Looks for devices around, until [`Subscriber::stop_discovery`].
+    pub async fn start_discovery(&self) -> anyhow::Result<()> {
+        self.adapter_proxy().await?.start_discovery().await?;
+        Ok(())
+    }
+
+    pub async fn stop_discovery(&self) -> anyhow::Result<()> {
+        self.adapter_proxy().await?.stop_discovery().await?;
+        Ok(())
+    }
+
+    pub async fn connect(&self, path: &str) -> anyhow::Result<()> {
+        self.device(path).await?.connect().await?;
+        Ok(())
+    }
+
+    pub async fn disconnect(&self, path: &str) -> anyhow::Result<()> {
+        self.device(path).await?.disconnect().await?;
+        Ok(())
+    }
+
+    pub async fn pair(&self, path: &str) -> anyhow::Result<()> {
+        self.device(path).await?.pair().await?;
+        Ok(())
+    }
+
+    pub async fn set_trusted(&self, path: &str, trusted: bool) -> anyhow::Result<()> {
+        self.device(path).await?.set_trusted(trusted).await?;
+        Ok(())
+    }
+
+    /// Forgets the device, unpairing it.
+    pub async fn remove(&self, path: &str) -> anyhow::Result<()> {
+        self.adapter_proxy()
+            .await?
+            .remove_device(&ObjectPath::try_from(path)?)
+            .await?;
+        Ok(())
+    }
+
+    /// Shows `kind` to the user, and waits for their answer, `None` when the request is
+    /// canceled or replaced.
+    async fn ask(&self, device: &ObjectPath<'_>, kind: RequestKind) -> Option<Reply> {
+        let (sender, receiver) = oneshot::channel();
+        let id = self.show(device, kind);
+        *self.reply.lock().unwrap() = Some((id, sender));
+
+        let reply = receiver.await.ok();
+        self.request.lock_mut().take_if(|request| request.id == id);
+        reply
+    }
+
+    fn show(&self, device: &ObjectPath<'_>, kind: RequestKind) -> u64 {
+        let id = self.next_request.fetch_add(1, Ordering::Relaxed);
+        let name = self
+            .data
+            .lock_ref()
+            .devices
+            .iter()
+            .find(|known| known.path == device.as_str())
+            .map(|known| known.name.clone())
+            // paths end with the address, as in `dev_AA_BB_CC_DD_EE_FF`
+            .unwrap_or_else(|| {
+                let name = device.as_str().rsplit('/').next().unwrap_or_default();
+                name.trim_start_matches("dev_").replace('_', ":")
+            });
+        self.request.set(Some(AgentRequest {
+            id,
+            device: device.to_string(),
+            name,
+            kind,
+        }));
+        id
+    }
+
+    fn cancel_request(&self) {
+        self.reply.lock().unwrap().take();
+        self.request.set(None);
+    }
+
+    /// Answers the request `id`, or closes it when it only shows something.
+    pub fn reply(&self, id: u64, reply: Reply) {
+        let mut pending = self.reply.lock().unwrap();
+        if let Some((_, sender)) = pending.take_if(|(pending, _)| *pending == id) {
+            sender.send(reply).ok();
+        }
+        drop(pending);
+        self.request.lock_mut().take_if(|request| request.id == id);
+    }
+
+    /// The request of the agent waiting for the user, if any.
+    pub fn subscribe_request(&self) -> MutableSignalCloned<Option<AgentRequest>> {
+        self.request.signal_cloned()
+    }
+
+    pub fn get(&self) -> BluetoothData {
+        self.data.get_cloned()
+    }
+
+    pub fn subscribe(&self) -> MutableSignalCloned<BluetoothData> {
+        self.data.signal_cloned()
+    }
+}
+
+/// Objects coming and going, and the changes of their properties.
+async fn changes(
+    conn: &Connection,
+    object_manager: &ObjectManagerProxy<'_>,
+) -> anyhow::Result<impl Stream<Item = Change> + Unpin + use<>> {
+    let added = object_manager
+        .receive_interfaces_added()
+        .await?
+        .filter_map(|signal| async move {
+            let args = signal.args().ok()?;
+            let interfaces = args
+                .interfaces_and_properties()
+                .iter()
+                .map(|(name, properties)| (name.to_string(), owned(properties)))
+                .collect();
+            Some(Change::Added(args.object_path().clone().into(), interfaces))
+        })
+        .boxed();
+    let removed = object_manager
+        .receive_interfaces_removed()
+        .await?
+        .filter_map(|signal| async move {
+            let args = signal.args().ok()?;
+            let interfaces = args.interfaces().iter().map(ToString::to_string).collect();
+            Some(Change::Removed(
+                args.object_path().clone().into(),
+                interfaces,
+            ))
+        })
+        .boxed();
+
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .sender(SERVICE)?
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path_namespace("/org/bluez")?
+        .build();
+    let properties = MessageStream::for_match_rule(rule, conn, None)
+        .await?
+        .filter_map(|message| async move {
+            let signal = PropertiesChanged::from_message(message.ok()?)?;
+            let path = signal.message().header().path()?.clone().into();
+            let args = signal.args().ok()?;
+            Some(Change::Properties {
+                path,
+                interface: args.interface_name().to_string(),
+                changed: owned(args.changed_properties()),
+                invalidated: args
+                    .invalidated_properties()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
+            })
+        })
+        .boxed();
+
+    Ok(select_all([added, removed, properties]))
+}
+
+fn owned(properties: &HashMap<&str, zbus::zvariant::Value<'_>>) -> Properties {
+    properties
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.try_to_owned().ok()?)))
+        .collect()
+}
@@ -9,16 +9,35 @@ use tracing::error;
 pub struct Workspace {
     pub id: i32,
     pub name: String,
-    // pub monitor_id: Option<usize>,
+    /// Name of the monitor the workspace is on.
+    pub monitor: Option<String>,
     pub active: bool,
     pub visible: bool,
     // pub windows: u16,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u16,
+    pub height: u16,
+    pub scale: f32,
+}
+
+impl Monitor {
+    /// Width in logical pixels.
+    pub fn logical_width(&self) -> f32 {
+        self.width as f32 / self.scale
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct WorkspacesData {
     pub workspaces: Vec<Workspace>,
     pub active: usize,
+    pub monitors: Vec<Monitor>,
 }
 
 impl WorkspacesData {
@@ -26,6 +45,7 @@ impl WorkspacesData {
         let mut workspaces = vec![Workspace {
             id: 0,
             name: "S".to_string(),
+            monitor: None,
             active: false,
             visible: false,
         }];
@@ -33,6 +53,7 @@ impl WorkspacesData {
             workspaces.push(Workspace {
                 id: i,
                 name: format!("{}", i),
+                monitor: None,
                 active: false,
                 visible: false,
             });
@@ -48,7 +69,46 @@ impl WorkspacesData {
         });
         workspaces[active].active = true;
 
-        Ok(Self { workspaces, active })
+        let mut data = Self {
+            workspaces,
+            active,
+            monitors: Vec::new(),
+        };
+        data.refresh_monitors()?;
+
+        Ok(data)
+    }
+
+    /// Re-reads the monitors and which monitor each workspace is on.
+    fn refresh_monitors(&mut self) -> anyhow::Result<()> {
+        self.monitors = hyprland::data::Monitors::get()?
+            .iter()
+            .map(|m| Monitor {
+                name: m.name.clone(),
+                x: m.x,
+                y: m.y,
+                width: m.width,
+                height: m.height,
+                scale: m.scale,
+            })
+            .collect();
+
+        for workspace in self.workspaces.iter_mut() {
+            workspace.monitor = None;
+        }
+        for w in hyprland::data::Workspaces::get()?.iter() {
+            if let Some(workspace) = self.workspaces.get_mut(w.id.max(0) as usize) {
+                workspace.monitor = Some(w.monitor.clone());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn refresh_monitors(data: &Mutable<WorkspacesData>) {
+    if let Err(e) = data.lock_mut().refresh_monitors() {
+        error!("Failed to refresh monitors: {:?}", e);
     }
 }
 
@@ -96,11 +156,26 @@ impl Subscriber {
             let data = self.data.clone();
             event_listener.add_workspace_added_handler(move |evt| {
                 let id = if evt.id < 0 { 0 } else { evt.id as usize };
-                let mut data = data.lock_mut();
-                data.workspaces[id].visible = true;
+                data.lock_mut().workspaces[id].visible = true;
+                refresh_monitors(&data);
             });
         }
 
+        {
+            let data = self.data.clone();
+            event_listener.add_workspace_moved_handler(move |_| refresh_monitors(&data));
+        }
+
+        {
+            let data = self.data.clone();
+            event_listener.add_monitor_added_handler(move |_| refresh_monitors(&data));
+        }
+
+        {
+            let data = self.data.clone();
+            event_listener.add_monitor_removed_handler(move |_| refresh_monitors(&data));
+        }
+
         {
             let data = self.data.clone();
             event_listener.add_workspace_deleted_handler(move |evt| {
@@ -1,6 +1,15 @@
+pub mod appearance;
+pub mod applications;
 pub mod audio;
+pub mod bluetooth;
 pub mod hyprland;
+pub mod mpris;
 pub mod network;
+pub mod notifications;
 pub mod sysinfo;
 pub mod timedate;
+pub mod tray;
 pub mod upower;
+
+/// The well-known name mgs owns on the session bus, and its name towards other services.
+pub const APP_ID: &str = "dev.gallon.mgs";
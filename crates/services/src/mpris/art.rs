@@ -0,0 +1,44 @@
+//! Album art, read from the local files players point at.
+
+use std::path::PathBuf;
+
+/// The local file holding the art at `url`. Remote art isn't fetched, players showing
+/// it get the fallback icon.
+pub async fn art(url: &str) -> Option<PathBuf> {
+    let url = url.to_string();
+    tokio::task::spawn_blocking(move || local(&url))
+        .await
+        .ok()
+        .flatten()
+}
+
+fn local(url: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(percent_decode(url.strip_prefix("file://")?));
+    path.is_file().then_some(path)
+}
+
+/// Decodes the `%XX` escapes of a URL path, leaving malformed ones as they are.
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
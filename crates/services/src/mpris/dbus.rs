@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use zbus::proxy;
+use zbus::zvariant::{ObjectPath, OwnedValue};
+
+#[proxy(
+    interface = "org.mpris.MediaPlayer2",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+pub trait MediaPlayer2 {
+    fn raise(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn identity(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn desktop_entry(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn can_raise(&self) -> zbus::Result<bool>;
+}
+
+#[proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+pub trait Player {
+    fn next(&self) -> zbus::Result<()>;
+
+    fn previous(&self) -> zbus::Result<()>;
+
+    fn play_pause(&self) -> zbus::Result<()>;
+
+    /// Moves the position by `offset` microseconds.
+    fn seek(&self, offset: i64) -> zbus::Result<()>;
+
+    /// Moves to `position` microseconds into the track `track_id`.
+    fn set_position(&self, track_id: &ObjectPath<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn loop_status(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn set_loop_status(&self, value: &str) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn rate(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn shuffle(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn set_shuffle(&self, value: bool) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+
+    /// Between 0 and 1.
+    #[zbus(property)]
+    fn volume(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn set_volume(&self, value: f64) -> zbus::Result<()>;
+
+    /// In microseconds. Players don't signal its changes, besides jumps with `Seeked`.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> zbus::Result<i64>;
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn can_play(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn can_pause(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn can_seek(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn can_control(&self) -> zbus::Result<bool>;
+
+    #[zbus(signal)]
+    fn seeked(&self, position: i64) -> zbus::Result<()>;
+}
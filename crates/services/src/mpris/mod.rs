@@ -0,0 +1,553 @@
+mod art;
+pub mod dbus;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use futures_signals::signal::{Mutable, MutableSignalCloned};
+use futures_util::StreamExt;
+use futures_util::stream::select_all;
+use tokio::task::AbortHandle;
+use tracing::{debug, error, info, warn};
+use zbus::Connection;
+use zbus::fdo::{DBusProxy, NameOwnerChangedStream};
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{ObjectPath, OwnedValue};
+
+pub use self::art::art;
+use self::dbus::{MediaPlayer2Proxy, PlayerProxy};
+
+/// Players own a name under this prefix, e.g. `org.mpris.MediaPlayer2.spotify`.
+const PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+impl From<&str> for PlaybackStatus {
+    fn from(status: &str) -> Self {
+        match status {
+            "Playing" => PlaybackStatus::Playing,
+            "Paused" => PlaybackStatus::Paused,
+            _ => PlaybackStatus::Stopped,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoopStatus {
+    #[default]
+    None,
+    /// Repeats the current track.
+    Track,
+    Playlist,
+}
+
+impl From<&str> for LoopStatus {
+    fn from(status: &str) -> Self {
+        match status {
+            "Track" => LoopStatus::Track,
+            "Playlist" => LoopStatus::Playlist,
+            _ => LoopStatus::None,
+        }
+    }
+}
+
+impl LoopStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            LoopStatus::None => "None",
+            LoopStatus::Track => "Track",
+            LoopStatus::Playlist => "Playlist",
+        }
+    }
+
+    /// The status after this one, cycling through none, playlist and track.
+    pub fn next(self) -> Self {
+        match self {
+            LoopStatus::None => LoopStatus::Playlist,
+            LoopStatus::Playlist => LoopStatus::Track,
+            LoopStatus::Track => LoopStatus::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// Object path identifying the track, which seeking to a position refers to.
+    pub track_id: Option<String>,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    /// Usually a `file://` or `https://` URL.
+    pub art_url: Option<String>,
+    pub length: Option<Duration>,
+}
+
+impl Metadata {
+    fn new(metadata: &HashMap<String, OwnedValue>) -> Self {
+        let string = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(|value| {
+                    <&str>::try_from(value)
+                        .map(ToString::to_string)
+                        .or_else(|_| ObjectPath::try_from(&**value).map(|path| path.to_string()))
+                        .ok()
+                })
+                .filter(|value| !value.is_empty())
+        };
+        // the spec asks for a list of artists, some players send a single one
+        let artists = metadata
+            .get("xesam:artist")
+            .and_then(|value| {
+                <Vec<String>>::try_from(value.try_clone().ok()?)
+                    .ok()
+                    .or_else(|| string("xesam:artist").map(|artist| vec![artist]))
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|artist| !artist.is_empty())
+            .collect();
+        // microseconds, as a signed or an unsigned integer depending on the player
+        let length = metadata.get("mpris:length").and_then(|value| {
+            i64::try_from(value)
+                .ok()
+                .and_then(|length| u64::try_from(length).ok())
+                .or_else(|| u64::try_from(value).ok())
+                .filter(|length| *length > 0)
+                .map(Duration::from_micros)
+        });
+
+        Self {
+            track_id: string("mpris:trackid"),
+            title: string("xesam:title"),
+            artists,
+            album: string("xesam:album"),
+            art_url: string("mpris:artUrl"),
+            length,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Player {
+    /// Bus name of the player.
+    pub name: String,
+    /// Name of the player to show, e.g. `Spotify`.
+    pub identity: String,
+    pub desktop_entry: Option<String>,
+    pub status: PlaybackStatus,
+    pub metadata: Metadata,
+    /// As of `updated`, see [`Player::current_position`].
+    pub position: Duration,
+    /// When `position` was read.
+    pub updated: Instant,
+    /// Speed of the playback, 1 being the normal speed.
+    pub rate: f64,
+    /// `None` for players that don't support shuffling.
+    pub shuffle: Option<bool>,
+    /// `None` for players that don't support looping.
+    pub loop_status: Option<LoopStatus>,
+    /// Between 0 and 1, `None` for players without a volume of their own.
+    pub volume: Option<f64>,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub can_play: bool,
+    pub can_pause: bool,
+    pub can_seek: bool,
+    /// Whether the player can be controlled at all, besides showing what it plays.
+    pub can_control: bool,
+}
+
+impl Player {
+    /// The position now, players only telling when it jumps.
+    pub fn current_position(&self) -> Duration {
+        if self.status != PlaybackStatus::Playing {
+            return self.position;
+        }
+
+        let position = self.position + self.updated.elapsed().mul_f64(self.rate.max(0.));
+        match self.metadata.length {
+            Some(length) => position.min(length),
+            None => position,
+        }
+    }
+
+    async fn load(
+        name: &str,
+        identity: &str,
+        desktop_entry: Option<&str>,
+        proxy: &PlayerProxy<'_>,
+    ) -> zbus::Result<Self> {
+        let position = proxy
+            .position()
+            .await
+            .ok()
+            .and_then(|position| u64::try_from(position).ok())
+            .map(Duration::from_micros)
+            .unwrap_or_default();
+
+        Ok(Self {
+            name: name.to_string(),
+            identity: identity.to_string(),
+            desktop_entry: desktop_entry.map(ToString::to_string),
+            status: PlaybackStatus::from(proxy.playback_status().await?.as_str()),
+            metadata: proxy
+                .metadata()
+                .await
+                .map(|metadata| Metadata::new(&metadata))
+                .unwrap_or_default(),
+            position,
+            updated: Instant::now(),
+            rate: proxy.rate().await.unwrap_or(1.),
+            shuffle: proxy.shuffle().await.ok(),
+            loop_status: proxy
+                .loop_status()
+                .await
+                .ok()
+                .map(|status| LoopStatus::from(status.as_str())),
+            volume: proxy.volume().await.ok(),
+            can_go_next: proxy.can_go_next().await.unwrap_or_default(),
+            can_go_previous: proxy.can_go_previous().await.unwrap_or_default(),
+            can_play: proxy.can_play().await.unwrap_or_default(),
+            can_pause: proxy.can_pause().await.unwrap_or_default(),
+            can_seek: proxy.can_seek().await.unwrap_or_default(),
+            can_control: proxy.can_control().await.unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MprisData {
+    /// In the order they showed up.
+    pub players: Vec<Player>,
+    /// Bus name of the player the controls act on.
+    pub active: Option<String>,
+}
+
+impl MprisData {
+    pub fn active(&self) -> Option<&Player> {
+        let active = self.active.as_ref()?;
+        self.players.iter().find(|player| &player.name == active)
+    }
+}
+
+/// Tracks the media players on the session bus.
+///
+/// The active player follows whichever starts playing, until one is picked with
+/// [`Subscriber::set_active`].
+#[derive(Debug, Clone)]
+pub struct Subscriber {
+    data: Mutable<MprisData>,
+    /// Whether the active player was picked, rather than followed.
+    pinned: Arc<AtomicBool>,
+    conn: Connection,
+}
+
+impl Subscriber {
+    pub async fn new() -> anyhow::Result<Self> {
+        let conn = Connection::session().await?;
+        let dbus = DBusProxy::new(&conn).await?;
+        // subscribe before listing, so no player slips through
+        let owner_changes = dbus.receive_name_owner_changed().await?;
+        let names: Vec<String> = dbus
+            .list_names()
+            .await?
+            .into_iter()
+            .map(|name| name.to_string())
+            .filter(|name| name.starts_with(PREFIX))
+            .collect();
+
+        let subscriber = Self {
+            data: Mutable::new(MprisData::default()),
+            pinned: Arc::new(AtomicBool::new(false)),
+            conn,
+        };
+
+        let subscriber_for_task = subscriber.clone();
+        tokio::spawn(async move {
+            if let Err(err) = subscriber_for_task.run(names, owner_changes).await {
+                error!("Error in mpris subscriber: {err:?}");
+            }
+        });
+
+        Ok(subscriber)
+    }
+
+    async fn run(
+        &self,
+        names: Vec<String>,
+        mut owner_changes: NameOwnerChangedStream,
+    ) -> anyhow::Result<()> {
+        info!("Mpris subscriber start");
+
+        let mut watches = HashMap::new();
+        for name in names {
+            self.add(name, &mut watches);
+        }
+
+        while let Some(signal) = owner_changes.next().await {
+            let Ok(args) = signal.args() else {
+                continue;
+            };
+            let name = args.name().to_string();
+            if !name.starts_with(PREFIX) {
+                continue;
+            }
+
+            // a player taking over the name of another one replaces it
+            if args.old_owner().is_some() {
+                self.remove(&name, &mut watches);
+            }
+            if args.new_owner().is_some() {
+                self.add(name, &mut watches);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add(&self, name: String, watches: &mut HashMap<String, AbortHandle>) {
+        if watches.contains_key(&name) {
+            return;
+        }
+
+        debug!("media player added: {name}");
+        let subscriber = self.clone();
+        let player_name = name.clone();
+        let watch = tokio::spawn(async move {
+            if let Err(err) = subscriber.watch(&player_name).await {
+                warn!("failed to watch media player {player_name}: {err}");
+                subscriber.forget(&player_name);
+            }
+        });
+        watches.insert(name, watch.abort_handle());
+    }
+
+    fn remove(&self, name: &str, watches: &mut HashMap<String, AbortHandle>) {
+        debug!("media player removed: {name}");
+        if let Some(watch) = watches.remove(name) {
+            watch.abort();
+        }
+        self.forget(name);
+    }
+
+    fn forget(&self, name: &str) {
+        let mut data = self.data.lock_mut();
+        data.players.retain(|player| player.name != name);
+        if data.active.as_deref() == Some(name) {
+            self.pinned.store(false, Ordering::Relaxed);
+            data.active = data
+                .players
+                .iter()
+                .find(|player| player.status == PlaybackStatus::Playing)
+                .or(data.players.first())
+                .map(|player| player.name.clone());
+        }
+    }
+
+    /// Keeps the player up to date until it leaves the bus.
+    async fn watch(&self, name: &str) -> zbus::Result<()> {
+        let media_player = MediaPlayer2Proxy::builder(&self.conn)
+            .destination(name.to_string())?
+            .build()
+            .await?;
+        let identity = media_player
+            .identity()
+            .await
+            .ok()
+            .filter(|identity| !identity.is_empty())
+            .unwrap_or_else(|| name.trim_start_matches(PREFIX).to_string());
+        let desktop_entry = media_player
+            .desktop_entry()
+            .await
+            .ok()
+            .filter(|entry| !entry.is_empty());
+
+        // property changes come through the cache, so it stays enabled here
+        let proxy = PlayerProxy::builder(&self.conn)
+            .destination(name.to_string())?
+            .build()
+            .await?;
+        let changes = vec![
+            proxy
+                .receive_playback_status_changed()
+                .await
+                .map(|_| None)
+                .boxed(),
+            proxy.receive_metadata_changed().await.map(|_| None).boxed(),
+            proxy
+                .receive_can_go_next_changed()
+                .await
+                .map(|_| None)
+                .boxed(),
+            proxy
+                .receive_can_go_previous_changed()
+                .await
+                .map(|_| None)
+                .boxed(),
+            proxy.receive_can_play_changed().await.map(|_| None).boxed(),
+            proxy
+                .receive_can_pause_changed()
+                .await
+                .map(|_| None)
+                .boxed(),
+            proxy.receive_can_seek_changed().await.map(|_| None).boxed(),
+            proxy.receive_rate_changed().await.map(|_| None).boxed(),
+            proxy.receive_shuffle_changed().await.map(|_| None).boxed(),
+            proxy
+                .receive_loop_status_changed()
+                .await
+                .map(|_| None)
+                .boxed(),
+            proxy.receive_volume_changed().await.map(|_| None).boxed(),
+            proxy
+                .receive_seeked()
+                .await?
+                .map(|signal| signal.args().ok().map(|args| args.position))
+                .boxed(),
+        ];
+        let mut changes = select_all(changes);
+
+        let load = || Player::load(name, &identity, desktop_entry.as_deref(), &proxy);
+        self.update(load().await?);
+        while let Some(seeked) = changes.next().await {
+            match seeked {
+                Some(position) => self.seeked(name, position),
+                None => self.update(load().await?),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update(&self, player: Player) {
+        if self.data.lock_ref().players.contains(&player) {
+            return;
+        }
+
+        let mut data = self.data.lock_mut();
+        let playing = player.status == PlaybackStatus::Playing;
+        let name = player.name.clone();
+        match data
+            .players
+            .iter_mut()
+            .find(|existing| existing.name == name)
+        {
+            Some(existing) => *existing = player,
+            None => data.players.push(player),
+        }
+
+        let follow = match data.active() {
+            None => true,
+            Some(active) => {
+                playing
+                    && active.status != PlaybackStatus::Playing
+                    && !self.pinned.load(Ordering::Relaxed)
+            }
+        };
+        if follow {
+            data.active = Some(name);
+        }
+    }
+
+    fn seeked(&self, name: &str, position: i64) {
+        let mut data = self.data.lock_mut();
+        if let Some(player) = data.players.iter_mut().find(|player| player.name == name) {
+            player.position = Duration::from_micros(position.max(0) as u64);
+            player.updated = Instant::now();
+        }
+    }
+
+    /// Makes `name` the player the controls act on, until it leaves.
+    pub fn set_active(&self, name: &str) {
+        let mut data = self.data.lock_mut();
+        if data.players.iter().any(|player| player.name == name) {
+            self.pinned.store(true, Ordering::Relaxed);
+            data.active = Some(name.to_string());
+        }
+    }
+
+    async fn player(&self, name: &str) -> zbus::Result<PlayerProxy<'static>> {
+        PlayerProxy::builder(&self.conn)
+            .destination(name.to_string())?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+    }
+
+    pub async fn play_pause(&self, name: &str) -> anyhow::Result<()> {
+        self.player(name).await?.play_pause().await?;
+        Ok(())
+    }
+
+    pub async fn next(&self, name: &str) -> anyhow::Result<()> {
+        self.player(name).await?.next().await?;
+        Ok(())
+    }
+
+    pub async fn previous(&self, name: &str) -> anyhow::Result<()> {
+        self.player(name).await?.previous().await?;
+        Ok(())
+    }
+
+    /// Moves to `position` into the current track.
+    pub async fn set_position(&self, name: &str, position: Duration) -> anyhow::Result<()> {
+        let (track_id, current) = {
+            let data = self.data.lock_ref();
+            let player = data
+                .players
+                .iter()
+                .find(|player| player.name == name)
+                .context("no such player")?;
+            (player.metadata.track_id.clone(), player.current_position())
+        };
+        let micros = |duration: Duration| i64::try_from(duration.as_micros()).unwrap_or(i64::MAX);
+
+        let proxy = self.player(name).await?;
+        match track_id.as_deref().map(ObjectPath::try_from) {
+            Some(Ok(track_id)) => proxy.set_position(&track_id, micros(position)).await?,
+            // without a track id, seeking relative to where the track is at is the only way
+            _ => proxy.seek(micros(position) - micros(current)).await?,
+        }
+        Ok(())
+    }
+
+    pub async fn set_shuffle(&self, name: &str, shuffle: bool) -> anyhow::Result<()> {
+        self.player(name).await?.set_shuffle(shuffle).await?;
+        Ok(())
+    }
+
+    pub async fn set_loop_status(&self, name: &str, status: LoopStatus) -> anyhow::Result<()> {
+        self.player(name)
+            .await?
+            .set_loop_status(status.as_str())
+            .await?;
+        Ok(())
+    }
+
+    /// Sets the volume of the player, between 0 and 1.
+    pub async fn set_volume(&self, name: &str, volume: f64) -> anyhow::Result<()> {
+        self.player(name)
+            .await?
+            .set_volume(volume.clamp(0., 1.))
+            .await?;
+        Ok(())
+    }
+
+    pub fn get(&self) -> MprisData {
+        self.data.get_cloned()
+    }
+
+    pub fn subscribe(&self) -> MutableSignalCloned<MprisData> {
+        self.data.signal_cloned()
+    }
+}
@@ -0,0 +1,167 @@
+//! Our `org.freedesktop.NetworkManager.SecretAgent`, which NetworkManager asks for the
+//! passwords it lacks to bring a connection up.
+
+use std::collections::HashMap;
+
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{Connection, DBusError, interface};
+
+use super::dbus::agent_manager::AgentManagerProxy;
+use super::{SecretKind, SecretReply, Subscriber};
+
+/// NetworkManager calls every agent at this path.
+const PATH: &str = "/org/freedesktop/NetworkManager/SecretAgent";
+const IDENTIFIER: &str = "dev.gallon.mgs";
+
+/// Whether the user may be asked, rather than only given saved secrets.
+const ALLOW_INTERACTION: u32 = 0x1;
+/// Whether the secrets given before were wrong.
+const REQUEST_NEW: u32 = 0x2;
+
+type Settings = HashMap<String, HashMap<String, OwnedValue>>;
+
+#[derive(Debug, DBusError)]
+#[zbus(prefix = "org.freedesktop.NetworkManager.SecretAgent")]
+enum Error {
+    NoSecrets(String),
+    UserCanceled(String),
+    AgentCanceled(String),
+}
+
+/// The secret asked for, and the key it goes under in its setting.
+struct Secret {
+    kind: SecretKind,
+    key: String,
+}
+
+impl Secret {
+    fn new(connection: &Settings, setting: &str, hints: &[String]) -> Option<Self> {
+        let string = |setting: &str, key: &str| {
+            let value = connection.get(setting)?.get(key)?;
+            String::try_from(value.try_clone().ok()?).ok()
+        };
+
+        let (kind, key) = match setting {
+            "802-11-wireless-security" => match string(setting, "key-mgmt")?.as_str() {
+                "wpa-psk" | "sae" => (SecretKind::Wifi, "psk"),
+                "none" => (SecretKind::Wep, "wep-key0"),
+                // 802.1X networks ask for the `802-1x` setting
+                _ => return None,
+            },
+            "802-1x" => {
+                let identity = string(setting, "identity").unwrap_or_default();
+                (SecretKind::Enterprise { identity }, "password")
+            }
+            "vpn" => {
+                // plugins hint the secrets they need, and messages for the user
+                let key = hints
+                    .iter()
+                    .find(|hint| !hint.starts_with("x-vpn-message:"))
+                    .map_or("password", String::as_str);
+                return Some(Self {
+                    kind: SecretKind::Vpn,
+                    key: key.to_string(),
+                });
+            }
+            "pppoe" | "gsm" | "cdma" => (SecretKind::Password, "password"),
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            key: key.to_string(),
+        })
+    }
+
+    /// The settings to give back to NetworkManager, holding `value`.
+    fn settings(
+        &self,
+        setting: &str,
+        value: String,
+    ) -> HashMap<String, HashMap<String, Value<'static>>> {
+        let value = if self.kind == SecretKind::Vpn {
+            // VPN secrets are a map of their own
+            (
+                "secrets".to_string(),
+                Value::from(HashMap::from([(self.key.clone(), value)])),
+            )
+        } else {
+            (self.key.clone(), Value::from(value))
+        };
+        HashMap::from([(setting.to_string(), HashMap::from([value]))])
+    }
+}
+
+struct Agent {
+    subscriber: Subscriber,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.SecretAgent")]
+impl Agent {
+    async fn get_secrets(
+        &self,
+        connection: Settings,
+        connection_path: ObjectPath<'_>,
+        setting_name: String,
+        hints: Vec<String>,
+        flags: u32,
+    ) -> Result<HashMap<String, HashMap<String, Value<'static>>>, Error> {
+        if flags & ALLOW_INTERACTION == 0 {
+            return Err(Error::NoSecrets("no saved secrets".to_string()));
+        }
+        let Some(secret) = Secret::new(&connection, &setting_name, &hints) else {
+            return Err(Error::NoSecrets(format!("can't ask for {setting_name}")));
+        };
+
+        let name = connection
+            .get("connection")
+            .and_then(|settings| settings.get("id"))
+            .and_then(|id| String::try_from(id.try_clone().ok()?).ok())
+            .unwrap_or_default();
+        let retry = flags & REQUEST_NEW != 0;
+        let reply = self
+            .subscriber
+            .ask_secret(
+                &connection_path,
+                &setting_name,
+                name,
+                secret.kind.clone(),
+                retry,
+            )
+            .await;
+        match reply {
+            Some(SecretReply::Secret(value)) => Ok(secret.settings(&setting_name, value)),
+            Some(SecretReply::Cancel) => {
+                Err(Error::UserCanceled("canceled by the user".to_string()))
+            }
+            None => Err(Error::AgentCanceled("canceled".to_string())),
+        }
+    }
+
+    fn cancel_get_secrets(&self, connection_path: ObjectPath<'_>, setting_name: String) {
+        self.subscriber
+            .cancel_secret(&connection_path, &setting_name);
+    }
+
+    // NetworkManager keeps the secrets with the connection, there's nothing of ours to save
+    // or delete
+
+    fn save_secrets(&self, _connection: Settings, _connection_path: ObjectPath<'_>) {}
+
+    fn delete_secrets(&self, _connection: Settings, _connection_path: ObjectPath<'_>) {}
+}
+
+/// Serves the agent on the connection of `subscriber`, and registers it.
+pub async fn start(subscriber: Subscriber) -> anyhow::Result<()> {
+    let conn = subscriber.conn.clone();
+    conn.object_server().at(PATH, Agent { subscriber }).await?;
+    register(&conn).await
+}
+
+/// Registers the agent, which NetworkManager forgets when it restarts.
+pub async fn register(conn: &Connection) -> anyhow::Result<()> {
+    AgentManagerProxy::new(conn)
+        .await?
+        .register(IDENTIFIER)
+        .await?;
+    Ok(())
+}
@@ -1,5 +1,6 @@
 pub mod access_point;
 pub mod active_connection;
+pub mod agent_manager;
 pub mod device;
 pub mod nm;
 pub mod settings;
@@ -73,3 +73,23 @@ pub trait Settings {
     #[zbus(property)]
     fn hostname(&self) -> zbus::Result<String>;
 }
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings.Connection",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait SettingsConnection {
+    /// Delete method
+    fn delete(&self) -> zbus::Result<()>;
+
+    /// GetSettings method
+    #[allow(clippy::type_complexity)]
+    fn get_settings(
+        &self,
+    ) -> zbus::Result<
+        std::collections::HashMap<
+            String,
+            std::collections::HashMap<String, zbus::zvariant::OwnedValue>,
+        >,
+    >;
+}
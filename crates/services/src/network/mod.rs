@@ -1,18 +1,26 @@
-use anyhow::Ok;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Ok, bail};
 use futures_signals::signal::{Mutable, MutableSignalCloned};
 use futures_util::StreamExt;
 use futures_util::stream::select_all;
-use tracing::{error, info};
+use tokio::sync::oneshot;
+use tracing::{error, info, warn};
 use zbus::Connection;
-use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+use zbus::fdo::DBusProxy;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, Value};
 
 use self::dbus::access_point::AccessPointProxy;
+use self::dbus::settings::SettingsConnectionProxy;
 use self::dbus::statistics::StatisticsProxy;
 use self::device::DeviceState;
 use self::nm::NetworkManager;
 
 pub mod access_point;
 pub mod active_connection;
+mod agent;
 pub mod dbus;
 pub mod device;
 pub mod nm;
@@ -23,11 +31,76 @@ pub struct AccessPoint {
     pub strength: u8,
     pub state: DeviceState,
     pub public: bool,
+    pub security: Security,
+    /// The saved connection to the network, if it was joined before.
+    pub connection: Option<OwnedObjectPath>,
     pub working: bool,
     pub path: ObjectPath<'static>,
     pub device_path: ObjectPath<'static>,
 }
 
+/// How an access point is secured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Security {
+    Open,
+    Wep,
+    /// WPA or WPA2 with a password.
+    Wpa,
+    /// WPA3 with a password.
+    Sae,
+    /// 802.1X, which takes more than a password.
+    Enterprise,
+}
+
+impl Security {
+    /// From the `Flags`, `WpaFlags` and `RsnFlags` of the access point.
+    pub fn new(flags: u32, wpa_flags: u32, rsn_flags: u32) -> Self {
+        const PRIVACY: u32 = 0x1;
+        const KEY_MGMT_PSK: u32 = 0x100;
+        const KEY_MGMT_802_1X: u32 = 0x200;
+        const KEY_MGMT_SAE: u32 = 0x400;
+        const KEY_MGMT_EAP_SUITE_B_192: u32 = 0x2000;
+
+        let key_mgmt = wpa_flags | rsn_flags;
+        if key_mgmt & (KEY_MGMT_802_1X | KEY_MGMT_EAP_SUITE_B_192) != 0 {
+            Self::Enterprise
+        } else if key_mgmt & KEY_MGMT_PSK != 0 {
+            Self::Wpa
+        } else if key_mgmt & KEY_MGMT_SAE != 0 {
+            Self::Sae
+        } else if flags & PRIVACY != 0 && key_mgmt == 0 {
+            Self::Wep
+        } else {
+            Self::Open
+        }
+    }
+
+    pub fn is_secured(self) -> bool {
+        self != Self::Open
+    }
+
+    /// The `802-11-wireless-security` settings to join with `password`.
+    fn settings(self, password: &str) -> anyhow::Result<Option<HashMap<&'static str, Value<'_>>>> {
+        let settings = match self {
+            Self::Open => return Ok(None),
+            Self::Wep => HashMap::from([
+                ("key-mgmt", Value::from("none")),
+                ("wep-key0", Value::from(password)),
+            ]),
+            Self::Wpa => HashMap::from([
+                ("key-mgmt", Value::from("wpa-psk")),
+                ("psk", Value::from(password)),
+            ]),
+            Self::Sae => HashMap::from([
+                ("key-mgmt", Value::from("sae")),
+                ("psk", Value::from(password)),
+            ]),
+            Self::Enterprise => bail!("joining enterprise networks isn't supported"),
+        };
+        Ok(Some(settings))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ActiveConnectionInfo {
     Wired {
@@ -126,7 +199,7 @@ impl NetworkData {
         let wifi_enabled = nm.wireless_enabled().await?;
         let connectivity = nm.connectivity().await?.into();
         let active_connections = nm.active_connections().await?;
-        let wireless_access_points = nm.wireless_access_points().await?;
+        let wireless_access_points = nm.wireless_access_points(true).await?;
         let network_statistics = nm.network_statistics().await?;
 
         Ok(Self {
@@ -139,40 +212,104 @@ impl NetworkData {
     }
 }
 
+/// What a connection needs to come up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretKind {
+    /// The password of a WPA network.
+    Wifi,
+    Wep,
+    /// The password of `identity` on an 802.1X network.
+    Enterprise {
+        identity: String,
+    },
+    Vpn,
+    /// The password of a mobile broadband or PPPoE connection.
+    Password,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretRequest {
+    pub id: u64,
+    /// The id of the connection, which is the SSID for Wi-Fi networks joined from mgs.
+    pub name: String,
+    pub kind: SecretKind,
+    /// Whether the secret given before was wrong.
+    pub retry: bool,
+    /// The path of the connection.
+    connection: String,
+    /// The setting holding the secret.
+    setting: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretReply {
+    Secret(String),
+    Cancel,
+}
+
+/// The id of the request the agent waits on, and where its answer goes.
+type PendingReply = Option<(u64, oneshot::Sender<SecretReply>)>;
+
 #[derive(Debug, Clone)]
 pub struct Subscriber {
     data: Mutable<NetworkData>,
+    /// What the secret agent waits for the user to answer.
+    request: Mutable<Option<SecretRequest>>,
+    /// Dropping the sender cancels the request.
+    reply: Arc<Mutex<PendingReply>>,
+    next_request: Arc<AtomicU64>,
     conn: Connection,
-    refresh_rate_ms: u32,
+    /// Shared with the task, so that devices it sets up get the latest rate.
+    refresh_rate_ms: Arc<AtomicU32>,
 }
 
 impl Subscriber {
     pub async fn new() -> anyhow::Result<Self> {
+        Self::with_refresh_rate(1000).await
+    }
+
+    /// Creates a subscriber polling the device statistics every `refresh_rate_ms`.
+    pub async fn with_refresh_rate(refresh_rate_ms: u32) -> anyhow::Result<Self> {
         let conn = Connection::system().await?;
-        let data = Mutable::new(NetworkData::init(&conn).await?);
+        let subscriber = Self {
+            data: Mutable::new(NetworkData::init(&conn).await?),
+            request: Mutable::new(None),
+            reply: Arc::new(Mutex::new(None)),
+            next_request: Arc::new(AtomicU64::new(1)),
+            conn,
+            refresh_rate_ms: Arc::new(AtomicU32::new(refresh_rate_ms)),
+        };
+        // saved connections still come up without it
+        if let Err(err) = agent::start(subscriber.clone()).await {
+            warn!("failed to register the network secret agent: {err:#}");
+        }
 
-        let data_for_task = data.clone();
-        let conn_for_task = conn.clone();
+        let subscriber_for_task = subscriber.clone();
         tokio::spawn(async move {
-            let subscriber = Self {
-                data: data_for_task,
-                conn: conn_for_task,
-                refresh_rate_ms: 1000,
-            };
-            if let Err(e) = subscriber.run().await {
+            if let Err(e) = subscriber_for_task.run().await {
                 error!("Error in NetworkManager subscriber: {:?}", e);
             }
         });
 
-        Ok(Self {
-            data,
-            conn,
-            refresh_rate_ms: 1000,
-        })
+        Ok(subscriber)
     }
 
-    pub fn set_refresh_rate_ms(&mut self, refresh_rate_ms: u32) {
-        self.refresh_rate_ms = refresh_rate_ms;
+    /// Changes how often NetworkManager refreshes the device statistics.
+    pub async fn set_refresh_rate_ms(&self, refresh_rate_ms: u32) -> anyhow::Result<()> {
+        self.refresh_rate_ms
+            .store(refresh_rate_ms, Ordering::Relaxed);
+
+        let nm = NetworkManager::new(&self.conn).await?;
+        for device in nm.devices().await? {
+            StatisticsProxy::builder(&self.conn)
+                .path(device)?
+                .build()
+                .await?
+                .set_refresh_rate_ms(refresh_rate_ms)
+                .await?;
+        }
+
+        Ok(())
     }
 
     async fn run(&self) -> anyhow::Result<()> {
@@ -211,7 +348,21 @@ impl Subscriber {
             })
             .boxed();
 
-        let wireless_ac = nm.wireless_access_points().await?;
+        let restarts = DBusProxy::new(&self.conn)
+            .await?
+            .receive_name_owner_changed_with_args(&[(0, "org.freedesktop.NetworkManager")])
+            .await?
+            .then(|signal| async move {
+                let restarted = signal.args().is_ok_and(|args| args.new_owner().is_some());
+                if restarted {
+                    if let Err(err) = agent::register(&self.conn).await {
+                        warn!("failed to register the network secret agent: {err:#}");
+                    }
+                }
+            })
+            .boxed();
+
+        let wireless_ac = nm.wireless_access_points(false).await?;
 
         let mut strength_changes = Vec::with_capacity(wireless_ac.len());
         for ap in wireless_ac {
@@ -266,9 +417,8 @@ impl Subscriber {
                 .build()
                 .await?;
             statstics_proxy
-                .set_refresh_rate_ms(self.refresh_rate_ms)
-                .await
-                .unwrap();
+                .set_refresh_rate_ms(self.refresh_rate_ms.load(Ordering::Relaxed))
+                .await?;
 
             let device_clone = device_string.clone();
             statistics_changes.push(
@@ -320,6 +470,7 @@ impl Subscriber {
             wireless_enabled,
             connectivity_changed,
             active_connections,
+            restarts,
         ]);
 
         for stream in strength_changes {
@@ -335,7 +486,117 @@ impl Subscriber {
         Ok(())
     }
 
+    /// Looks for access points again.
+    pub async fn scan(&self) -> anyhow::Result<()> {
+        self.refresh_access_points(true).await
+    }
+
+    async fn refresh_access_points(&self, scan: bool) -> anyhow::Result<()> {
+        let nm = NetworkManager::new(&self.conn).await?;
+        let access_points = nm.wireless_access_points(scan).await?;
+        self.data.lock_mut().wireless_access_points = access_points;
+        Ok(())
+    }
+
+    /// Joins the network of `access_point`, with its saved connection if there is one,
+    /// otherwise saving a new one with `password`.
+    pub async fn connect(
+        &self,
+        access_point: &AccessPoint,
+        password: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let nm = NetworkManager::new(&self.conn).await?;
+        if let Some(connection) = &access_point.connection {
+            nm.activate_connection(connection, &access_point.device_path, &access_point.path)
+                .await?;
+            return Ok(());
+        }
+
+        let mut settings = HashMap::from([(
+            "802-11-wireless",
+            HashMap::from([("ssid", Value::from(access_point.ssid.as_bytes()))]),
+        )]);
+        let security = access_point
+            .security
+            .settings(password.unwrap_or_default())?;
+        if let Some(security) = security {
+            settings.insert("802-11-wireless-security", security);
+        }
+        // NetworkManager fills in the rest from the access point
+        nm.add_and_activate_connection(settings, &access_point.device_path, &access_point.path)
+            .await?;
+        self.refresh_access_points(false).await
+    }
+
+    /// Deletes the saved connection to the network of `access_point`.
+    pub async fn forget(&self, access_point: &AccessPoint) -> anyhow::Result<()> {
+        let Some(connection) = &access_point.connection else {
+            return Ok(());
+        };
+        SettingsConnectionProxy::builder(&self.conn)
+            .path(connection)?
+            .build()
+            .await?
+            .delete()
+            .await?;
+        self.refresh_access_points(false).await
+    }
+
+    /// Asks the user for the secret of `setting` in the connection at `path`, and waits for
+    /// their answer, `None` when the request is canceled or replaced.
+    async fn ask_secret(
+        &self,
+        path: &ObjectPath<'_>,
+        setting: &str,
+        name: String,
+        kind: SecretKind,
+        retry: bool,
+    ) -> Option<SecretReply> {
+        let (sender, receiver) = oneshot::channel();
+        let id = self.next_request.fetch_add(1, Ordering::Relaxed);
+        *self.reply.lock().unwrap() = Some((id, sender));
+        self.request.set(Some(SecretRequest {
+            id,
+            name,
+            kind,
+            retry,
+            connection: path.to_string(),
+            setting: setting.to_string(),
+        }));
+
+        let reply = receiver.await.ok();
+        self.request.lock_mut().take_if(|request| request.id == id);
+        reply
+    }
+
+    fn cancel_secret(&self, path: &ObjectPath<'_>, setting: &str) {
+        let mut request = self.request.lock_mut();
+        let canceled = request
+            .take_if(|request| request.connection == path.as_str() && request.setting == setting);
+        if let Some(canceled) = canceled {
+            self.reply
+                .lock()
+                .unwrap()
+                .take_if(|(id, _)| *id == canceled.id);
+        }
+    }
+
+    /// Answers the request `id` of the secret agent.
+    pub fn reply_secret(&self, id: u64, reply: SecretReply) {
+        let mut pending = self.reply.lock().unwrap();
+        if let Some((_, sender)) = pending.take_if(|(pending, _)| *pending == id) {
+            sender.send(reply).ok();
+        }
+        drop(pending);
+        self.request.lock_mut().take_if(|request| request.id == id);
+    }
+
     pub fn subscribe(&self) -> MutableSignalCloned<NetworkData> {
         self.data.signal_cloned()
     }
+
+    /// The request of the secret agent waiting for the user, if any.
+    pub fn subscribe_secret_request(&self) -> MutableSignalCloned<Option<SecretRequest>> {
+        self.request.signal_cloned()
+    }
 }
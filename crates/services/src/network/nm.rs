@@ -13,9 +13,10 @@ use super::dbus::device::DeviceProxy;
 use super::dbus::device::wired::WiredDeviceProxy;
 use super::dbus::device::wireless::WirelessDeviceProxy;
 use super::dbus::nm::NetworkManagerProxy;
+use super::dbus::settings::{SettingsConnectionProxy, SettingsProxy};
 use super::dbus::statistics::StatisticsProxy;
 use super::device::{DeviceState, DeviceType};
-use super::{AccessPoint, ActiveConnectionInfo, NetworkStatistics};
+use super::{AccessPoint, ActiveConnectionInfo, NetworkStatistics, Security};
 
 #[derive(Debug)]
 pub struct NetworkManager<'a>(NetworkManagerProxy<'a>);
@@ -175,8 +176,36 @@ impl<'a> NetworkManager<'a> {
         Ok(network_statistics)
     }
 
-    pub async fn wireless_access_points(&self) -> Result<Vec<AccessPoint>> {
+    /// The saved Wi-Fi connections, by SSID.
+    pub async fn wifi_connections(&self) -> Result<HashMap<String, OwnedObjectPath>> {
+        let settings = SettingsProxy::new(self.inner().connection()).await?;
+        let mut connections = HashMap::new();
+        for path in settings.list_connections().await? {
+            let connection = SettingsConnectionProxy::builder(self.inner().connection())
+                .path(&path)?
+                .build()
+                .await?;
+            let Ok(settings) = connection.get_settings().await else {
+                continue;
+            };
+            let ssid = settings
+                .get("802-11-wireless")
+                .and_then(|wireless| wireless.get("ssid"))
+                .and_then(|ssid| ssid.try_clone().ok())
+                .and_then(|ssid| Vec::<u8>::try_from(ssid).ok());
+            if let Some(ssid) = ssid {
+                connections.insert(String::from_utf8_lossy(&ssid).into_owned(), path);
+            }
+        }
+
+        Ok(connections)
+    }
+
+    /// The access points in range, the strongest first, asking the devices to look for
+    /// them again first when `scan` is set.
+    pub async fn wireless_access_points(&self, scan: bool) -> Result<Vec<AccessPoint>> {
         let wireless_devices = self.wireless_devices().await?;
+        let connections = &self.wifi_connections().await.unwrap_or_default();
         let wireless_ap_futures: Vec<_> = wireless_devices
             .iter()
             .map(|path| async move {
@@ -188,11 +217,13 @@ impl<'a> NetworkManager<'a> {
                     .path(path)?
                     .build()
                     .await?;
-                wireless_device.request_scan(HashMap::new()).await?;
-                let mut scan_changed = wireless_device.receive_last_scan_changed().await;
-                if let Some(t) = scan_changed.next().await {
-                    if let Ok(-1) = t.get().await {
-                        return Ok(Default::default());
+                // scanning again right after a scan is refused, the last results are fine then
+                if scan && wireless_device.request_scan(HashMap::new()).await.is_ok() {
+                    let mut scan_changed = wireless_device.receive_last_scan_changed().await;
+                    if let Some(t) = scan_changed.next().await {
+                        if let Ok(-1) = t.get().await {
+                            return Ok(Default::default());
+                        }
                     }
                 }
                 let access_points = wireless_device.get_access_points().await?;
@@ -210,7 +241,13 @@ impl<'a> NetworkManager<'a> {
                         .await?;
 
                     let ssid = String::from_utf8_lossy(&ap_proxy.ssid().await?).into_owned();
-                    let public = ap_proxy.flags().await.unwrap_or_default() == 0;
+                    let flags = ap_proxy.flags().await.unwrap_or_default();
+                    let public = flags == 0;
+                    let security = Security::new(
+                        flags,
+                        ap_proxy.wpa_flags().await.unwrap_or_default(),
+                        ap_proxy.rsn_flags().await.unwrap_or_default(),
+                    );
                     let strength = ap_proxy.strength().await?;
                     if let Some(access_point) = aps.get(&ssid) {
                         if access_point.strength > strength {
@@ -218,6 +255,7 @@ impl<'a> NetworkManager<'a> {
                         }
                     }
 
+                    let connection = connections.get(&ssid).cloned();
                     aps.insert(
                         ssid.clone(),
                         AccessPoint {
@@ -225,6 +263,8 @@ impl<'a> NetworkManager<'a> {
                             strength,
                             state,
                             public,
+                            security,
+                            connection,
                             working: false,
                             path: ap_proxy.inner().path().to_owned(),
                             device_path: device_proxy.inner().path().to_owned(),
@@ -0,0 +1,101 @@
+//! Our `org.freedesktop.Notifications`, the other side of `notify-send`.
+
+use std::collections::HashMap;
+
+use anyhow::bail;
+use zbus::fdo::{self, DBusProxy};
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::OwnedValue;
+use zbus::{Connection, interface};
+
+use super::{CloseReason, Subscriber};
+
+const NAME: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+
+struct Daemon {
+    subscriber: Subscriber,
+}
+
+#[interface(name = "org.freedesktop.Notifications")]
+impl Daemon {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: Vec<&str>,
+        hints: HashMap<String, OwnedValue>,
+        expire_timeout: i32,
+    ) -> u32 {
+        self.subscriber.notify(
+            app_name,
+            replaces_id,
+            app_icon,
+            summary,
+            body,
+            &actions,
+            &hints,
+            expire_timeout,
+        )
+    }
+
+    async fn close_notification(&self, id: u32) -> fdo::Result<()> {
+        self.subscriber
+            .close(id, CloseReason::Closed)
+            .await
+            .map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    fn get_capabilities(&self) -> Vec<&str> {
+        vec!["actions", "body", "icon-static"]
+    }
+
+    fn get_server_information(&self) -> (&str, &str, &str, &str) {
+        ("mgs", "mgs", env!("CARGO_PKG_VERSION"), "1.2")
+    }
+
+    #[zbus(signal)]
+    async fn notification_closed(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        reason: u32,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn action_invoked(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        action_key: &str,
+    ) -> zbus::Result<()>;
+}
+
+/// Serves the daemon on the connection of `subscriber`, failing when another one runs.
+pub async fn start(subscriber: Subscriber) -> anyhow::Result<()> {
+    let conn = subscriber.conn.clone();
+    conn.object_server().at(PATH, Daemon { subscriber }).await?;
+
+    let reply = DBusProxy::new(&conn)
+        .await?
+        .request_name(NAME.try_into()?, fdo::RequestNameFlags::DoNotQueue.into())
+        .await?;
+    if reply != fdo::RequestNameReply::PrimaryOwner {
+        conn.object_server().remove::<Daemon, _>(PATH).await?;
+        bail!("another notification daemon owns {NAME}");
+    }
+
+    Ok(())
+}
+
+pub async fn closed(conn: &Connection, id: u32, reason: CloseReason) -> zbus::Result<()> {
+    let emitter = SignalEmitter::new(conn, PATH)?;
+    Daemon::notification_closed(&emitter, id, reason as u32).await
+}
+
+pub async fn action_invoked(conn: &Connection, id: u32, key: &str) -> zbus::Result<()> {
+    let emitter = SignalEmitter::new(conn, PATH)?;
+    Daemon::action_invoked(&emitter, id, key).await
+}
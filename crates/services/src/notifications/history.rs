@@ -0,0 +1,58 @@
+//! The notification history, kept across restarts.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{Image, Notification};
+
+const STATE_DIR: &str = "mgs";
+const STATE_FILE: &str = "notifications.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedState {
+    pub history: Vec<Notification>,
+    pub unread: usize,
+    pub do_not_disturb: bool,
+}
+
+/// `$XDG_STATE_HOME/mgs/notifications.json`, falling back to
+/// `~/.local/state/mgs/notifications.json`.
+fn path() -> Option<PathBuf> {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state"))
+        })?;
+
+    Some(state_home.join(STATE_DIR).join(STATE_FILE))
+}
+
+/// Loads the saved history, starting from scratch if there is none or it's unreadable.
+pub fn load() -> SavedState {
+    path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|source| serde_json::from_str(&source).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(mut state: SavedState) -> Result<()> {
+    let path = path().context("no state directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+
+    // raw pixels would bloat the file, paths to images are cheap to keep
+    for notification in &mut state.history {
+        if matches!(notification.image, Some(Image::Data(_))) {
+            notification.image = None;
+        }
+    }
+
+    std::fs::write(&path, serde_json::to_string(&state)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
@@ -0,0 +1,499 @@
+mod daemon;
+mod history;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures_signals::signal::{Mutable, MutableSignalCloned};
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Handle;
+use tokio::task::AbortHandle;
+use tracing::{debug, info, warn};
+use zbus::Connection;
+use zbus::zvariant::OwnedValue;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    /// Stays until dismissed.
+    Critical,
+}
+
+impl From<u8> for Urgency {
+    fn from(urgency: u8) -> Self {
+        match urgency {
+            0 => Urgency::Low,
+            2 => Urgency::Critical,
+            _ => Urgency::Normal,
+        }
+    }
+}
+
+/// Why a notification went away, as told to the app that sent it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    Expired = 1,
+    Dismissed = 2,
+    /// Closed by the app with `CloseNotification`.
+    Closed = 3,
+    Undefined = 4,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Action {
+    pub key: String,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    /// ARGB32 pixels, in network byte order.
+    pub data: Vec<u8>,
+}
+
+impl ImageData {
+    /// Reads an `image-data` hint, RGB or RGBA rows of `rowstride` bytes.
+    fn from_hint(value: &OwnedValue) -> Option<Self> {
+        let (width, height, rowstride, has_alpha, bits_per_sample, channels, data) =
+            <(i32, i32, i32, bool, i32, i32, Vec<u8>)>::try_from(&**value).ok()?;
+
+        let width = u32::try_from(width).ok().filter(|width| *width > 0)?;
+        let height = u32::try_from(height).ok().filter(|height| *height > 0)?;
+        let rowstride = usize::try_from(rowstride).ok()?;
+        let channels = usize::try_from(channels).ok()?;
+        if bits_per_sample != 8 || channels != if has_alpha { 4 } else { 3 } {
+            return None;
+        }
+        // the last row may be cut short of the full stride
+        let row_len = width as usize * channels;
+        if rowstride < row_len || data.len() < rowstride * (height as usize - 1) + row_len {
+            return None;
+        }
+
+        let mut argb = Vec::with_capacity(width as usize * height as usize * 4);
+        for row in 0..height as usize {
+            let start = row * rowstride;
+            for pixel in data[start..start + row_len].chunks_exact(channels) {
+                let alpha = if has_alpha { pixel[3] } else { 0xff };
+                argb.extend_from_slice(&[alpha, pixel[0], pixel[1], pixel[2]]);
+            }
+        }
+
+        Some(Self {
+            width,
+            height,
+            data: argb,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Image {
+    Data(ImageData),
+    /// A file path or `file://` URI, or an icon name.
+    Path(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: u32,
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+    pub actions: Vec<Action>,
+    /// The `image-data` or `image-path` hint, or else the app icon.
+    pub image: Option<Image>,
+    pub urgency: Urgency,
+    pub category: Option<String>,
+    /// Id of the desktop entry of the app.
+    pub desktop_entry: Option<String>,
+    /// Whether the notification should go away without being kept around.
+    pub transient: bool,
+    /// Whether invoking an action leaves the notification in place.
+    pub resident: bool,
+    /// How long the notification stays, `None` meaning until dismissed.
+    pub timeout: Option<Duration>,
+    /// Unix timestamp of when the notification came in, in seconds.
+    pub time: u64,
+    /// Whether the notification came in during do not disturb, keeping it from popping up.
+    pub silent: bool,
+}
+
+impl Notification {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        id: u32,
+        app_name: &str,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: &HashMap<String, OwnedValue>,
+        expire_timeout: i32,
+        default_timeout: Duration,
+    ) -> Self {
+        // some apps use the older names of the hints
+        let hint = |names: &[&str]| names.iter().find_map(|name| hints.get(*name));
+        let string = |names: &[&str]| {
+            hint(names)
+                .and_then(|value| <&str>::try_from(value).ok())
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        // booleans and bytes are sometimes sent as other types
+        let integer = |names: &[&str]| {
+            let value = hint(names)?;
+            u8::try_from(value)
+                .map(i64::from)
+                .or_else(|_| i32::try_from(value).map(i64::from))
+                .or_else(|_| u32::try_from(value).map(i64::from))
+                .or_else(|_| bool::try_from(value).map(i64::from))
+                .ok()
+        };
+
+        let image = hint(&["image-data", "image_data", "icon_data"])
+            .and_then(ImageData::from_hint)
+            .map(Image::Data)
+            .or_else(|| string(&["image-path", "image_path"]).map(Image::Path))
+            .or_else(|| (!app_icon.is_empty()).then(|| Image::Path(app_icon.to_string())));
+        let urgency = integer(&["urgency"])
+            .and_then(|urgency| u8::try_from(urgency).ok())
+            .map(Urgency::from)
+            .unwrap_or_default();
+        let timeout = match expire_timeout {
+            0 => None,
+            _ if urgency == Urgency::Critical => None,
+            timeout if timeout < 0 => Some(default_timeout),
+            timeout => Some(Duration::from_millis(timeout as u64)),
+        };
+
+        Self {
+            id,
+            app_name: app_name.to_string(),
+            summary: summary.to_string(),
+            body: body.to_string(),
+            actions: actions
+                .chunks_exact(2)
+                .map(|pair| Action {
+                    key: pair[0].to_string(),
+                    label: pair[1].to_string(),
+                })
+                .collect(),
+            image,
+            urgency,
+            category: string(&["category"]),
+            desktop_entry: string(&["desktop-entry"]),
+            transient: integer(&["transient"]).is_some_and(|transient| transient != 0),
+            resident: integer(&["resident"]).is_some_and(|resident| resident != 0),
+            timeout,
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or_default(),
+            silent: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NotificationsData {
+    /// Notifications that didn't expire and weren't closed yet, oldest first.
+    pub active: Vec<Notification>,
+    /// Past and active notifications, oldest first, transient ones aside.
+    pub history: Vec<Notification>,
+    /// Notifications added to the history since it was last looked at.
+    pub unread: usize,
+    /// Whether only critical notifications pop up.
+    pub do_not_disturb: bool,
+}
+
+/// Serves `org.freedesktop.Notifications`, keeping the notifications until they expire
+/// or are closed, and a history of them.
+#[derive(Debug, Clone)]
+pub struct Subscriber {
+    data: Mutable<NotificationsData>,
+    /// How long notifications stay when their app leaves it to us.
+    default_timeout: Mutable<Duration>,
+    history_limit: Mutable<usize>,
+    next_id: Arc<AtomicU32>,
+    expiries: Arc<Mutex<HashMap<u32, AbortHandle>>>,
+    saving: Arc<Mutex<()>>,
+    conn: Connection,
+    /// Notifications come in on the executor of zbus, which timers can't run on.
+    runtime: Handle,
+}
+
+impl Subscriber {
+    pub async fn new() -> anyhow::Result<Self> {
+        let conn = Connection::session().await?;
+        let saved = tokio::task::spawn_blocking(history::load).await?;
+        // ids of the saved notifications aren't reused, as actions refer to them
+        let next_id = saved
+            .history
+            .iter()
+            .map(|notification| notification.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        let subscriber = Self {
+            data: Mutable::new(NotificationsData {
+                active: Vec::new(),
+                history: saved.history,
+                unread: saved.unread,
+                do_not_disturb: saved.do_not_disturb,
+            }),
+            default_timeout: Mutable::new(Duration::from_secs(5)),
+            history_limit: Mutable::new(100),
+            next_id: Arc::new(AtomicU32::new(next_id)),
+            expiries: Arc::new(Mutex::new(HashMap::new())),
+            saving: Arc::new(Mutex::new(())),
+            conn,
+            runtime: Handle::current(),
+        };
+        daemon::start(subscriber.clone()).await?;
+        info!("Notification daemon start");
+
+        Ok(subscriber)
+    }
+
+    /// Applies to the notifications received from now on.
+    pub fn set_default_timeout(&self, timeout: Duration) {
+        self.default_timeout.set(timeout);
+    }
+
+    /// Keeps at most `limit` notifications in the history, dropping the oldest ones.
+    pub fn set_history_limit(&self, limit: usize) {
+        self.history_limit.set(limit);
+        let changed = {
+            let mut data = self.data.lock_mut();
+            let excess = data.history.len().saturating_sub(limit);
+            data.history.drain(..excess);
+            data.unread = data.unread.min(data.history.len());
+            excess > 0
+        };
+        if changed {
+            self.save();
+        }
+    }
+
+    /// Adds a notification, or replaces the one with id `replaces_id` if it's still
+    /// around, and returns its id.
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: &HashMap<String, OwnedValue>,
+        expire_timeout: i32,
+    ) -> u32 {
+        let replaces = replaces_id != 0
+            && self
+                .data
+                .lock_ref()
+                .active
+                .iter()
+                .any(|notification| notification.id == replaces_id);
+        let id = if replaces {
+            replaces_id
+        } else {
+            self.next_id.fetch_add(1, Ordering::Relaxed)
+        };
+
+        let mut notification = Notification::new(
+            id,
+            app_name,
+            app_icon,
+            summary,
+            body,
+            actions,
+            hints,
+            expire_timeout,
+            self.default_timeout.get(),
+        );
+        debug!("notification {id} from {app_name}: {summary}");
+
+        let timeout = notification.timeout;
+        {
+            let mut data = self.data.lock_mut();
+            notification.silent = data.do_not_disturb && notification.urgency != Urgency::Critical;
+
+            if !notification.transient {
+                match data.history.iter_mut().find(|existing| existing.id == id) {
+                    Some(existing) => *existing = notification.clone(),
+                    None => {
+                        data.history.push(notification.clone());
+                        data.unread += 1;
+                        let excess = data.history.len().saturating_sub(self.history_limit.get());
+                        data.history.drain(..excess);
+                        data.unread = data.unread.min(data.history.len());
+                    }
+                }
+            }
+            match data.active.iter_mut().find(|existing| existing.id == id) {
+                Some(existing) => *existing = notification,
+                None => data.active.push(notification),
+            }
+        }
+        // only once it's there, a short timeout could close it first otherwise
+        self.expire(id, timeout);
+        self.save();
+
+        id
+    }
+
+    /// Closes the notification once `timeout` is over, replacing its previous expiry.
+    fn expire(&self, id: u32, timeout: Option<Duration>) {
+        let mut expiries = self.expiries.lock().unwrap();
+        if let Some(expiry) = expiries.remove(&id) {
+            expiry.abort();
+        }
+        let Some(timeout) = timeout else {
+            return;
+        };
+
+        let subscriber = self.clone();
+        let expiry = self.runtime.spawn(async move {
+            tokio::time::sleep(timeout).await;
+            subscriber.expiries.lock().unwrap().remove(&id);
+            subscriber.close(id, CloseReason::Expired).await.ok();
+        });
+        expiries.insert(id, expiry.abort_handle());
+    }
+
+    /// Takes the notification down, leaving it in the history.
+    async fn close(&self, id: u32, reason: CloseReason) -> zbus::Result<()> {
+        let removed = {
+            let mut data = self.data.lock_mut();
+            let len = data.active.len();
+            data.active.retain(|notification| notification.id != id);
+            data.active.len() != len
+        };
+        if !removed {
+            return Ok(());
+        }
+
+        if let Some(expiry) = self.expiries.lock().unwrap().remove(&id) {
+            expiry.abort();
+        }
+        daemon::closed(&self.conn, id, reason).await
+    }
+
+    /// Closes the notification on behalf of the user, leaving it in the history.
+    pub async fn dismiss(&self, id: u32) -> anyhow::Result<()> {
+        self.close(id, CloseReason::Dismissed).await?;
+        Ok(())
+    }
+
+    /// Closes the notification and removes it from the history.
+    pub async fn remove(&self, id: u32) -> anyhow::Result<()> {
+        self.close(id, CloseReason::Dismissed).await?;
+        {
+            let mut data = self.data.lock_mut();
+            data.history.retain(|notification| notification.id != id);
+            data.unread = data.unread.min(data.history.len());
+        }
+        self.save();
+        Ok(())
+    }
+
+    /// Closes every notification and empties the history.
+    pub async fn clear(&self) -> anyhow::Result<()> {
+        let ids: Vec<_> = {
+            let mut data = self.data.lock_mut();
+            data.history.clear();
+            data.unread = 0;
+            data.active
+                .iter()
+                .map(|notification| notification.id)
+                .collect()
+        };
+        self.save();
+
+        for id in ids {
+            self.close(id, CloseReason::Dismissed).await?;
+        }
+        Ok(())
+    }
+
+    /// Tells the app the action `key` was picked, removing the notification unless it's
+    /// resident.
+    pub async fn invoke_action(&self, id: u32, key: &str) -> anyhow::Result<()> {
+        let resident = {
+            let data = self.data.lock_ref();
+            data.active
+                .iter()
+                .chain(&data.history)
+                .find(|notification| notification.id == id)
+                .map(|notification| notification.resident)
+        };
+        let Some(resident) = resident else {
+            return Ok(());
+        };
+
+        daemon::action_invoked(&self.conn, id, key).await?;
+        if !resident {
+            self.remove(id).await?;
+        }
+        Ok(())
+    }
+
+    /// Marks the history as seen.
+    pub fn mark_read(&self) {
+        if self.data.lock_ref().unread == 0 {
+            return;
+        }
+        self.data.lock_mut().unread = 0;
+        self.save();
+    }
+
+    pub fn set_do_not_disturb(&self, do_not_disturb: bool) {
+        if self.data.lock_ref().do_not_disturb == do_not_disturb {
+            return;
+        }
+        self.data.lock_mut().do_not_disturb = do_not_disturb;
+        info!(
+            "do not disturb {}",
+            if do_not_disturb { "on" } else { "off" }
+        );
+        self.save();
+    }
+
+    /// Writes the history out, off the executor as it hits the disk.
+    fn save(&self) {
+        let subscriber = self.clone();
+        self.runtime.spawn_blocking(move || {
+            // saves run one at a time, each one writing what's current when it gets its turn
+            let _saving = subscriber.saving.lock().unwrap();
+            let state = {
+                let data = subscriber.data.lock_ref();
+                history::SavedState {
+                    history: data.history.clone(),
+                    unread: data.unread,
+                    do_not_disturb: data.do_not_disturb,
+                }
+            };
+            if let Err(err) = history::save(state) {
+                warn!("failed to save notification history: {err:#}");
+            }
+        });
+    }
+
+    pub fn get(&self) -> NotificationsData {
+        self.data.get_cloned()
+    }
+
+    pub fn subscribe(&self) -> MutableSignalCloned<NotificationsData> {
+        self.data.signal_cloned()
+    }
+}
@@ -38,6 +38,10 @@ impl SysInfoSubscriber {
             let mut ticker = interval(Duration::from_millis(this.refresh_rate_ms));
             loop {
                 ticker.tick().await;
+                // the task holds the last reference once every subscriber is dropped
+                if Arc::strong_count(&this) == 1 {
+                    break;
+                }
                 sys.refresh_cpu_all();
                 sys.refresh_memory();
                 let cpu_usage =
@@ -0,0 +1,253 @@
+//! Client side of `com.canonical.dbusmenu`, the menus tray items export.
+
+use std::sync::Arc;
+
+use futures_signals::signal::{Mutable, MutableSignalCloned};
+use futures_util::StreamExt;
+use futures_util::stream::select;
+use tokio::task::AbortHandle;
+use tracing::debug;
+use zbus::Connection;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{OwnedValue, Value};
+
+use super::dbus::{DBusMenuProxy, LayoutNode};
+
+/// The id of the root item, whose children make up the menu.
+pub const ROOT: i32 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToggleKind {
+    Checkmark,
+    Radio,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MenuItem {
+    pub id: i32,
+    /// The label without its mnemonic underscores.
+    pub label: String,
+    pub separator: bool,
+    pub enabled: bool,
+    pub visible: bool,
+    pub icon_name: Option<String>,
+    pub toggle: Option<ToggleKind>,
+    pub checked: bool,
+    /// Whether the item opens a submenu, which apps may only fill in once it is about
+    /// to be shown.
+    pub submenu: bool,
+    pub children: Vec<MenuItem>,
+}
+
+impl MenuItem {
+    fn new(id: i32) -> Self {
+        Self {
+            id,
+            label: String::new(),
+            separator: false,
+            enabled: true,
+            visible: true,
+            icon_name: None,
+            toggle: None,
+            checked: false,
+            submenu: false,
+            children: Vec::new(),
+        }
+    }
+
+    fn from_layout((id, properties, children): LayoutNode) -> Self {
+        let mut item = Self::new(id);
+        for (name, value) in &properties {
+            item.set(name, Some(value));
+        }
+        item.children = children
+            .iter()
+            .filter_map(|child| LayoutNode::try_from(&**child).ok())
+            .map(Self::from_layout)
+            .collect();
+        item.submenu |= !item.children.is_empty();
+        item
+    }
+
+    /// Sets the property `name`, or resets it to its default without a `value`.
+    fn set(&mut self, name: &str, value: Option<&OwnedValue>) {
+        let string = || value.and_then(|value| <&str>::try_from(value).ok());
+        let flag = |default| {
+            value
+                .and_then(|value| bool::try_from(value).ok())
+                .unwrap_or(default)
+        };
+
+        match name {
+            "label" => self.label = strip_mnemonics(string().unwrap_or_default()),
+            "type" => self.separator = string() == Some("separator"),
+            "enabled" => self.enabled = flag(true),
+            "visible" => self.visible = flag(true),
+            "icon-name" => {
+                self.icon_name = string().filter(|name| !name.is_empty()).map(str::to_string)
+            }
+            "toggle-type" => {
+                self.toggle = match string() {
+                    Some("checkmark") => Some(ToggleKind::Checkmark),
+                    Some("radio") => Some(ToggleKind::Radio),
+                    _ => None,
+                }
+            }
+            "toggle-state" => {
+                self.checked = value.and_then(|value| i32::try_from(value).ok()) == Some(1)
+            }
+            "children-display" => self.submenu = string() == Some("submenu"),
+            _ => {}
+        }
+    }
+
+    fn find_mut(&mut self, id: i32) -> Option<&mut MenuItem> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children
+            .iter_mut()
+            .find_map(|child| child.find_mut(id))
+    }
+}
+
+/// `_` marks the access key of a label, `__` being a literal underscore.
+fn strip_mnemonics(label: &str) -> String {
+    let mut stripped = String::with_capacity(label.len());
+    let mut chars = label.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '_' => stripped.extend(chars.next()),
+            c => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+#[derive(Debug)]
+struct Watch(AbortHandle);
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// The menu of a tray item, kept up to date as long as a clone of it is alive.
+#[derive(Debug, Clone)]
+pub struct Menu {
+    root: Mutable<MenuItem>,
+    proxy: DBusMenuProxy<'static>,
+    _watch: Arc<Watch>,
+}
+
+impl Menu {
+    pub(super) async fn new(
+        conn: &Connection,
+        destination: &str,
+        path: &str,
+    ) -> anyhow::Result<Self> {
+        let proxy = DBusMenuProxy::builder(conn)
+            .destination(destination.to_string())?
+            .path(path.to_string())?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+
+        // subscribe before fetching, so no update slips through
+        let layout_updates = proxy.receive_layout_updated().await?.map(|_| None);
+        let property_updates = proxy
+            .receive_items_properties_updated()
+            .await?
+            .map(|signal| {
+                signal
+                    .args()
+                    .ok()
+                    .map(|args| (args.updated_props().clone(), args.removed_props().clone()))
+            });
+        let root = Mutable::new(fetch(&proxy).await?);
+
+        let watch = tokio::spawn({
+            let root = root.clone();
+            let proxy = proxy.clone();
+            let mut updates = select(layout_updates, property_updates);
+            async move {
+                while let Some(update) = updates.next().await {
+                    match update {
+                        Some((updated, removed)) => {
+                            let mut root = root.lock_mut();
+                            for (id, properties) in &updated {
+                                if let Some(item) = root.find_mut(*id) {
+                                    for (name, value) in properties {
+                                        item.set(name, Some(value));
+                                    }
+                                }
+                            }
+                            for (id, names) in &removed {
+                                if let Some(item) = root.find_mut(*id) {
+                                    for name in names {
+                                        item.set(name, None);
+                                    }
+                                }
+                            }
+                        }
+                        None => match fetch(&proxy).await {
+                            Ok(layout) => root.set(layout),
+                            Err(err) => {
+                                debug!("menu went away: {err}");
+                                break;
+                            }
+                        },
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            root,
+            proxy,
+            _watch: Arc::new(Watch(watch.abort_handle())),
+        })
+    }
+
+    /// The root item, holding the entries of the menu as children.
+    pub fn get(&self) -> MenuItem {
+        self.root.get_cloned()
+    }
+
+    pub fn subscribe(&self) -> MutableSignalCloned<MenuItem> {
+        self.root.signal_cloned()
+    }
+
+    /// To call before showing the submenu `id`, or the menu itself for [`ROOT`], so the
+    /// app can fill it in.
+    pub async fn about_to_show(&self, id: i32) -> anyhow::Result<()> {
+        if self.proxy.about_to_show(id).await? {
+            self.root.set(fetch(&self.proxy).await?);
+        }
+        Ok(())
+    }
+
+    pub async fn opened(&self, id: i32) -> anyhow::Result<()> {
+        self.event(id, "opened").await
+    }
+
+    pub async fn closed(&self, id: i32) -> anyhow::Result<()> {
+        self.event(id, "closed").await
+    }
+
+    pub async fn clicked(&self, id: i32) -> anyhow::Result<()> {
+        self.event(id, "clicked").await
+    }
+
+    async fn event(&self, id: i32, event: &str) -> anyhow::Result<()> {
+        // the timestamp is meant for focus stealing prevention, which layer shells lack
+        self.proxy.event(id, event, &Value::I32(0), 0).await?;
+        Ok(())
+    }
+}
+
+async fn fetch(proxy: &DBusMenuProxy<'_>) -> zbus::Result<MenuItem> {
+    let (_revision, layout) = proxy.get_layout(ROOT, -1, &[]).await?;
+    Ok(MenuItem::from_layout(layout))
+}
@@ -0,0 +1,392 @@
+pub mod dbus;
+mod menu;
+mod watcher;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use futures_signals::signal::{Mutable, MutableSignalCloned};
+use futures_util::StreamExt;
+use futures_util::stream::select_all;
+use tokio::task::AbortHandle;
+use tracing::{debug, error, info, warn};
+use zbus::Connection;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::OwnedObjectPath;
+
+pub use self::menu::{Menu, MenuItem, ROOT, ToggleKind};
+
+use crate::lock;
+
+use self::dbus::{
+    Pixmap, StatusNotifierItemProxy, StatusNotifierItemRegisteredStream,
+    StatusNotifierItemUnregisteredStream, StatusNotifierWatcherProxy,
+};
+
+const DEFAULT_PATH: &str = "/StatusNotifierItem";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Status {
+    /// Nothing worth showing going on, hosts may hide the item.
+    Passive,
+    #[default]
+    Active,
+    NeedsAttention,
+}
+
+impl From<&str> for Status {
+    fn from(status: &str) -> Self {
+        match status {
+            "Passive" => Status::Passive,
+            "NeedsAttention" => Status::NeedsAttention,
+            _ => Status::Active,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Vertical,
+    Horizontal,
+}
+
+impl Orientation {
+    fn as_str(self) -> &'static str {
+        match self {
+            Orientation::Vertical => "vertical",
+            Orientation::Horizontal => "horizontal",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconPixmap {
+    pub width: u32,
+    pub height: u32,
+    /// ARGB32 pixels, in network byte order.
+    pub data: Vec<u8>,
+}
+
+impl IconPixmap {
+    fn from_raw((width, height, data): Pixmap) -> Option<Self> {
+        let width = u32::try_from(width).ok().filter(|width| *width > 0)?;
+        let height = u32::try_from(height).ok().filter(|height| *height > 0)?;
+        if data.len() != width as usize * height as usize * 4 {
+            return None;
+        }
+
+        Some(Self {
+            width,
+            height,
+            data,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolTip {
+    pub title: String,
+    /// May contain a subset of HTML markup.
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrayItem {
+    /// Bus name and object path of the item, e.g. `:1.42/StatusNotifierItem`.
+    pub id: String,
+    pub title: String,
+    pub status: Status,
+    pub icon_name: Option<String>,
+    pub icon_pixmaps: Vec<IconPixmap>,
+    pub attention_icon_name: Option<String>,
+    pub attention_icon_pixmaps: Vec<IconPixmap>,
+    /// A directory the item ships its icons in, to look up the icon names in first.
+    pub icon_theme_path: Option<String>,
+    pub tooltip: Option<ToolTip>,
+    /// Whether the item only has a menu to show, even when activated.
+    pub item_is_menu: bool,
+    /// Path of the item's `com.canonical.dbusmenu` object.
+    pub menu: Option<OwnedObjectPath>,
+}
+
+impl TrayItem {
+    /// Reads the properties of the item. Besides `Id`, items are free to leave them out.
+    async fn load(id: &str, proxy: &StatusNotifierItemProxy<'_>) -> zbus::Result<Self> {
+        let item_id = proxy.id().await?;
+
+        let non_empty = |value: zbus::Result<String>| value.ok().filter(|value| !value.is_empty());
+        let pixmaps = |value: zbus::Result<Vec<Pixmap>>| {
+            value
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(IconPixmap::from_raw)
+                .collect()
+        };
+
+        Ok(Self {
+            id: id.to_string(),
+            title: non_empty(proxy.title().await).unwrap_or(item_id),
+            status: proxy
+                .status()
+                .await
+                .map(|status| Status::from(status.as_str()))
+                .unwrap_or_default(),
+            icon_name: non_empty(proxy.icon_name().await),
+            icon_pixmaps: pixmaps(proxy.icon_pixmap().await),
+            attention_icon_name: non_empty(proxy.attention_icon_name().await),
+            attention_icon_pixmaps: pixmaps(proxy.attention_icon_pixmap().await),
+            icon_theme_path: non_empty(proxy.icon_theme_path().await),
+            tooltip: proxy
+                .tool_tip()
+                .await
+                .ok()
+                .map(|(_, _, title, description)| ToolTip { title, description })
+                .filter(|tooltip| !tooltip.title.is_empty() || !tooltip.description.is_empty()),
+            item_is_menu: proxy.item_is_menu().await.unwrap_or_default(),
+            menu: proxy.menu().await.ok().filter(|menu| menu.as_str() != "/"),
+        })
+    }
+}
+
+/// Hosts the system tray: serves `org.kde.StatusNotifierWatcher` unless another program
+/// does, and tracks the items registered with it.
+#[derive(Debug, Clone)]
+pub struct Subscriber {
+    items: Mutable<Vec<TrayItem>>,
+    /// The tasks following the items, by id.
+    watches: Arc<Mutex<HashMap<String, AbortHandle>>>,
+    conn: Connection,
+}
+
+impl Subscriber {
+    pub async fn new() -> anyhow::Result<Self> {
+        let conn = Connection::session().await?;
+        watcher::start(&conn).await?;
+
+        let watcher = StatusNotifierWatcherProxy::builder(&conn)
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+        // subscribe before registering, so no item slips through
+        let registered = watcher.receive_status_notifier_item_registered().await?;
+        let unregistered = watcher.receive_status_notifier_item_unregistered().await?;
+
+        let host = format!("org.kde.StatusNotifierHost-{}", std::process::id());
+        conn.request_name(host.as_str()).await?;
+        watcher.register_status_notifier_host(&host).await?;
+        let ids = watcher.registered_status_notifier_items().await?;
+
+        let subscriber = Self {
+            items: Mutable::new(Vec::new()),
+            watches: Arc::new(Mutex::new(HashMap::new())),
+            conn,
+        };
+
+        let subscriber_for_task = subscriber.clone();
+        tokio::spawn(async move {
+            if let Err(e) = subscriber_for_task.run(ids, registered, unregistered).await {
+                error!("Error in tray subscriber: {:?}", e);
+            }
+        });
+
+        Ok(subscriber)
+    }
+
+    async fn run(
+        &self,
+        ids: Vec<String>,
+        registered: StatusNotifierItemRegisteredStream,
+        unregistered: StatusNotifierItemUnregisteredStream,
+    ) -> anyhow::Result<()> {
+        info!("Tray subscriber start");
+
+        for id in ids {
+            self.add(id);
+        }
+
+        let registered = registered
+            .filter_map(|signal| async move {
+                signal
+                    .args()
+                    .ok()
+                    .map(|args| (true, args.service().clone()))
+            })
+            .boxed();
+        let unregistered = unregistered
+            .filter_map(|signal| async move {
+                signal
+                    .args()
+                    .ok()
+                    .map(|args| (false, args.service().clone()))
+            })
+            .boxed();
+
+        let mut events = select_all(vec![registered, unregistered]);
+        while let Some((registered, id)) = events.next().await {
+            if registered {
+                self.add(id);
+            } else {
+                self.remove(&id);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add(&self, id: String) {
+        // held until the watch is in, so that it can't end before
+        let mut watches = lock!(self.watches);
+        if watches.contains_key(&id) {
+            return;
+        }
+
+        debug!("tray item added: {id}");
+        let subscriber = self.clone();
+        let item_id = id.clone();
+        let watch = tokio::spawn(async move {
+            if let Err(err) = subscriber.watch(&item_id).await {
+                warn!("failed to watch tray item {item_id}: {err}");
+                subscriber
+                    .items
+                    .lock_mut()
+                    .retain(|item| item.id != item_id);
+            }
+
+            // so that the item is watched again if it registers again, unless that's
+            // already the case
+            let mut watches = lock!(subscriber.watches);
+            if watches
+                .get(&item_id)
+                .is_some_and(|watch| watch.id() == tokio::task::id())
+            {
+                watches.remove(&item_id);
+            }
+        });
+        watches.insert(id, watch.abort_handle());
+    }
+
+    fn remove(&self, id: &str) {
+        debug!("tray item removed: {id}");
+        if let Some(watch) = lock!(self.watches).remove(id) {
+            watch.abort();
+        }
+        self.items.lock_mut().retain(|item| item.id != id);
+    }
+
+    /// Keeps the item up to date until it is removed.
+    async fn watch(&self, id: &str) -> zbus::Result<()> {
+        let proxy = self.item(id).await?;
+
+        let changes = vec![
+            proxy.receive_new_title().await?.map(|_| ()).boxed(),
+            proxy.receive_new_icon().await?.map(|_| ()).boxed(),
+            proxy
+                .receive_new_attention_icon()
+                .await?
+                .map(|_| ())
+                .boxed(),
+            proxy
+                .receive_new_icon_theme_path()
+                .await?
+                .map(|_| ())
+                .boxed(),
+            proxy.receive_new_tool_tip().await?.map(|_| ()).boxed(),
+            proxy.receive_new_status().await?.map(|_| ()).boxed(),
+        ];
+        let mut changes = select_all(changes);
+
+        self.update(TrayItem::load(id, &proxy).await?);
+        while changes.next().await.is_some() {
+            self.update(TrayItem::load(id, &proxy).await?);
+        }
+
+        Ok(())
+    }
+
+    fn update(&self, item: TrayItem) {
+        if self.items.lock_ref().contains(&item) {
+            return;
+        }
+
+        let mut items = self.items.lock_mut();
+        match items.iter_mut().find(|existing| existing.id == item.id) {
+            Some(existing) => *existing = item,
+            None => items.push(item),
+        }
+    }
+
+    async fn item(&self, id: &str) -> zbus::Result<StatusNotifierItemProxy<'static>> {
+        let (destination, path) = split_id(id);
+        StatusNotifierItemProxy::builder(&self.conn)
+            .destination(destination.to_string())?
+            .path(path.to_string())?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+    }
+
+    /// Activates the item, as on a left click at `x`, `y` on the screen.
+    pub async fn activate(&self, id: &str, x: i32, y: i32) -> anyhow::Result<()> {
+        self.item(id).await?.activate(x, y).await?;
+        Ok(())
+    }
+
+    /// The secondary action of the item, usually bound to a middle click.
+    pub async fn secondary_activate(&self, id: &str, x: i32, y: i32) -> anyhow::Result<()> {
+        self.item(id).await?.secondary_activate(x, y).await?;
+        Ok(())
+    }
+
+    /// Asks the item to show its own context menu.
+    pub async fn context_menu(&self, id: &str, x: i32, y: i32) -> anyhow::Result<()> {
+        self.item(id).await?.context_menu(x, y).await?;
+        Ok(())
+    }
+
+    /// The `com.canonical.dbusmenu` menu of the item, if it exports one.
+    pub async fn menu(&self, id: &str) -> anyhow::Result<Option<Menu>> {
+        let path = self
+            .items
+            .lock_ref()
+            .iter()
+            .find(|item| item.id == id)
+            .and_then(|item| item.menu.clone());
+        let Some(path) = path else {
+            return Ok(None);
+        };
+
+        let (destination, _) = split_id(id);
+        Ok(Some(
+            Menu::new(&self.conn, destination, path.as_str()).await?,
+        ))
+    }
+
+    pub async fn scroll(
+        &self,
+        id: &str,
+        delta: i32,
+        orientation: Orientation,
+    ) -> anyhow::Result<()> {
+        self.item(id)
+            .await?
+            .scroll(delta, orientation.as_str())
+            .await?;
+        Ok(())
+    }
+
+    pub fn get(&self) -> Vec<TrayItem> {
+        self.items.get_cloned()
+    }
+
+    pub fn subscribe(&self) -> MutableSignalCloned<Vec<TrayItem>> {
+        self.items.signal_cloned()
+    }
+}
+
+/// Splits an item id into its bus name and object path.
+fn split_id(id: &str) -> (&str, &str) {
+    match id.find('/') {
+        Some(index) => id.split_at(index),
+        None => (id, DEFAULT_PATH),
+    }
+}
@@ -0,0 +1,180 @@
+//! Our `org.kde.StatusNotifierWatcher`, for sessions where no other program hosts one.
+
+use futures_util::StreamExt;
+use tracing::{debug, error, info};
+use zbus::fdo::{self, DBusProxy};
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::{Connection, interface};
+
+const NAME: &str = "org.kde.StatusNotifierWatcher";
+const PATH: &str = "/StatusNotifierWatcher";
+
+#[derive(Default)]
+struct Watcher {
+    /// Bus name and object path of each item, e.g. `:1.42/StatusNotifierItem`.
+    items: Vec<String>,
+    /// Bus names of the hosts.
+    hosts: Vec<String>,
+}
+
+#[interface(name = "org.kde.StatusNotifierWatcher")]
+impl Watcher {
+    /// `service` is either the bus name of the item, serving it at `/StatusNotifierItem`,
+    /// or the path the sender serves it at, as libappindicator does.
+    async fn register_status_notifier_item(
+        &mut self,
+        service: &str,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        let item = if service.starts_with('/') {
+            let sender = header
+                .sender()
+                .ok_or_else(|| fdo::Error::InvalidArgs("unknown sender".to_string()))?;
+            format!("{sender}{service}")
+        } else {
+            format!("{service}/StatusNotifierItem")
+        };
+        if self.items.contains(&item) {
+            return Ok(());
+        }
+
+        debug!("registered tray item {item}");
+        self.items.push(item.clone());
+        Self::status_notifier_item_registered(&emitter, &item).await?;
+        self.registered_status_notifier_items_changed(&emitter)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn register_status_notifier_host(
+        &mut self,
+        service: &str,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        // hosts name themselves after their well-known name, but only their unique name
+        // shows up when they leave the bus
+        let host = match header.sender() {
+            Some(sender) => sender.to_string(),
+            None => service.to_string(),
+        };
+        if self.hosts.contains(&host) {
+            return Ok(());
+        }
+
+        self.hosts.push(host);
+        Self::status_notifier_host_registered(&emitter).await?;
+        if self.hosts.len() == 1 {
+            self.is_status_notifier_host_registered_changed(&emitter)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    #[zbus(property)]
+    async fn registered_status_notifier_items(&self) -> Vec<String> {
+        self.items.clone()
+    }
+
+    #[zbus(property)]
+    async fn is_status_notifier_host_registered(&self) -> bool {
+        !self.hosts.is_empty()
+    }
+
+    #[zbus(property)]
+    async fn protocol_version(&self) -> i32 {
+        0
+    }
+
+    #[zbus(signal)]
+    async fn status_notifier_item_registered(
+        emitter: &SignalEmitter<'_>,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_item_unregistered(
+        emitter: &SignalEmitter<'_>,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_host_registered(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_host_unregistered(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+}
+
+/// Serves the watcher on `conn`, unless another program already does.
+///
+/// Returns whether we are the watcher.
+pub async fn start(conn: &Connection) -> anyhow::Result<bool> {
+    conn.object_server().at(PATH, Watcher::default()).await?;
+
+    let dbus = DBusProxy::new(conn).await?;
+    let reply = dbus
+        .request_name(NAME.try_into()?, fdo::RequestNameFlags::DoNotQueue.into())
+        .await?;
+    if reply != fdo::RequestNameReply::PrimaryOwner {
+        conn.object_server().remove::<Watcher, _>(PATH).await?;
+        info!("using the running {NAME}");
+        return Ok(false);
+    }
+    info!("hosting {NAME}");
+
+    let mut owner_changes = dbus.receive_name_owner_changed().await?;
+    let conn = conn.clone();
+    tokio::spawn(async move {
+        while let Some(signal) = owner_changes.next().await {
+            let Ok(args) = signal.args() else {
+                continue;
+            };
+            if args.new_owner().is_some() {
+                continue;
+            }
+            if let Err(err) = remove_owner(&conn, args.name().as_str()).await {
+                error!("failed to unregister tray items of {}: {err}", args.name());
+            }
+        }
+    });
+
+    Ok(true)
+}
+
+/// Forgets the items and hosts of `name`, which left the bus.
+async fn remove_owner(conn: &Connection, name: &str) -> zbus::Result<()> {
+    let iface = conn.object_server().interface::<_, Watcher>(PATH).await?;
+    let emitter = iface.signal_emitter();
+    let mut watcher = iface.get_mut().await;
+
+    let prefix = format!("{name}/");
+    let (removed, items): (Vec<_>, Vec<_>) = std::mem::take(&mut watcher.items)
+        .into_iter()
+        .partition(|item| item.starts_with(&prefix));
+    watcher.items = items;
+    for item in &removed {
+        debug!("unregistered tray item {item}");
+        Watcher::status_notifier_item_unregistered(emitter, item).await?;
+    }
+    if !removed.is_empty() {
+        watcher
+            .registered_status_notifier_items_changed(emitter)
+            .await?;
+    }
+
+    if let Some(index) = watcher.hosts.iter().position(|host| host == name) {
+        watcher.hosts.remove(index);
+        Watcher::status_notifier_host_unregistered(emitter).await?;
+        if watcher.hosts.is_empty() {
+            watcher
+                .is_status_notifier_host_registered_changed(emitter)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
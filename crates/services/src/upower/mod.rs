@@ -129,6 +129,10 @@ impl Subscriber {
         Ok(())
     }
 
+    pub fn get(&self) -> UpowerData {
+        self.data.get_cloned()
+    }
+
     pub fn subscribe(&self) -> MutableSignalCloned<UpowerData> {
         self.data.signal_cloned()
     }
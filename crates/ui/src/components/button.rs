@@ -3,12 +3,14 @@ use std::sync::Arc;
 use gpui::prelude::*;
 use gpui::*;
 
+use crate::ActiveTheme;
+
 type ButtonCallback = dyn Fn(MouseDownEvent, &mut Window, &mut App) + 'static;
 
 #[derive(IntoElement)]
 pub struct Button {
     size: Option<f32>,
-    rounded: f32,
+    rounded: Option<f32>,
     border: f32,
     bg_color: Option<Rgba>,
     hover_bg: Option<Rgba>,
@@ -25,11 +27,11 @@ impl Button {
         Self {
             label: SharedString::from("Button"),
             size: None,
-            rounded: 4.0,
+            rounded: None,
             border: 0.0,
             bg_color: None,
             border_color: None,
-            hover_bg: Some(rgb(0xe1dede)),
+            hover_bg: None,
             on_click: Arc::new(|_, _, _| println!("Clicked!")),
         }
     }
@@ -60,7 +62,7 @@ impl Button {
     }
 
     pub fn rounded(mut self, rounded: f32) -> Self {
-        self.rounded = rounded;
+        self.rounded = Some(rounded);
         self
     }
 
@@ -85,18 +87,18 @@ impl Default for Button {
 }
 
 impl RenderOnce for Button {
-    fn render(self, _window: &mut Window, _cx: &mut App) -> impl IntoElement {
+    fn render(self, _window: &mut Window, cx: &mut App) -> impl IntoElement {
+        let theme = cx.theme();
         let on_click = self.on_click.clone();
+        let hover_bg = self.hover_bg.unwrap_or(theme.palette.hover);
         div()
             .flex()
             .border(px(self.border))
-            .rounded(px(self.rounded))
+            .rounded(self.rounded.map(px).unwrap_or(theme.radius))
             .child(self.label.clone())
             .when_some(self.size, |this, size| this.size(px(size)).px(px(5.)))
             .when_none(&self.size, |this| this.w_auto())
-            .when_some(self.hover_bg, |this, color| {
-                this.hover(|this| this.bg(color))
-            })
+            .hover(|this| this.bg(hover_bg))
             .when_some(self.border_color, |this, color| this.border_color(color))
             .on_mouse_down(MouseButton::Left, move |event, win, cx| {
                 (on_click)(event.clone(), win, cx);
@@ -1,11 +1,13 @@
 use gpui::*;
 use prelude::FluentBuilder;
 
+use crate::ActiveTheme;
+
 #[derive(IntoElement)]
 pub struct Icon {
     icon_path: SharedString,
     size: f32,
-    color: Rgba,
+    color: Option<Rgba>,
     hover: Option<Rgba>,
 }
 
@@ -14,7 +16,7 @@ impl Icon {
         Icon {
             icon_path,
             size: 22.0,
-            color: rgb(0x000000),
+            color: None,
             hover: None,
         }
     }
@@ -25,7 +27,7 @@ impl Icon {
     }
 
     pub fn color(mut self, color: Rgba) -> Self {
-        self.color = color;
+        self.color = Some(color);
         self
     }
 
@@ -36,13 +38,14 @@ impl Icon {
 }
 
 impl RenderOnce for Icon {
-    fn render(self, _: &mut Window, _: &mut App) -> impl IntoElement {
+    fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
+        let color = self.color.unwrap_or(cx.theme().palette.text);
         svg()
             .flex()
             .items_center()
             .justify_center()
             .size(px(self.size))
-            .text_color(self.color)
+            .text_color(color)
             .when_some(self.hover, |this, color| {
                 this.hover(|this| this.text_color(color))
             })
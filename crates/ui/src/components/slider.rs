@@ -1,6 +1,7 @@
 use gpui::*;
 
 use super::h_flex;
+use crate::ActiveTheme;
 
 #[derive(Clone, Render)]
 pub struct Thumb(EntityId);
@@ -16,9 +17,10 @@ pub struct Slider {
     value: f32,
     percentage: f32,
     bounds: Bounds<Pixels>,
-    bg: Rgba,
-    fill: Rgba,
-    thumb_bg: Rgba,
+    /// Colors default to the theme when unset.
+    bg: Option<Rgba>,
+    fill: Option<Rgba>,
+    thumb_bg: Option<Rgba>,
 }
 
 impl EventEmitter<SliderEvent> for Slider {}
@@ -32,9 +34,9 @@ impl Default for Slider {
             value: 0.0,
             percentage: 0.0,
             bounds: Bounds::default(),
-            bg: rgb(0x1e1e2d),
-            fill: rgb(0xcba6f7),
-            thumb_bg: rgb(0xcba6f7),
+            bg: None,
+            fill: None,
+            thumb_bg: None,
         }
     }
 }
@@ -61,6 +63,21 @@ impl Slider {
         self
     }
 
+    pub fn bg(mut self, color: Rgba) -> Self {
+        self.bg = Some(color);
+        self
+    }
+
+    pub fn fill(mut self, color: Rgba) -> Self {
+        self.fill = Some(color);
+        self
+    }
+
+    pub fn thumb_bg(mut self, color: Rgba) -> Self {
+        self.thumb_bg = Some(color);
+        self
+    }
+
     fn update_thumb_pos(&mut self) {
         self.percentage = self.value.clamp(self.min, self.max) / self.max;
     }
@@ -148,12 +165,14 @@ impl Slider {
             .border_1()
             .rounded_full()
             .shadow_md()
-            .bg(self.thumb_bg)
+            .bg(self.thumb_bg.unwrap_or(cx.theme().palette.accent))
     }
 }
 
 impl Render for Slider {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let bg = self.bg.unwrap_or(cx.theme().palette.track);
+        let fill = self.fill.unwrap_or(cx.theme().palette.accent);
         let thumb_bar_size = if self.percentage < 0.1 {
             0.1 * self.bounds.size.width
         } else {
@@ -172,8 +191,8 @@ impl Render for Slider {
                         .relative()
                         .w_full()
                         .h_2()
-                        .bg(self.bg)
-                        .active(|this| this.bg(self.fill))
+                        .bg(bg)
+                        .active(|this| this.bg(fill))
                         .rounded(px(4.))
                         .child(
                             div()
@@ -181,7 +200,7 @@ impl Render for Slider {
                                 .left_0()
                                 .h_full()
                                 .w(thumb_bar_size)
-                                .bg(self.fill)
+                                .bg(fill)
                                 .rounded_full(),
                         )
                         .child(self.render_thumb(thumb_bar_size, window, cx))
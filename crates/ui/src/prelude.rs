@@ -4,8 +4,9 @@ pub use gpui::{
     Element, ElementId, Entity, EventEmitter, InteractiveElement, KeyboardInteractivity, Layer,
     LayerShellSettings, MouseButton, MouseDownEvent, MouseEvent, ParentElement, Pixels, Rems,
     RenderOnce, Rgba, SharedString, Styled, Timer, WeakEntity, Window, WindowBounds, WindowKind,
-    WindowOptions, div, point, px, relative, rems, rgb, size,
+    WindowOptions, div, point, px, relative, rems, rgb, rgba, size,
 };
 
 pub use crate::components::*;
+pub use crate::theme::{ActiveTheme, Theme};
 pub use crate::traits::styled_ext::*;
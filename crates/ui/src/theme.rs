@@ -0,0 +1,132 @@
+use gpui::{App, FontWeight, Global, Pixels, Rgba, SharedString, px, rgb};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    /// Background of the bar and popups.
+    pub background: Rgba,
+    /// Background of elements drawn on top of the background, like list rows.
+    pub surface: Rgba,
+    pub text: Rgba,
+    /// Secondary text, like descriptions and disabled items.
+    pub subtext: Rgba,
+    pub hover: Rgba,
+    pub border: Rgba,
+    /// Highlights the active element, like the focused workspace or a slider fill.
+    pub accent: Rgba,
+    /// Background of slider and progress tracks.
+    pub track: Rgba,
+    pub warning: Rgba,
+    pub error: Rgba,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: SharedString,
+    pub appearance: Appearance,
+    pub palette: Palette,
+    pub font_family: SharedString,
+    pub font_weight: FontWeight,
+    /// Gap between widgets.
+    pub spacing: Pixels,
+    pub radius: Pixels,
+}
+
+impl Global for Theme {}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::latte()
+    }
+}
+
+impl Theme {
+    /// Catppuccin Latte.
+    pub fn latte() -> Self {
+        Self::new(
+            "latte",
+            Appearance::Light,
+            Palette {
+                background: rgb(0xeff1f5),
+                surface: rgb(0xe6e9ef),
+                text: rgb(0x4c4f69),
+                subtext: rgb(0x6c6f85),
+                hover: rgb(0xccd0da),
+                border: rgb(0xbcc0cc),
+                accent: rgb(0xea76cb),
+                track: rgb(0xbcc0cc),
+                warning: rgb(0xfe640b),
+                error: rgb(0xd20f39),
+            },
+        )
+    }
+
+    /// Catppuccin Mocha.
+    pub fn mocha() -> Self {
+        Self::new(
+            "mocha",
+            Appearance::Dark,
+            Palette {
+                background: rgb(0x1e1e2e),
+                surface: rgb(0x181825),
+                text: rgb(0xcdd6f4),
+                subtext: rgb(0xa6adc8),
+                hover: rgb(0x313244),
+                border: rgb(0x45475a),
+                accent: rgb(0xcba6f7),
+                track: rgb(0x45475a),
+                warning: rgb(0xfab387),
+                error: rgb(0xf38ba8),
+            },
+        )
+    }
+
+    pub fn presets() -> [Self; 2] {
+        [Self::latte(), Self::mocha()]
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        Self::presets()
+            .into_iter()
+            .find(|theme| &*theme.name == name)
+    }
+
+    /// Sets the default theme, unless one is already set.
+    pub fn init(cx: &mut App) {
+        if !cx.has_global::<Self>() {
+            cx.set_global(Self::default());
+        }
+    }
+
+    pub fn global(cx: &App) -> &Self {
+        cx.global::<Self>()
+    }
+
+    fn new(name: &'static str, appearance: Appearance, palette: Palette) -> Self {
+        Self {
+            name: name.into(),
+            appearance,
+            palette,
+            font_family: "MonoLisa".into(),
+            font_weight: FontWeight::EXTRA_BOLD,
+            spacing: px(4.),
+            radius: px(4.),
+        }
+    }
+}
+
+/// Access to the current [`Theme`].
+pub trait ActiveTheme {
+    fn theme(&self) -> &Theme;
+}
+
+impl ActiveTheme for App {
+    fn theme(&self) -> &Theme {
+        Theme::global(self)
+    }
+}
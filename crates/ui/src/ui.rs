@@ -1,6 +1,8 @@
 mod components;
 pub mod prelude;
+mod theme;
 mod traits;
 
 pub use components::*;
 pub use prelude::*;
+pub use theme::*;